@@ -0,0 +1,28 @@
+use std::{env, process::Command};
+
+/// Build details shown by `tms --version --verbose`
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_owned())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=TMS_GIT_COMMIT={commit}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    let target = env::var("TARGET").unwrap_or_default();
+    println!("cargo:rustc-env=TMS_TARGET={target}");
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=TMS_FEATURES={}", features.join(","));
+}
@@ -0,0 +1,180 @@
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{
+    cli::{Context, TmsCommand},
+    configs::Config,
+    error::{Result, TmsError},
+    session::{create_sessions, SessionContainer},
+    tmux::Tmux,
+};
+
+#[derive(Debug, Args)]
+pub struct RunCommand {
+    /// File with one operation per line. Available operations are `open-session <name>`,
+    /// `new-session <name> [path]`, `new-window <name> [path]`, `select-window <window>`,
+    /// `send <keys>` and `switch [session]`
+    file: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Operation {
+    OpenSession(String),
+    NewSession(String, Option<String>),
+    NewWindow(String, Option<String>),
+    SelectWindow(String),
+    Send(String),
+    Switch(Option<String>),
+}
+
+impl TmsCommand for RunCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        run_command(self, &ctx.take_config()?, ctx.tmux)
+    }
+}
+
+pub fn run_command(args: &RunCommand, config: &Config, tmux: &Tmux) -> Result<()> {
+    let contents = fs::read_to_string(&args.file)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not read {}", args.file.display()))?;
+    let operations = parse(&contents)?;
+
+    let sessions = if operations
+        .iter()
+        .any(|operation| matches!(operation, Operation::OpenSession(_)))
+    {
+        Some(create_sessions(config)?)
+    } else {
+        None
+    };
+
+    // The session that windows are created in and keys are sent to
+    let mut target: Option<String> = None;
+
+    for operation in operations {
+        match operation {
+            Operation::OpenSession(name) => {
+                let session = sessions
+                    .as_ref()
+                    .and_then(|sessions| sessions.find_session(&name))
+                    .ok_or(TmsError::SessionNotFound(name))?;
+                target = Some(session.create(tmux, config)?);
+            }
+            Operation::NewSession(name, path) => {
+                if !tmux.session_exists(&name) {
+                    tmux.new_session(Some(&name), path.as_deref());
+                }
+                target = Some(name);
+            }
+            Operation::NewWindow(name, path) => {
+                tmux.new_window(Some(&name), path.as_deref(), target.as_deref());
+            }
+            Operation::SelectWindow(window) => {
+                let window = match &target {
+                    Some(session) => format!("{session}:{window}"),
+                    None => window,
+                };
+                tmux.select_window(&window);
+            }
+            Operation::Send(keys) => {
+                tmux.send_keys(&keys, target.as_deref());
+            }
+            Operation::Switch(name) => {
+                if let Some(name) = name.or(target.take()) {
+                    tmux.switch_to_session(&name)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn parse(contents: &str) -> Result<Vec<Operation>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            parse_line(line)
+                .map_err(|message| {
+                    error_stack::Report::new(TmsError::ScriptError)
+                        .attach_printable(format!("line {}: {message}", index + 1))
+                })
+                .transpose()
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> std::result::Result<Option<Operation>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (operation, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let words = shell_words::split(rest).map_err(|e| e.to_string())?;
+
+    let operation = match (operation, words.as_slice()) {
+        ("open-session", [name]) => Operation::OpenSession(name.to_owned()),
+        ("new-session", [name]) => Operation::NewSession(name.to_owned(), None),
+        ("new-session", [name, path]) => {
+            Operation::NewSession(name.to_owned(), Some(expand(path)?))
+        }
+        ("new-window", [name]) => Operation::NewWindow(name.to_owned(), None),
+        ("new-window", [name, path]) => Operation::NewWindow(name.to_owned(), Some(expand(path)?)),
+        ("select-window", [window]) => Operation::SelectWindow(window.to_owned()),
+        ("send", _) if !rest.is_empty() => Operation::Send(rest.to_owned()),
+        ("switch", []) => Operation::Switch(None),
+        ("switch", [name]) => Operation::Switch(Some(name.to_owned())),
+        (
+            "open-session" | "new-session" | "new-window" | "select-window" | "send" | "switch",
+            _,
+        ) => return Err(format!("wrong number of arguments for `{operation}`")),
+        _ => return Err(format!("unknown operation `{operation}`")),
+    };
+
+    Ok(Some(operation))
+}
+
+fn expand(path: &str) -> std::result::Result<String, String> {
+    shellexpand::full(path)
+        .map(|path| path.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_operations() {
+        let script = r#"
+            # bootstrap the dotfiles workspace
+            open-session dotfiles
+            new-window "edit config" /tmp
+            send nvim -c 'set ft=toml' config.toml
+
+            switch
+        "#;
+
+        assert_eq!(
+            parse(script).unwrap(),
+            vec![
+                Operation::OpenSession("dotfiles".into()),
+                Operation::NewWindow("edit config".into(), Some("/tmp".into())),
+                Operation::Send("nvim -c 'set ft=toml' config.toml".into()),
+                Operation::Switch(None),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        assert!(parse_line("open-session").is_err());
+        assert!(parse_line("new-window a b c").is_err());
+        assert!(parse_line("detach").is_err());
+    }
+}
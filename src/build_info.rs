@@ -0,0 +1,26 @@
+//! Details about how tms was built, set by the build script
+
+use crate::tmux::Tmux;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_COMMIT: &str = env!("TMS_GIT_COMMIT");
+const TARGET: &str = env!("TMS_TARGET");
+const FEATURES: &str = env!("TMS_FEATURES");
+
+/// The version of tms, followed by the build details and the tmux version in use when `verbose` to
+/// include in bug reports
+pub fn version(verbose: bool, tmux: &Tmux) -> String {
+    let mut version = format!("tms {VERSION}");
+    if verbose {
+        let features = if FEATURES.is_empty() {
+            "none"
+        } else {
+            FEATURES
+        };
+        let tmux_version = tmux.version().unwrap_or_else(|| String::from("not found"));
+        version.push_str(&format!(
+            "\ncommit: {GIT_COMMIT}\ntarget: {TARGET}\nfeatures: {features}\ntmux: {tmux_version}"
+        ));
+    }
+    version
+}
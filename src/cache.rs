@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use error_stack::ResultExt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{files::write_atomic, Result, TmsError};
+
+/// Results of previous repository scans, persisted between runs so directories that are known
+/// not to be repositories don't have to be opened again as long as they are unchanged, and the
+/// repositories that were found can be listed before scanning again
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct ScanCache {
+    #[serde(default)]
+    non_repos: HashMap<String, u64>,
+    #[serde(default)]
+    repos: Vec<PathBuf>,
+    #[serde(skip)]
+    seen: HashMap<String, u64>,
+    #[serde(skip)]
+    found: Vec<PathBuf>,
+}
+
+impl ScanCache {
+    /// Load the cache from disk, starting with an empty cache if it is missing or unreadable
+    pub fn load() -> Self {
+        cache_file()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `path` was not a repository the last time it was scanned and its modification
+    /// time hasn't changed since
+    pub fn is_non_repo(&mut self, path: &Path, mtime: u64) -> bool {
+        let Some(key) = path.to_str() else {
+            return false;
+        };
+        if self.non_repos.get(key) == Some(&mtime) {
+            self.seen.insert(key.to_string(), mtime);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn insert_non_repo(&mut self, path: &Path, mtime: u64) {
+        if let Some(key) = path.to_str() {
+            self.seen.insert(key.to_string(), mtime);
+        }
+    }
+
+    pub fn insert_repo(&mut self, path: &Path) {
+        self.found.push(path.to_path_buf());
+    }
+
+    /// The repositories found by the last complete scan, git and Jujutsu ones alike
+    pub fn repos(&self) -> &[PathBuf] {
+        &self.repos
+    }
+
+    /// Write the entries seen during this scan, dropping everything that wasn't visited
+    pub fn save(self) -> Result<()> {
+        let path = cache_file()
+            .ok_or(TmsError::IoError)
+            .attach_printable("Could not find a valid location for the cache file")?;
+        let cache = ScanCache {
+            non_repos: self.seen,
+            repos: self.found,
+            ..Default::default()
+        };
+        let contents = toml::to_string(&cache).change_context(TmsError::IoError)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).change_context(TmsError::IoError)?;
+        }
+        write_atomic(&path, contents).change_context(TmsError::IoError)
+    }
+}
+
+/// Modification time of a directory in nanoseconds, `None` for anything that isn't a directory
+pub fn dir_mtime(path: &Path) -> Option<u64> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return None;
+    }
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(modified.as_nanos()).ok()
+}
+
+fn cache_file() -> Option<PathBuf> {
+    dirs::cache_dir().map(|path| path.join("tms/scan_cache.toml"))
+}
@@ -0,0 +1,127 @@
+use std::{
+    env::current_dir,
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{configs::Config, dirty_paths::DirtyUtf8Path, i18n::tr_fmt, Result, TmsError};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct BookmarkCommand {
+    #[arg(long, short)]
+    /// Delete instead of add a bookmark
+    delete: bool,
+    #[arg(long, short, value_name = "name")]
+    /// Add to or delete from the bookmarks of this profile's group
+    group: Option<String>,
+    #[arg(long, short, value_name = "file", conflicts_with = "path")]
+    /// Bookmark every directory listed in the file, one per line, or in stdin when it is `-`
+    from_file: Option<String>,
+    /// Path to bookmark, if left empty bookmark the current directory.
+    path: Option<String>,
+}
+
+impl TmsCommand for BookmarkCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        bookmark_command(self, ctx.stored_config()?)
+    }
+}
+
+fn bookmark_command(args: &BookmarkCommand, mut config: Config) -> Result<()> {
+    if let Some(file) = &args.from_file {
+        bookmark_list(
+            &read_list(file)?,
+            args.delete,
+            args.group.as_deref(),
+            &mut config,
+        );
+        return config.save().change_context(TmsError::ConfigError);
+    }
+
+    let path = if let Some(path) = &args.path {
+        path.to_owned()
+    } else {
+        current_dir()
+            .change_context(TmsError::IoError)?
+            .to_string()
+            .change_context(TmsError::IoError)?
+    };
+
+    let group = args.group.as_deref();
+    if !args.delete {
+        config.add_bookmark(path, group);
+    } else {
+        config.delete_bookmark(path, group);
+    }
+
+    config.save().change_context(TmsError::ConfigError)?;
+
+    Ok(())
+}
+
+fn read_list(file: &str) -> Result<String> {
+    let mut list = String::new();
+    if file == "-" {
+        io::stdin()
+            .read_to_string(&mut list)
+            .change_context(TmsError::IoError)
+            .attach_printable("Could not read the bookmarks from stdin")?;
+    } else {
+        list = fs::read_to_string(file)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not read the bookmarks from {file}"))?;
+    }
+    Ok(list)
+}
+
+/// Add or delete the directories in a list with one per line, skipping empty lines and `#`
+/// comments. Added directories must exist and aren't added again when they are already bookmarked
+/// under another spelling like `~/code` and `/home/me/code`
+fn bookmark_list(list: &str, delete: bool, group: Option<&str>, config: &mut Config) {
+    let mut changed = 0;
+    let mut skipped = 0;
+    for line in list.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if delete {
+            config.delete_bookmark(line.to_owned(), group);
+            changed += 1;
+            continue;
+        }
+        let Some(path) = existing_dir(line) else {
+            eprintln!("{}", tr_fmt("Skipping {}, it is not a directory", &[&line]));
+            skipped += 1;
+            continue;
+        };
+        if config.group_bookmark_paths(group).contains(&path) {
+            skipped += 1;
+            continue;
+        }
+        config.add_bookmark(line.to_owned(), group);
+        changed += 1;
+    }
+
+    if delete {
+        println!("{}", tr_fmt("Deleted {} bookmarks", &[&changed]));
+    } else {
+        println!(
+            "{}",
+            tr_fmt("Added {} bookmarks, skipped {}", &[&changed, &skipped])
+        );
+    }
+}
+
+fn existing_dir(path: &str) -> Option<PathBuf> {
+    let expanded = shellexpand::full(path).ok()?;
+    PathBuf::from(expanded.as_ref())
+        .canonicalize()
+        .ok()
+        .filter(|path| path.is_dir())
+}
@@ -0,0 +1,360 @@
+use std::{
+    env,
+    io::{stdin, BufRead},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use error_stack::ResultExt;
+
+use crate::{
+    clone::{git_clone, CloneProgress},
+    configs::{CloneRepoSwitchConfig, Config},
+    dirty_paths::DirtyUtf8Path,
+    forge::{is_search_term, Forge, ForgeRepo},
+    get_single_selection,
+    i18n::{tr, tr_fmt},
+    picker::{Picker, Preview},
+    session::{dir_name, project_name, Session, SessionType},
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{pick_search_path, Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct CloneRepoCommand {
+    /// Git repository to clone, or a name or `owner/repo` to search the configured forges for
+    repository: String,
+    #[arg(long)]
+    /// Clone in this terminal and wait for it, instead of in a new tmux window
+    wait: bool,
+    #[arg(long)]
+    /// Don't show the progress of the clone
+    quiet: bool,
+    #[arg(long, value_name = "open | rename | abort")]
+    /// What to do when the directory to clone into already exists, asked when not given
+    if_exists: Option<IfExists>,
+    #[arg(long, hide = true)]
+    /// Clone into this directory right away, used by the window the clone runs in
+    into: Option<PathBuf>,
+    #[arg(long, hide = true, requires = "into")]
+    /// The session the client was in when the clone was started
+    previous_session: Option<String>,
+    #[arg(long, hide = true, requires = "into")]
+    /// The client to switch to the new session
+    client: Option<String>,
+}
+
+/// What to do when the directory to clone into already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum IfExists {
+    /// Open the existing directory as a session instead of cloning
+    Open,
+    /// Clone into the directory's name followed by the first free number, like `repo-2`
+    Rename,
+    /// Stop without cloning
+    Abort,
+}
+
+impl TmsCommand for CloneRepoCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        clone_repo_command(self, ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn clone_repo_command(args: &CloneRepoCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    if let Some(path) = &args.into {
+        return clone_in_window(&args.repository, args, path, &config, tmux);
+    }
+
+    let repository = if is_search_term(&args.repository) {
+        match search_forges(&args.repository, &config, tmux)? {
+            Some(url) => url,
+            None => return Ok(()),
+        }
+    } else {
+        args.repository.clone()
+    };
+
+    let Some(mut path) = pick_search_path(&config, tmux)? else {
+        return Ok(());
+    };
+    path.push(repo_name(&repository));
+
+    if is_taken(&path) {
+        let if_exists = match args.if_exists {
+            Some(if_exists) => if_exists,
+            // Aborting from the picker is what was asked for, so it isn't an error
+            None => match ask_if_exists(&path, &config, tmux)? {
+                IfExists::Abort => return Ok(()),
+                if_exists => if_exists,
+            },
+        };
+        match if_exists {
+            IfExists::Open => return open_existing(&path, &config, tmux),
+            IfExists::Rename => path = free_path(&path),
+            IfExists::Abort => {
+                return Err(TmsError::IoError)
+                    .attach_printable(format!("{} already exists", path.display()))
+            }
+        }
+    }
+    let name = dir_name(&path)?;
+
+    let previous_session = tmux.current_session("#{session_name}");
+
+    // Outside of tmux there is no window to clone in and nothing to keep working in meanwhile
+    if args.wait || !tmux.in_session() {
+        if !args.quiet {
+            println!("{}", tr_fmt("Cloning into '{}'...", &[&name]));
+        }
+        return clone(
+            &repository,
+            &path,
+            &previous_session,
+            None,
+            args.quiet,
+            &config,
+            tmux,
+        );
+    }
+
+    let exe = env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_owned))
+        .unwrap_or_else(|| "tms".to_owned());
+    let client = tmux.display_message("#{client_tty}");
+    let path = path.to_string()?;
+    let mut command = vec![
+        exe.as_str(),
+        "clone-repo",
+        "--into",
+        &path,
+        "--previous-session",
+        previous_session.trim(),
+        "--client",
+        client.trim(),
+    ];
+    if args.quiet {
+        command.push("--quiet");
+    }
+    command.push(&repository);
+    let command = shell_words::join(command);
+    let command = format!(
+        "TMS_TMUX_SOCKET={} {command}",
+        shell_words::quote(tmux.socket_name())
+    );
+    tmux.new_background_window(&tr_fmt("clone {}", &[&name]), &command);
+    println!(
+        "{}",
+        tr_fmt("Cloning into '{}' in a new window...", &[&name])
+    );
+
+    Ok(())
+}
+
+/// Clone in the window opened for it, keeping the window open when the clone fails so the error
+/// can be read
+fn clone_in_window(
+    repository: &str,
+    args: &CloneRepoCommand,
+    path: &Path,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    if !args.quiet {
+        println!(
+            "{}",
+            tr_fmt("Cloning {} into '{}'...", &[&repository, &path.display()])
+        );
+    }
+    let previous_session = args.previous_session.as_deref().unwrap_or_default();
+    let result = clone(
+        repository,
+        path,
+        previous_session,
+        args.client.as_deref(),
+        args.quiet,
+        config,
+        tmux,
+    );
+    if let Err(error) = &result {
+        eprintln!("{error:?}");
+        println!("{}", tr("Press enter to close the window"));
+        stdin().lock().read_line(&mut String::new()).ok();
+    }
+    result
+}
+
+fn clone(
+    repository: &str,
+    path: &Path,
+    previous_session: &str,
+    client: Option<&str>,
+    quiet: bool,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    let repo = git_clone(repository, path, CloneProgress::detect(quiet))?;
+
+    let switch_config = config.clone_repo_switch.clone().unwrap_or_default();
+    let switch = match switch_config {
+        CloneRepoSwitchConfig::Always => true,
+        CloneRepoSwitchConfig::Never => false,
+        CloneRepoSwitchConfig::Foreground => {
+            let active_session = tmux.current_session("#{session_name}");
+            previous_session.trim() == active_session.trim()
+        }
+    };
+
+    let name = project_name(path)?;
+    let session_name = Session::new(name, SessionType::Git(repo)).create(tmux, config)?;
+
+    if switch {
+        match client {
+            Some(client) => {
+                tmux.switch_client_of(client, &session_name);
+            }
+            None => tmux.switch_to_session(&session_name)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Pick one of the repositories the configured forges find for the term, returning the URL to clone
+fn search_forges(term: &str, config: &Config, tmux: &Tmux) -> Result<Option<String>> {
+    let forges = config.forges.clone().unwrap_or_else(|| vec![Forge::Github]);
+    let mut repos = Vec::new();
+    for forge in forges {
+        repos.extend(forge.search(term)?);
+    }
+    if repos.is_empty() {
+        return Err(TmsError::IoError)
+            .attach_printable(format!("No repositories found for {term}"));
+    }
+
+    let items: Vec<String> = repos.iter().map(ForgeRepo::item).collect();
+    let details = repos
+        .iter()
+        .filter_map(|repo| Some((repo.item(), repo.description.clone()?)))
+        .collect();
+    let selected = Picker::new(&items, Preview::None, config.shortcuts.as_ref(), tmux)
+        .set_colors(config.picker_colors.as_ref())
+        .set_color_mode(config.color)
+        .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+        .set_item_details(details)
+        .run()?;
+
+    let protocol = config.clone_protocol.unwrap_or_default();
+    Ok(selected
+        .and_then(|selected| repos.into_iter().find(|repo| repo.item() == selected))
+        .map(|repo| repo.clone_url(protocol).to_owned()))
+}
+
+/// Whether cloning into the path would fail because something is already there
+fn is_taken(path: &Path) -> bool {
+    path.exists()
+        && !path
+            .read_dir()
+            .is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// The path with the first number appended to its name that isn't taken, like `repo-2`
+fn free_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (2..)
+        .map(|number| path.with_file_name(format!("{name}-{number}")))
+        .find(|path| !is_taken(path))
+        .expect("some number is free")
+}
+
+fn ask_if_exists(path: &Path, config: &Config, tmux: &Tmux) -> Result<IfExists> {
+    let choices = if_exists_choices(path);
+    let items: Vec<String> = choices.iter().map(|(item, _)| item.clone()).collect();
+    let selected = get_single_selection(&items, Preview::None, config, tmux)?;
+    let if_exists = selected
+        .and_then(|selected| choices.into_iter().find(|(item, _)| *item == selected))
+        .map_or(IfExists::Abort, |(_, if_exists)| if_exists);
+    Ok(if_exists)
+}
+
+/// The choices offered when the path is taken, in the order they are given to the picker. It
+/// starts on the first item and draws it at the bottom, so opening the existing directory is
+/// highlighted next to the prompt
+fn if_exists_choices(path: &Path) -> [(String, IfExists); 3] {
+    let free_name = free_path(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    [
+        (
+            tr_fmt("Open the existing '{}'", &[&path.display()]),
+            IfExists::Open,
+        ),
+        (tr_fmt("Clone into '{}'", &[&free_name]), IfExists::Rename),
+        (tr("Abort").to_owned(), IfExists::Abort),
+    ]
+}
+
+/// Open the directory that was in the way of the clone, named like it is in the picker
+fn open_existing(path: &Path, config: &Config, tmux: &Tmux) -> Result<()> {
+    let mut session = Session::from_path(path)?;
+    session.name = project_name(path)?;
+    session.switch_to(tmux, config)
+}
+
+/// The name of the directory the repository is cloned into
+fn repo_name(repository: &str) -> &str {
+    let (_, repo_name) = repository
+        .rsplit_once('/')
+        .expect("Repository path contains '/'");
+    repo_name.trim_end_matches(".git")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn only_directories_with_something_in_them_are_taken() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        assert!(!is_taken(&repo));
+        fs::create_dir(&repo).unwrap();
+        assert!(!is_taken(&repo));
+        fs::write(repo.join("README.md"), "").unwrap();
+        assert!(is_taken(&repo));
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(is_taken(&file));
+    }
+
+    #[test]
+    fn finds_the_first_free_numbered_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        assert_eq!(free_path(&repo), dir.path().join("repo-2"));
+        fs::create_dir_all(dir.path().join("repo-2/src")).unwrap();
+        fs::create_dir(dir.path().join("repo-3")).unwrap();
+        assert_eq!(free_path(&repo), dir.path().join("repo-3"));
+    }
+
+    #[test]
+    fn offers_to_open_first_then_clone_into_a_free_path_or_abort() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("repo-2/src")).unwrap();
+
+        let choices = if_exists_choices(&repo);
+        let actions: Vec<IfExists> = choices.iter().map(|(_, action)| *action).collect();
+        assert_eq!(actions, [IfExists::Open, IfExists::Rename, IfExists::Abort]);
+        assert!(choices[0].0.contains(&repo.display().to_string()));
+        assert!(choices[1].0.contains("'repo-3'"));
+    }
+}
@@ -0,0 +1,446 @@
+use std::fs::canonicalize;
+
+use clap::{Args, Subcommand};
+use error_stack::ResultExt;
+use ratatui::style::Color;
+use schemars::gen::SchemaSettings;
+
+use crate::{
+    columns::PickerColumn,
+    configs::{
+        parse_duration, CloneRepoSwitchConfig, ColorMode, Config, ConfigExport, SearchDirectory,
+        SessionSortOrderConfig,
+    },
+    forge::{CloneProtocol, Forge},
+    i18n::tr,
+    session::tmux_session_name,
+    sort::parse_sort_keys,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct ConfigCommand {
+    #[command(flatten)]
+    args: ConfigArgs,
+    #[command(subcommand)]
+    subcommand: Option<ConfigSubCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigSubCommand {
+    /// List current config including all default values
+    List(ConfigSubCommandArgs),
+    /// Print a JSON Schema of the config file, for editors to complete and validate it
+    Schema,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigSubCommandArgs {
+    #[arg(short, long)]
+    /// List only defaults without user set values
+    defaults: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[arg(short = 'p', long = "paths", value_name = "search paths", num_args = 1..)]
+    /// The paths to search through. Shell like expansions such as '~' are supported
+    search_paths: Option<Vec<String>>,
+    #[arg(short = 's', long = "session", value_name = "default session")]
+    /// The default session to switch to (if available) when killing another session
+    default_session: Option<String>,
+    #[arg(long = "excluded", value_name = "excluded dirs", num_args = 1..)]
+    /// As many directory names as desired to not be searched over
+    excluded_dirs: Option<Vec<String>>,
+    #[arg(long = "remove", value_name = "remove dir", num_args = 1..)]
+    /// As many directory names to be removed from exclusion list
+    remove_dir: Option<Vec<String>>,
+    #[arg(long = "full-path", value_name = "true | false")]
+    /// Use the full path when displaying directories
+    display_full_path: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Also show initialized submodules
+    search_submodules: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Search submodules for submodules
+    recursive_submodules: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    ///Only include sessions from search paths in the switcher
+    switch_filter_unknown: Option<bool>,
+    #[arg(long, short = 'd', value_name = "max depth", num_args = 1..)]
+    /// The maximum depth to traverse when searching for repositories in search paths, length
+    /// should match the number of search paths if specified (defaults to 10)
+    max_depths: Option<Vec<usize>>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Background color of the highlighted item in the picker
+    picker_highlight_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Text color of the hightlighted item in the picker
+    picker_highlight_text_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Color of the borders between widgets in the picker
+    picker_border_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Color of the item count in the picker
+    picker_info_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Color of the prompt in the picker
+    picker_prompt_color: Option<Color>,
+    #[arg(long, value_name = "#rrggbb")]
+    /// Color of the messages shown in the picker when something goes wrong
+    picker_error_color: Option<Color>,
+    #[arg(long, value_name = "Alphabetical | LastAttached | Frecency")]
+    /// Set the sort order of the sessions in the switch command, and with `Frecency` of the
+    /// projects in the picker
+    session_sort_order: Option<SessionSortOrderConfig>,
+    #[arg(long, value_name = "Always | Never | Foreground", verbatim_doc_comment)]
+    /// Whether to automatically switch to the new session after the `clone-repo` command finishes
+    /// `Always` will always switch tmux to the new session
+    /// `Never` will always create the new session in the background
+    /// When set to `Foreground`, the new session will only be opened in the background if the active
+    /// tmux session has changed since starting the clone process (for long clone processes on larger repos)
+    clone_repo_switch: Option<CloneRepoSwitchConfig>,
+    #[arg(long, value_name = "auto | always | never")]
+    /// Whether to use colors in the picker, `auto` disables them when NO_COLOR is set
+    color: Option<ColorMode>,
+    #[arg(long, value_name = "true | false")]
+    /// Only type into the picker's filter after entering search mode (with `/` by default)
+    explicit_search_mode: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Open the worktrees of bare repositories as windows when creating their session
+    worktree_windows: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Pick which worktrees to open as windows, remembering the choice for the next time
+    pick_worktrees: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Give every project a color picked from its name, shown in the picker and the status line
+    project_colors: Option<bool>,
+    #[arg(long, value_name = "path")]
+    /// Where `open-session --ref` adds worktrees, in a folder per repository
+    worktrees_dir: Option<String>,
+    #[arg(long, value_name = "path | url")]
+    /// A TOML file shared by a team with `search_dirs` and `excluded_dirs` to add to your own
+    team_config: Option<String>,
+    #[arg(long, value_name = "true | false")]
+    /// Don't search directories starting with a dot inside the search paths
+    skip_hidden_dirs: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Don't search directories listed in the `.gitignore` files found while searching
+    respect_gitignore: Option<bool>,
+    #[arg(long, value_name = "duration", value_parser = stale_age)]
+    /// Kill sessions that weren't attached to for this long, like `30d` or `12h`
+    auto_kill_stale: Option<String>,
+    #[arg(long, value_name = "session names", num_args = 1..)]
+    /// Sessions that are only killed with `tms kill --force` and never for being stale
+    protected_sessions: Option<Vec<String>>,
+    #[arg(long, value_name = "true | false")]
+    /// Keep a log of the sessions tms creates, switches to and kills, shown by `tms history`
+    event_log: Option<bool>,
+    #[arg(long, value_name = "https | ssh")]
+    /// Clone repositories found by searching with `tms clone-repo` over https or ssh
+    clone_protocol: Option<CloneProtocol>,
+    #[arg(long, value_name = "github | gitlab", num_args = 1..)]
+    /// Where `tms clone-repo` searches for repositories when given a name instead of a URL
+    forges: Option<Vec<Forge>>,
+    #[arg(long, value_name = "workspace dirs", num_args = 1..)]
+    /// Directories whose directories are all projects, listed without searching them or needing git
+    workspace_dirs: Option<Vec<String>>,
+    #[arg(
+        long,
+        value_name = "branch | last_commit | dirty | ahead_behind",
+        num_args = 1..
+    )]
+    /// Columns with the state of each repository shown after its name in the picker
+    picker_columns: Option<Vec<PickerColumn>>,
+    #[arg(long, value_name = "branch")]
+    /// The branch `tms init-repo` starts new repositories on, instead of git's `init.defaultBranch`
+    init_repo_branch: Option<String>,
+    #[arg(long, value_name = "keys", value_parser = sort_keys)]
+    /// The order of the projects in the picker before anything is typed, like `recency desc, name asc`
+    picker_sort: Option<String>,
+    #[arg(long, value_name = "lines")]
+    /// Lines of a pane's scrollback shown in the previews of `switch`, `kill` and `windows`, above
+    /// the visible part
+    preview_scrollback: Option<usize>,
+    #[arg(long, value_name = "true | false")]
+    /// Ask before killing a session or deleting a project from the picker, which is done by default
+    confirm_destructive_actions: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Show the branch of each repository in the picker and whether it has changes, read in the
+    /// background
+    show_git_status: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Leave out search directories that another search directory already searches as deep,
+    /// instead of warning about them
+    merge_search_dirs: Option<bool>,
+    #[arg(long, value_name = "true | false")]
+    /// Match the filter against the full path of each project too, while still showing its name
+    match_paths: Option<bool>,
+}
+
+fn stale_age(value: &str) -> std::result::Result<String, String> {
+    parse_duration(value)
+        .map(|_| value.to_owned())
+        .ok_or_else(|| "expected a number with a unit like `90m`, `12h`, `30d` or `2w`".to_owned())
+}
+
+fn sort_keys(value: &str) -> std::result::Result<String, String> {
+    parse_sort_keys(value).map(|_| value.to_owned())
+}
+
+impl TmsCommand for ConfigCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        // The schema describes any config file, so a broken or missing one doesn't matter
+        if let Some(ConfigSubCommand::Schema) = self.subcommand {
+            return print_schema();
+        }
+        config_command(self, ctx.stored_config()?)
+    }
+}
+
+fn print_schema() -> Result<()> {
+    // TOML has no null, an unset option is just left out
+    let schema = SchemaSettings::draft07()
+        .with(|settings| settings.option_add_null_type = false)
+        .into_generator()
+        .into_root_schema_for::<Config>();
+    let schema = serde_json::to_string_pretty(&schema).change_context(TmsError::ConfigError)?;
+    println!("{schema}");
+    Ok(())
+}
+
+fn config_command(cmd: &ConfigCommand, mut config: Config) -> Result<()> {
+    match &cmd.subcommand {
+        None => {}
+        Some(ConfigSubCommand::List(args)) => {
+            let config = if args.defaults {
+                Config::default()
+            } else {
+                config
+            };
+            config.print_search_dir_warnings();
+            let config = ConfigExport::from(config);
+            let toml_pretty =
+                toml::to_string_pretty(&config).change_context(TmsError::ConfigError)?;
+            println!("{}", toml_pretty);
+            return Ok(());
+        }
+        Some(ConfigSubCommand::Schema) => return print_schema(),
+    };
+    let args = &cmd.args;
+    let max_depths = args.max_depths.clone().unwrap_or_default();
+    config.search_dirs = match &args.search_paths {
+        Some(paths) => Some(
+            paths
+                .iter()
+                .zip(max_depths.into_iter().chain(std::iter::repeat(10)))
+                .map(|(path, depth)| {
+                    let path = if path.ends_with('/') {
+                        let mut modified_path = path.clone();
+                        modified_path.pop();
+                        modified_path
+                    } else {
+                        path.clone()
+                    };
+                    shellexpand::full(&path)
+                        .map(|val| (val.to_string(), depth))
+                        .change_context(TmsError::IoError)
+                })
+                .collect::<Result<Vec<(String, usize)>>>()?
+                .iter()
+                .map(|(path, depth)| {
+                    canonicalize(path)
+                        .map(|val| SearchDirectory::new(val, *depth))
+                        .change_context(TmsError::IoError)
+                })
+                .collect::<Result<Vec<SearchDirectory>>>()?,
+        ),
+        None => config.search_dirs,
+    };
+
+    if let Some(default_session) = args
+        .default_session
+        .clone()
+        .map(|val| tmux_session_name(&val))
+    {
+        config.default_session = Some(default_session);
+    }
+
+    if let Some(display) = args.display_full_path {
+        config.display_full_path = Some(display.to_owned());
+    }
+
+    if let Some(submodules) = args.search_submodules {
+        config.search_submodules = Some(submodules.to_owned());
+    }
+
+    if let Some(submodules) = args.recursive_submodules {
+        config.recursive_submodules = Some(submodules.to_owned());
+    }
+
+    if let Some(switch_filter_unknown) = args.switch_filter_unknown {
+        config.switch_filter_unknown = Some(switch_filter_unknown.to_owned());
+    }
+
+    if let Some(dirs) = &args.excluded_dirs {
+        let current_excluded = config.excluded_dirs;
+        match current_excluded {
+            Some(mut excl_dirs) => {
+                excl_dirs.extend(dirs.iter().map(|str| str.to_string()));
+                config.excluded_dirs = Some(excl_dirs)
+            }
+            None => {
+                config.excluded_dirs = Some(dirs.iter().map(|str| str.to_string()).collect());
+            }
+        }
+    }
+    if let Some(dirs) = &args.remove_dir {
+        let current_excluded = config.excluded_dirs;
+        match current_excluded {
+            Some(mut excl_dirs) => {
+                dirs.iter().for_each(|dir| excl_dirs.retain(|x| x != dir));
+                config.excluded_dirs = Some(excl_dirs);
+            }
+            None => todo!(),
+        }
+    }
+
+    if let Some(color) = &args.picker_highlight_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.highlight_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+    if let Some(color) = &args.picker_highlight_text_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.highlight_text_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+    if let Some(color) = &args.picker_border_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.border_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+    if let Some(color) = &args.picker_info_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.info_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+    if let Some(color) = &args.picker_prompt_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.prompt_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+    if let Some(color) = &args.picker_error_color {
+        let mut picker_colors = config.picker_colors.unwrap_or_default();
+        picker_colors.error_color = Some(*color);
+        config.picker_colors = Some(picker_colors);
+    }
+
+    if let Some(order) = &args.session_sort_order {
+        config.session_sort_order = Some(order.to_owned());
+    }
+
+    if let Some(switch) = &args.clone_repo_switch {
+        config.clone_repo_switch = Some(switch.to_owned());
+    }
+
+    if let Some(color) = args.color {
+        config.color = Some(color);
+    }
+
+    if let Some(explicit_search_mode) = args.explicit_search_mode {
+        config.explicit_search_mode = Some(explicit_search_mode);
+    }
+
+    if let Some(worktree_windows) = args.worktree_windows {
+        config.worktree_windows = Some(worktree_windows);
+    }
+
+    if let Some(pick_worktrees) = args.pick_worktrees {
+        config.pick_worktrees = Some(pick_worktrees);
+    }
+
+    if let Some(project_colors) = args.project_colors {
+        config.project_colors = Some(project_colors);
+    }
+
+    if let Some(worktrees_dir) = &args.worktrees_dir {
+        config.worktrees_dir = Some(worktrees_dir.to_owned());
+    }
+
+    if let Some(team_config) = &args.team_config {
+        config.team_config = Some(team_config.to_owned());
+    }
+
+    if let Some(skip_hidden_dirs) = args.skip_hidden_dirs {
+        config.skip_hidden_dirs = Some(skip_hidden_dirs);
+    }
+
+    if let Some(respect_gitignore) = args.respect_gitignore {
+        config.respect_gitignore = Some(respect_gitignore);
+    }
+
+    if let Some(auto_kill_stale) = &args.auto_kill_stale {
+        config.auto_kill_stale = Some(auto_kill_stale.to_owned());
+    }
+
+    if let Some(protected_sessions) = &args.protected_sessions {
+        config.protected_sessions = Some(protected_sessions.to_owned());
+    }
+
+    if let Some(event_log) = args.event_log {
+        config.event_log = Some(event_log);
+    }
+
+    if let Some(clone_protocol) = args.clone_protocol {
+        config.clone_protocol = Some(clone_protocol);
+    }
+
+    if let Some(forges) = &args.forges {
+        config.forges = Some(forges.to_owned());
+    }
+
+    if let Some(workspace_dirs) = &args.workspace_dirs {
+        config.workspace_dirs = Some(workspace_dirs.to_owned());
+    }
+
+    if let Some(picker_columns) = &args.picker_columns {
+        config.picker_columns = Some(picker_columns.to_owned());
+    }
+
+    if let Some(init_repo_branch) = &args.init_repo_branch {
+        config.init_repo_branch = Some(init_repo_branch.to_owned());
+    }
+
+    if let Some(picker_sort) = &args.picker_sort {
+        config.picker_sort = Some(picker_sort.to_owned());
+    }
+
+    if let Some(preview_scrollback) = args.preview_scrollback {
+        config.preview_scrollback = Some(preview_scrollback);
+    }
+
+    if let Some(confirm_destructive_actions) = args.confirm_destructive_actions {
+        config.confirm_destructive_actions = Some(confirm_destructive_actions);
+    }
+
+    if let Some(show_git_status) = args.show_git_status {
+        config.show_git_status = Some(show_git_status);
+    }
+
+    if let Some(merge_search_dirs) = args.merge_search_dirs {
+        config.merge_search_dirs = Some(merge_search_dirs);
+    }
+
+    if let Some(match_paths) = args.match_paths {
+        config.match_paths = Some(match_paths);
+    }
+
+    config.save().change_context(TmsError::ConfigError)?;
+    println!("{}", tr("Configuration has been stored"));
+    config.print_search_dir_warnings();
+    Ok(())
+}
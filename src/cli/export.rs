@@ -0,0 +1,285 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeSet,
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configs::Config,
+    error::Suggestion,
+    i18n::{tr, tr_fmt},
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+/// The version of the export format, raised when it changes in ways older versions can't read
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Args)]
+pub struct ExportCommand {
+    #[arg(value_name = "file")]
+    /// Where to write the export, stdout when left out or `-`
+    file: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportCommand {
+    #[arg(value_name = "file")]
+    /// The export to import, stdin when it is `-`
+    file: String,
+    #[arg(long = "map", value_name = "from=to")]
+    /// Move paths under `from` to `to` without asking, can be given several times
+    maps: Vec<String>,
+}
+
+/// The file `tms export` writes, the config with the machine it came from
+#[derive(Debug, Serialize, Deserialize)]
+struct Export {
+    export: ExportInfo,
+    config: toml::Table,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportInfo {
+    version: u32,
+    /// The home directory on the exporting machine, which paths under it are moved from
+    home: Option<String>,
+}
+
+impl TmsCommand for ExportCommand {
+    fn run(&self, _ctx: &mut Context) -> Result<()> {
+        export_command(self)
+    }
+}
+
+impl TmsCommand for ImportCommand {
+    fn run(&self, _ctx: &mut Context) -> Result<()> {
+        import_command(self)
+    }
+}
+
+fn export_command(args: &ExportCommand) -> Result<()> {
+    // The config file as it is, not changed by the active profile, the same as import replaces
+    let config = Config::stored().change_context(TmsError::ConfigError)?;
+    let export = Export {
+        export: ExportInfo {
+            version: EXPORT_VERSION,
+            home: dirs::home_dir().map(|home| home.to_string_lossy().into_owned()),
+        },
+        config: toml::Table::try_from(&config).change_context(TmsError::ConfigError)?,
+    };
+    let contents = toml::to_string_pretty(&export).change_context(TmsError::ConfigError)?;
+
+    match args.file.as_deref() {
+        None | Some("-") => print!("{contents}"),
+        Some(file) => fs::write(file, contents)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not write the export to {file}"))?,
+    }
+    Ok(())
+}
+
+fn import_command(args: &ImportCommand) -> Result<()> {
+    let contents = if args.file == "-" {
+        let mut contents = String::new();
+        io::stdin()
+            .read_to_string(&mut contents)
+            .change_context(TmsError::IoError)
+            .attach_printable("Could not read the export from stdin")?;
+        contents
+    } else {
+        fs::read_to_string(&args.file)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not read the export {}", args.file))?
+    };
+    let export: Export = toml::from_str(&contents)
+        .change_context(TmsError::ConfigError)
+        .attach_printable_lazy(|| format!("{} is not an export of tms", args.file))
+        .attach(Suggestion("Create one with `tms export <file>`"))?;
+    if export.export.version > EXPORT_VERSION {
+        return Err(TmsError::ConfigError)
+            .attach_printable(format!(
+                "The export was made by a newer version of tms (format {})",
+                export.export.version
+            ))
+            .attach(Suggestion("Update tms to import it"));
+    }
+
+    let mut maps = args
+        .maps
+        .iter()
+        .map(|map| {
+            map.split_once('=')
+                .map(|(from, to)| (from.trim_end_matches('/').to_owned(), to.to_owned()))
+                .ok_or(TmsError::ConfigError)
+                .attach_printable(format!("Expected from=to but got `{map}`"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut config = toml::Value::Table(export.config);
+    let home = dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+    for (root, default) in roots_to_move(&config, export.export.home.as_deref(), home.as_deref()) {
+        if maps.iter().any(|(from, _)| *from == root) {
+            continue;
+        }
+        let to = ask_destination(&root, &default)?;
+        if to != root {
+            maps.push((root, to));
+        }
+    }
+    // The deepest directories are moved first, so they win over the ones they are in
+    maps.sort_by_key(|(from, _)| Reverse(from.len()));
+    move_paths(&mut config, &maps);
+
+    let config: Config = config
+        .try_into()
+        .change_context(TmsError::ConfigError)
+        .attach_printable("The export doesn't contain a valid config")?;
+    let _lock = Config::lock().change_context(TmsError::ConfigError)?;
+    if let Some(backup) = Config::backup().change_context(TmsError::ConfigError)? {
+        println!(
+            "{}",
+            tr_fmt("The previous config was saved as {}", &[&backup.display()])
+        );
+    }
+    config.save().change_context(TmsError::ConfigError)?;
+    println!("{}", tr("The setup has been imported"));
+    Ok(())
+}
+
+/// The directories the paths in the config may have to be moved out of, each with where they go
+/// when nothing else is given: the home directory of the exporting machine goes to this one's, and
+/// the top two directories of other paths that don't exist here stay where they are
+fn roots_to_move(
+    config: &toml::Value,
+    exported_home: Option<&str>,
+    home: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut paths = Vec::new();
+    collect_paths(config, &mut paths);
+
+    let exported_home = exported_home.filter(|exported| Some(*exported) != home);
+    let mut roots = Vec::new();
+    if let Some((exported_home, home)) = exported_home.zip(home) {
+        if paths.iter().any(|path| is_under(path, exported_home)) {
+            roots.push((exported_home.to_owned(), home.to_owned()));
+        }
+    }
+    let missing: BTreeSet<String> = paths
+        .iter()
+        .filter(|path| !exported_home.is_some_and(|exported| is_under(path, exported)))
+        .filter(|path| !Path::new(path).exists())
+        .filter_map(|path| {
+            let mut components = path.split('/').filter(|component| !component.is_empty());
+            Some(format!("/{}/{}", components.next()?, components.next()?))
+        })
+        .collect();
+    roots.extend(missing.into_iter().map(|root| (root.clone(), root)));
+    roots
+}
+
+/// Every string in the config that is an absolute path
+fn collect_paths<'a>(value: &'a toml::Value, paths: &mut Vec<&'a str>) {
+    match value {
+        toml::Value::String(string) if string.starts_with('/') => paths.push(string),
+        toml::Value::Array(values) => values.iter().for_each(|value| collect_paths(value, paths)),
+        toml::Value::Table(table) => table.values().for_each(|value| collect_paths(value, paths)),
+        _ => {}
+    }
+}
+
+/// Move every path in the config under the first `from` it's in to the `to` of that mapping
+fn move_paths(value: &mut toml::Value, maps: &[(String, String)]) {
+    match value {
+        toml::Value::String(string) => {
+            let moved = maps.iter().find_map(|(from, to)| {
+                is_under(string, from).then(|| format!("{to}{}", &string[from.len()..]))
+            });
+            if let Some(moved) = moved {
+                *string = moved;
+            }
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(|value| move_paths(value, maps)),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| move_paths(value, maps)),
+        _ => {}
+    }
+}
+
+fn is_under(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Ask where the paths under `root` go, `default` when nothing is typed or nobody can answer
+fn ask_destination(root: &str, default: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        return Ok(default.to_owned());
+    }
+    print!(
+        "{} ",
+        tr_fmt("Move the paths under {} to [{}]:", &[&root, &default])
+    );
+    io::stdout().flush().change_context(TmsError::IoError)?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .change_context(TmsError::IoError)?;
+    let answer = answer.trim().trim_end_matches('/');
+    Ok(if answer.is_empty() { default } else { answer }.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_paths_from_the_other_home() {
+        let mut config: toml::Value = toml::from_str(
+            r#"
+            bookmarks = ["/home/a/notes", "/home/ab/other", "~/dotfiles"]
+            worktrees_dir = "/home/a"
+            [marks]
+            0 = "/home/a/code/api"
+            [[search_dirs]]
+            path = "/nonexistent-tms-disk/code/work"
+            depth = 2
+            "#,
+        )
+        .unwrap();
+
+        let roots = roots_to_move(&config, Some("/home/a"), Some("/Users/b"));
+        assert_eq!(
+            roots,
+            [
+                ("/home/a".into(), "/Users/b".into()),
+                ("/home/ab".into(), "/home/ab".into()),
+                (
+                    "/nonexistent-tms-disk/code".into(),
+                    "/nonexistent-tms-disk/code".into()
+                ),
+            ]
+        );
+
+        move_paths(
+            &mut config,
+            &[
+                ("/home/a/code".into(), "/Volumes/code".into()),
+                ("/home/a".into(), "/Users/b".into()),
+            ],
+        );
+        assert_eq!(
+            config["bookmarks"],
+            toml::Value::from(vec!["/Users/b/notes", "/home/ab/other", "~/dotfiles"])
+        );
+        assert_eq!(config["worktrees_dir"].as_str(), Some("/Users/b"));
+        assert_eq!(config["marks"]["0"].as_str(), Some("/Volumes/code/api"));
+    }
+}
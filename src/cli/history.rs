@@ -0,0 +1,76 @@
+use clap::Args;
+
+use crate::{
+    configs::{parse_duration, Config},
+    error::Suggestion,
+    history::{self, format_time, Action},
+    state::now,
+    Result, TmsError,
+};
+use error_stack::ResultExt;
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct HistoryCommand {
+    #[arg(long, short)]
+    /// Only show what happened to this session
+    session: Option<String>,
+    #[arg(long, short)]
+    /// Only show this kind of action
+    action: Option<Action>,
+    #[arg(long, value_name = "duration", value_parser = since)]
+    /// Only show what happened this long ago or later, like `2h` or `7d`
+    since: Option<u64>,
+    #[arg(long, short = 'n', value_name = "count", default_value_t = 20)]
+    /// How many of the latest entries to show
+    limit: usize,
+}
+
+fn since(value: &str) -> std::result::Result<u64, String> {
+    parse_duration(value)
+        .map(|duration| duration.as_secs())
+        .ok_or_else(|| "expected a number with a unit like `90m`, `12h`, `30d` or `2w`".to_owned())
+}
+
+impl TmsCommand for HistoryCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        history_command(self, ctx.config()?)
+    }
+}
+
+fn history_command(args: &HistoryCommand, config: &Config) -> Result<()> {
+    let log_file = history::log_file()?;
+    if config.event_log != Some(true) && !log_file.exists() {
+        return Err(TmsError::ConfigError)
+            .attach_printable("The event log is disabled")
+            .attach(Suggestion("Enable it with `tms config --event-log true`"));
+    }
+
+    let oldest = args.since.map(|since| now().saturating_sub(since));
+    let entries: Vec<_> = history::entries()?
+        .into_iter()
+        .filter(|entry| oldest.is_none_or(|oldest| entry.time >= oldest))
+        .filter(|entry| args.action.is_none_or(|action| entry.action == action))
+        .filter(|entry| {
+            args.session
+                .as_ref()
+                .is_none_or(|session| entry.session.as_ref() == Some(session))
+        })
+        .collect();
+
+    for entry in &entries[entries.len().saturating_sub(args.limit)..] {
+        let mut line = format!("{}  {:<6}", format_time(entry.time), entry.action.name());
+        if let Some(session) = &entry.session {
+            line.push_str(&format!("  {session}"));
+        }
+        if let Some(path) = &entry.path {
+            line.push_str(&format!("  {}", path.display()));
+        }
+        if let Some(details) = &entry.details {
+            line.push_str(&format!("  {details}"));
+        }
+        println!("{line}");
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use clap::{Args, ValueEnum};
+use error_stack::ResultExt;
+
+use crate::{i18n::tr, state::State, tmux::Tmux, Result, TmsError};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct InstallHooksCommand {
+    #[arg(long)]
+    /// Remove the hooks instead of installing them
+    uninstall: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct HookCommand {
+    event: HookEvent,
+    session: String,
+    /// The session the client was attached to before
+    previous: Option<String>,
+    #[arg(long)]
+    /// The tty of the client that switched sessions
+    client: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum HookEvent {
+    SessionCreated,
+    ClientSessionChanged,
+}
+
+impl HookEvent {
+    const ALL: [HookEvent; 2] = [HookEvent::SessionCreated, HookEvent::ClientSessionChanged];
+
+    fn tmux_hook(self) -> &'static str {
+        match self {
+            HookEvent::SessionCreated => "session-created",
+            HookEvent::ClientSessionChanged => "client-session-changed",
+        }
+    }
+
+    fn format_args(self) -> &'static str {
+        match self {
+            HookEvent::SessionCreated => "'#{hook_session_name}'",
+            HookEvent::ClientSessionChanged => {
+                "'#{client_session}' '#{client_last_session}' --client '#{client_tty}'"
+            }
+        }
+    }
+}
+
+impl TmsCommand for InstallHooksCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        install_hooks_command(self, ctx.tmux)
+    }
+}
+
+impl TmsCommand for HookCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        hook_command(self, ctx.tmux)
+    }
+}
+
+/// Index in the tmux hook arrays used by tms, so hooks set up by the user are left alone
+const HOOK_INDEX: usize = 100;
+
+fn install_hooks_command(args: &InstallHooksCommand, tmux: &Tmux) -> Result<()> {
+    let exe = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_owned))
+        .unwrap_or_else(|| "tms".to_owned());
+
+    for event in HookEvent::ALL {
+        let hook = format!("{}[{HOOK_INDEX}]", event.tmux_hook());
+        if args.uninstall {
+            tmux.unset_hook(&hook);
+            continue;
+        }
+
+        let shell_command = format!(
+            "TMS_TMUX_SOCKET={} {} hook {} {}",
+            shell_words::quote(tmux.socket_name()),
+            shell_words::quote(&exe),
+            event
+                .to_possible_value()
+                .expect("hook events are never skipped")
+                .get_name(),
+            event.format_args()
+        );
+        let escaped = shell_command
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$");
+        let output = tmux.set_hook(&hook, &format!("run-shell -b \"{escaped}\""));
+        if !output.status.success() {
+            return Err(TmsError::IoError)
+                .attach_printable(format!("Could not set the {} hook", event.tmux_hook()))
+                .attach_printable(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+        }
+    }
+
+    if args.uninstall {
+        println!("{}", tr("Removed the tms hooks"));
+    } else {
+        println!(
+            "{}",
+            tr("Installed the tms hooks, they last until the tmux server exits")
+        );
+    }
+    Ok(())
+}
+
+fn hook_command(args: &HookCommand, tmux: &Tmux) -> Result<()> {
+    let mut previous = None;
+    let mut client = None;
+    if let HookEvent::ClientSessionChanged = args.event {
+        previous = args
+            .previous
+            .as_deref()
+            .filter(|previous| !previous.is_empty() && *previous != args.session);
+        if let Some(previous) = previous {
+            tmux.set_global_option("@tms_previous_session", previous);
+        }
+        client = args
+            .client
+            .as_ref()
+            .map(|client| tmux.client_key_of(client));
+    }
+
+    State::update(|state| {
+        state.mark_used(&args.session);
+        if let Some(client) = &client {
+            state.record_switch(client, previous, &args.session);
+        }
+    })
+}
@@ -0,0 +1,206 @@
+use std::{fs, path::Path};
+
+use clap::Args;
+use clap_complete::{ArgValueCandidates, CompletionCandidate};
+use error_stack::ResultExt;
+use git2::{Branch, PushOptions, RemoteCallbacks, Repository, RepositoryInitOptions};
+
+use crate::{
+    configs::{Config, Template},
+    credentials::Credentials,
+    error::Suggestion,
+    fs_ops::copy_dir,
+    i18n::tr_fmt,
+    session::{project_name, Session, SessionType},
+    tmux::{hook_command, Tmux},
+    Result, TmsError,
+};
+
+use super::{pick_search_path, Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct InitRepoCommand {
+    /// Name of the repository to initialize
+    repository: String,
+    #[arg(long, value_name = "branch")]
+    /// The branch to start on, instead of the configured `init_repo_branch` or git's default
+    branch: Option<String>,
+    #[arg(long, value_name = "url")]
+    /// Add this URL as the `origin` remote
+    remote: Option<String>,
+    #[arg(long, requires = "remote")]
+    /// Push an empty first commit to the remote and track it as the branch's upstream
+    push: bool,
+    #[arg(long, value_name = "name", add = ArgValueCandidates::new(template_completion_candidates))]
+    /// Fill the repository from a template configured under `[templates.<name>]`
+    template: Option<String>,
+}
+
+impl TmsCommand for InitRepoCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        init_repo_command(self, ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn init_repo_command(args: &InitRepoCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let branch = args.branch.as_ref().or(config.init_repo_branch.as_ref());
+    if let Some(branch) = branch {
+        if !Branch::name_is_valid(branch).unwrap_or_default() {
+            return Err(TmsError::GitError)
+                .attach_printable(format!("{branch} is not a valid branch name"));
+        }
+    }
+
+    let template = args
+        .template
+        .as_deref()
+        .map(|name| config.template(name))
+        .transpose()
+        .change_context(TmsError::ConfigError)?;
+
+    let Some(mut path) = pick_search_path(&config, tmux)? else {
+        return Ok(());
+    };
+    path.push(&args.repository);
+    if let Some(template) = template {
+        apply_template(template, &args.repository, &path)?;
+    }
+
+    let mut options = RepositoryInitOptions::new();
+    if let Some(branch) = branch {
+        options.initial_head(branch);
+    }
+    let repo = Repository::init_opts(&path, &options).change_context(TmsError::GitError)?;
+
+    if let Some(url) = &args.remote {
+        repo.remote("origin", url)
+            .change_context(TmsError::GitError)
+            .attach_printable_lazy(|| format!("Could not add {url} as the origin remote"))?;
+        if args.push {
+            push_first_commit(&repo)?;
+        }
+    }
+
+    let name = project_name(&path)?;
+    Session::new(name, SessionType::Git(repo)).switch_to(tmux, &config)?;
+
+    Ok(())
+}
+
+/// Fill the new project's directory, which must be empty, by copying the template's directory and
+/// then running its command
+fn apply_template(template: &Template, name: &str, path: &Path) -> Result<()> {
+    if path
+        .read_dir()
+        .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        return Err(TmsError::IoError)
+            .attach_printable(format!("{} already exists and isn't empty", path.display()));
+    }
+    fs::create_dir_all(path).change_context(TmsError::IoError)?;
+
+    if let Some(dir) = &template.path {
+        let dir = shellexpand::full(dir).change_context(TmsError::IoError)?;
+        copy_dir(Path::new(dir.as_ref()), path)?;
+    }
+    if let Some(command) = &template.command {
+        let status = hook_command(command)?
+            .current_dir(path)
+            .env("TMS_PROJECT_NAME", name)
+            .env("TMS_PROJECT_PATH", path)
+            .status()
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not run the template command `{command}`"))?;
+        if !status.success() {
+            return Err(TmsError::ScriptError)
+                .attach_printable(format!("The template command `{command}` failed"));
+        }
+    }
+
+    Ok(())
+}
+
+fn template_completion_candidates() -> Vec<CompletionCandidate> {
+    let templates = Config::new()
+        .ok()
+        .and_then(|config| config.templates)
+        .unwrap_or_default();
+    let mut candidates: Vec<CompletionCandidate> = templates
+        .into_iter()
+        .map(|(name, template)| {
+            let help = template.path.or(template.command).unwrap_or_default();
+            CompletionCandidate::new(name).help(Some(help.into()))
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.get_value().cmp(b.get_value()));
+    candidates
+}
+
+/// Commit nothing yet, push it to `origin` and set the remote branch as the upstream, so the
+/// repository is ready for `git push` and `git pull`
+fn push_first_commit(repo: &Repository) -> Result<()> {
+    let signature = repo
+        .signature()
+        .change_context(TmsError::GitError)
+        .attach_printable("Could not make the first commit")
+        .attach(Suggestion(
+            "Set your name and email with `git config --global user.name` and `user.email`",
+        ))?;
+    let tree = repo
+        .index()
+        .and_then(|mut index| index.write_tree())
+        .and_then(|tree| repo.find_tree(tree))
+        .change_context(TmsError::GitError)?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Initial commit",
+        &tree,
+        &[],
+    )
+    .change_context(TmsError::GitError)?;
+
+    let head = repo.head().change_context(TmsError::GitError)?;
+    let branch = head.shorthand().unwrap_or_default();
+    let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+
+    let mut remote = repo
+        .find_remote("origin")
+        .change_context(TmsError::GitError)?;
+    let mut credentials = Credentials::default();
+    let mut rejected = None;
+    {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| credentials.get(url, username, allowed));
+        callbacks.push_update_reference(|_, status| {
+            rejected = status.map(str::to_owned);
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote
+            .push(&[&refspec], Some(&mut options))
+            .change_context(TmsError::GitError)
+            .attach_printable_lazy(|| format!("Could not push {branch} to origin"))?;
+    }
+    if let Some(reason) = rejected {
+        return Err(TmsError::GitError)
+            .attach_printable(format!("The remote rejected {branch}: {reason}"));
+    }
+    credentials.approve();
+
+    let mut git_config = repo.config().change_context(TmsError::GitError)?;
+    git_config
+        .set_str(&format!("branch.{branch}.remote"), "origin")
+        .and_then(|_| {
+            git_config.set_str(
+                &format!("branch.{branch}.merge"),
+                &format!("refs/heads/{branch}"),
+            )
+        })
+        .change_context(TmsError::GitError)?;
+    println!("{}", tr_fmt("Pushed {} to origin", &[&branch]));
+
+    Ok(())
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{
+    configs::{Config, SessionEvent, SessionSortOrderConfig},
+    error::Suggestion,
+    i18n::{tr, tr_fmt},
+    picker::{Picker, Preview},
+    state::{log_stale_session, now, State},
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct KillCommand {
+    #[arg(long, short)]
+    /// Kill the session without asking even if programs other than a shell are running in it or it
+    /// is protected
+    force: bool,
+    #[arg(long, short)]
+    /// Pick the sessions to kill, marking several with tab
+    pick: bool,
+}
+
+impl TmsCommand for KillCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        kill_subcommand(self, ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn kill_subcommand(args: &KillCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    tmux.require_server()?;
+    // Outside of tmux no session is the current one, which only leaves picking them
+    let in_session = tmux.in_session();
+    let mut current_session = if in_session {
+        tmux.display_message("'#S'")
+    } else {
+        String::new()
+    };
+    current_session.retain(|x| x != '\'' && x != '\n');
+
+    if args.pick {
+        return kill_picked(args, &config, &current_session, tmux);
+    }
+    if !in_session {
+        return Err(TmsError::NotInTmux).attach(Suggestion(
+            "Pick the sessions to kill with `tms kill --pick`",
+        ));
+    }
+
+    if !args.force {
+        refuse_protected(&[current_session.as_str()], &config)?;
+        if !confirm_running(&[current_session.as_str()], tmux)? {
+            return Ok(());
+        }
+    }
+    let mut session_path = tmux.display_message("'#{session_path}'");
+    session_path.retain(|x| x != '\'' && x != '\n');
+
+    if let Some(to_session) = next_session(&config, &[current_session.as_str()], tmux) {
+        tmux.switch_client(&to_session);
+    }
+    close_session(current_session, PathBuf::from(session_path), &config, tmux)
+}
+
+/// Kill the sessions marked in the picker, switching away first when the current one is among them
+fn kill_picked(
+    args: &KillCommand,
+    config: &Config,
+    current_session: &str,
+    tmux: &Tmux,
+) -> Result<()> {
+    let session_paths: HashMap<String, PathBuf> = tmux
+        .list_sessions("#{session_name}\t#{session_path}")
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, path)| (name.to_owned(), PathBuf::from(path)))
+        .collect();
+    let mut sessions: Vec<String> = session_paths.keys().cloned().collect();
+    sessions.sort();
+
+    let Some(picked) = Picker::new(
+        &sessions,
+        Preview::SessionPane,
+        config.shortcuts.as_ref(),
+        tmux,
+    )
+    .set_colors(config.picker_colors.as_ref())
+    .set_color_mode(config.color)
+    .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+    .set_pane_scrollback(config.preview_scrollback.unwrap_or_default())
+    .set_multi_select(&[])
+    .run_multi()?
+    else {
+        return Ok(());
+    };
+    let mut picked: Vec<&str> = picked.iter().map(String::as_str).collect();
+    // tms is likely running in the current session, which ends it when it is killed
+    picked.sort_by_key(|session| *session == current_session);
+    if !args.force {
+        refuse_protected(&picked, config)?;
+        if !confirm_running(&picked, tmux)? {
+            return Ok(());
+        }
+    }
+
+    if picked.contains(&current_session) {
+        if let Some(to_session) = next_session(config, &picked, tmux) {
+            tmux.switch_client(&to_session);
+        }
+    }
+    for session in picked {
+        if let Some(path) = session_paths.get(session) {
+            close_session(session.to_owned(), path.clone(), config, tmux)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fail for the first of the sessions that is protected from being killed
+fn refuse_protected(sessions: &[&str], config: &Config) -> Result<()> {
+    match sessions.iter().find(|session| config.is_protected(session)) {
+        Some(session) => Err(TmsError::KillRefused(session.to_string()))
+            .attach_printable(tr_fmt("Session {} is protected", &[session]))
+            .attach(Suggestion("Use `tms kill --force` to kill it anyway")),
+        None => Ok(()),
+    }
+}
+
+/// Ask whether to kill the sessions when programs other than a shell are running in them, refusing
+/// when there is no terminal to ask in
+fn confirm_running(sessions: &[&str], tmux: &Tmux) -> Result<bool> {
+    for session in sessions {
+        let running = running_programs(session, tmux);
+        if running.is_empty() {
+            continue;
+        }
+        let prompt = tr_fmt(
+            "Session {} is running {}. Kill it anyway?",
+            &[session, &running.join(", ")],
+        );
+        if !io::stdin().is_terminal() {
+            return Err(TmsError::KillRefused(session.to_string()))
+                .attach_printable(prompt)
+                .attach(Suggestion("Use `tms kill --force` to kill it anyway"));
+        }
+        if !confirm(&prompt)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The session to switch to when leaving the given ones, the default session if it isn't among
+/// them or else another one that no client is attached to
+fn next_session(config: &Config, leaving: &[&str], tmux: &Tmux) -> Option<String> {
+    let sessions = tmux
+        .list_sessions("'#{?session_attached,,#{session_name}#,#{session_last_attached}}'")
+        .replace('\'', "")
+        .replace("\n\n", "\n");
+
+    let mut sessions: Vec<(&str, &str)> = sessions
+        .trim()
+        .split('\n')
+        .filter_map(|s| s.split_once(','))
+        .filter(|session| !leaving.contains(&session.0))
+        .collect();
+
+    if let Some(SessionSortOrderConfig::LastAttached) = config.session_sort_order {
+        sessions.sort_by(|a, b| b.1.cmp(a.1));
+    }
+
+    let to_session = if config.default_session.is_some()
+        && sessions
+            .iter()
+            .any(|session| session.0 == config.default_session.as_deref().unwrap())
+    {
+        config.default_session.as_deref()
+    } else {
+        sessions.first().map(|s| s.0)
+    };
+    to_session.map(str::to_owned)
+}
+
+/// Kill a session, remembering it as recently closed so it is offered first when picking a
+/// project again
+pub(super) fn close_session(
+    session: String,
+    session_path: PathBuf,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    tmux.run_session_hooks(SessionEvent::Kill, &session_path, &session, config)?;
+    tmux.kill_session(&session);
+
+    State::update(|state| state.add_recently_closed(session, session_path))
+}
+
+/// How often sessions are checked for being stale, so not every invocation lists them
+const STALE_CHECK_INTERVAL: u64 = 60 * 60;
+
+/// Kill the sessions that weren't attached to for longer than `auto_kill_stale` and aren't
+/// protected, logging which ones were killed. It's done on the side of picking a project or
+/// session, so a failure is only printed and doesn't stop what was asked for
+pub fn kill_stale_sessions(config: &Config, tmux: &Tmux) {
+    if let Err(error) = kill_stale(config, tmux) {
+        eprintln!("{error:?}");
+    }
+}
+
+fn kill_stale(config: &Config, tmux: &Tmux) -> Result<()> {
+    let Some(max_age) = config
+        .stale_session_age()
+        .change_context(TmsError::ConfigError)?
+    else {
+        return Ok(());
+    };
+    let now = now();
+    let is_due = |state: &State| now.saturating_sub(state.last_stale_check) >= STALE_CHECK_INTERVAL;
+    if !is_due(&State::load()?) {
+        return Ok(());
+    }
+    // Another invocation may have started checking since
+    let Some(last_used_at) = State::update(|state| {
+        let due = is_due(state);
+        state.last_stale_check = now;
+        due.then(|| state.last_used.clone())
+    })?
+    else {
+        return Ok(());
+    };
+
+    let sessions = tmux.list_sessions(
+        "#{session_name}\t#{session_path}\t#{session_attached}\t#{session_last_attached}\t#{session_created}",
+    );
+    for line in sessions.lines() {
+        let [name, path, attached, last_attached, created] =
+            line.split('\t').collect::<Vec<_>>()[..]
+        else {
+            continue;
+        };
+        if attached != "0"
+            || config.is_protected(name)
+            || config.default_session.as_deref() == Some(name)
+        {
+            continue;
+        }
+        // Sessions switched to without tms are only known to tmux, the others also to the state
+        let last_used = [last_attached, created]
+            .into_iter()
+            .filter_map(|time| time.parse().ok())
+            .chain(last_used_at.get(name).copied())
+            .max()
+            .unwrap_or(now);
+        let unused_for = Duration::from_secs(now.saturating_sub(last_used));
+        if unused_for < max_age {
+            continue;
+        }
+        close_session(name.to_owned(), PathBuf::from(path), config, tmux)?;
+        log_stale_session(name, Path::new(path), unused_for)?;
+    }
+    Ok(())
+}
+
+const SHELLS: &[&str] = &[
+    "sh", "bash", "zsh", "fish", "dash", "ksh", "mksh", "tcsh", "csh", "nu", "elvish", "xonsh",
+    "pwsh", "tms",
+];
+
+/// Foreground programs in the session's panes that aren't an idle shell
+fn running_programs(session: &str, tmux: &Tmux) -> Vec<String> {
+    let mut programs = Vec::new();
+    for command in tmux
+        .list_panes("'#{pane_current_command}'", session)
+        .lines()
+    {
+        let command = command.replace('\'', "");
+        let command = command.trim().trim_start_matches('-');
+        if !command.is_empty()
+            && !SHELLS.contains(&command)
+            && !programs.iter().any(|p| p == command)
+        {
+            programs.push(command.to_string());
+        }
+    }
+    programs
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} {} ", tr("[y/N]"));
+    io::stdout().flush().change_context(TmsError::IoError)?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .change_context(TmsError::IoError)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -0,0 +1,41 @@
+use clap::Args;
+
+use crate::{state::State, tmux::Tmux, Result, TmsError};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct LastCommand {}
+
+impl TmsCommand for LastCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        ctx.tmux.require_server()?;
+        switch_to_last(ctx.tmux)
+    }
+}
+
+/// Switch back to the session the client was in before the current one
+pub fn switch_to_last(tmux: &Tmux) -> Result<()> {
+    let session = last_session(tmux)?.ok_or(TmsError::NoPreviousSession)?;
+    tmux.switch_to_session(&session)
+}
+
+/// The most recent session of the client that is still running, other than the current one,
+/// falling back to the one the tmux hooks saw last
+fn last_session(tmux: &Tmux) -> Result<Option<String>> {
+    let current = tmux.display_message("#{session_name}");
+    let previous = tmux.display_message("#{@tms_previous_session}");
+    let state = State::load()?;
+    let recent = state.recent_sessions.get(&tmux.client_key());
+
+    let last = recent
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .chain([previous.trim()])
+        .find(|session| {
+            !session.is_empty() && *session != current.trim() && tmux.session_exists(session)
+        })
+        .map(str::to_owned);
+    Ok(last)
+}
@@ -0,0 +1,81 @@
+use clap::Args;
+use serde_derive::Serialize;
+
+use crate::{
+    configs::Config,
+    dirty_paths::DirtyUtf8Path,
+    session::{create_sessions, running_sessions, ProjectKind, SessionContainer},
+    tmux::Tmux,
+    Result,
+};
+
+use super::{print_json, Context, OutputFormat, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct ListProjectsCommand {
+    #[arg(long)]
+    /// Print each project's directory after its name, separated by a tab
+    with_path: bool,
+}
+
+impl TmsCommand for ListProjectsCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        let tmux = ctx.tmux;
+        let output = ctx.output();
+        list_projects(self, output, ctx.config()?, tmux)
+    }
+}
+
+/// A project, as listed by `--output json`
+#[derive(Debug, Serialize)]
+struct Project {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    kind: ProjectKind,
+    /// The tmux session open in the project's directory, if there is one
+    running_session: Option<String>,
+}
+
+/// Print the projects the picker lists, by name one per line, or with `--output json` with their
+/// details
+fn list_projects(
+    args: &ListProjectsCommand,
+    output: OutputFormat,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    // Printed to stderr, so the list stays the same for other tools
+    config.print_search_dir_warnings();
+    let sessions = create_sessions(config)?;
+    if output == OutputFormat::Text {
+        for name in sessions.list() {
+            match sessions.find_session(&name) {
+                Some(session) if args.with_path => {
+                    println!("{name}\t{}", session.path().display())
+                }
+                _ => println!("{name}"),
+            }
+        }
+        return Ok(());
+    }
+
+    let running = running_sessions(tmux);
+    let projects = sessions
+        .list()
+        .into_iter()
+        .filter_map(|name| {
+            let session = sessions.find_session(&name)?;
+            Some((name, session))
+        })
+        .map(|(name, session)| {
+            Ok(Project {
+                path: session.path().to_string()?,
+                kind: session.kind(),
+                running_session: session.running_session(&running),
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    print_json(&projects)
+}
@@ -0,0 +1,379 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    batch::RunCommand,
+    build_info,
+    configs::{ColorMode, Config, ConfigError},
+    dirty_paths::DirtyUtf8Path,
+    error::set_colors,
+    files::FileLock,
+    get_single_selection,
+    marks::MarksCommand,
+    picker::Preview,
+    tmux::Tmux,
+    Result, TmsError,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use error_stack::ResultExt;
+use serde::Serialize;
+
+mod bookmark;
+mod clone_repo;
+mod config;
+mod export;
+mod history;
+mod hooks;
+mod init_repo;
+mod kill;
+mod last;
+mod list_projects;
+mod open_session;
+mod refresh;
+mod rename;
+mod sessions;
+mod shell_init;
+mod start;
+mod switch;
+mod windows;
+mod worktree;
+
+pub use self::{
+    bookmark::BookmarkCommand,
+    clone_repo::CloneRepoCommand,
+    config::{ConfigArgs, ConfigCommand, ConfigSubCommand, ConfigSubCommandArgs},
+    export::{ExportCommand, ImportCommand},
+    history::HistoryCommand,
+    hooks::{HookCommand, InstallHooksCommand},
+    init_repo::InitRepoCommand,
+    kill::{kill_stale_sessions, KillCommand},
+    last::{switch_to_last, LastCommand},
+    list_projects::ListProjectsCommand,
+    open_session::OpenSessionCommand,
+    refresh::RefreshCommand,
+    rename::RenameCommand,
+    sessions::SessionsCommand,
+    shell_init::ShellInitCommand,
+    start::StartCommand,
+    switch::SwitchCommand,
+    windows::WindowsCommand,
+    worktree::WorktreeCommand,
+};
+
+#[derive(Debug, Parser)]
+#[command(author, version, disable_version_flag = true)]
+///Scan for all git folders in specified directorires, select one and open it as a new tmux session
+pub struct Cli {
+    #[arg(value_name = "path")]
+    /// Open the project in this directory without searching for projects
+    path: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "auto | always | never")]
+    /// Override the configured use of colors, `auto` disables them when NO_COLOR is set
+    color: Option<ColorMode>,
+    #[arg(long, short, value_name = "query")]
+    /// Open the project best matching the query without showing the picker
+    immediate: Option<String>,
+    #[arg(long, value_name = "query")]
+    /// Start the picker with this query in its filter
+    filter: Option<String>,
+    #[arg(long, requires = "filter")]
+    /// Open the project best matching `--filter` without showing the picker, failing when nothing
+    /// matches
+    first: bool,
+    #[arg(long)]
+    /// Only create the session of the picked project, without switching or attaching to it
+    detached: bool,
+    #[arg(long, value_name = "file", conflicts_with = "detached")]
+    /// Write the directory of the picked project to this file instead of opening it, used by the
+    /// `tms_cd` function of `shell-init`
+    cwd_file: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "text | json",
+        default_value = "text"
+    )]
+    /// Print the output of `sessions`, `marks list` and `list-projects` as text or as JSON for
+    /// other tools
+    output: OutputFormat,
+    #[arg(long, global = true, value_name = "name", env = "TMS_PROFILE")]
+    /// Only list the search directories, bookmarks and sessions of this profile, or read
+    /// `<name>.toml` next to the config file on top of it
+    profile: Option<String>,
+    #[arg(long = "set", global = true, value_name = "key=value")]
+    /// Override a config value for this run only, can be given several times
+    overrides: Vec<String>,
+    #[arg(long, short = 'V')]
+    /// Print version, with `--verbose` also the build details and tmux version for bug reports
+    version: bool,
+    #[arg(long, requires = "version")]
+    /// Print the build details with `--version`
+    verbose: bool,
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    #[command(arg_required_else_help = true)]
+    /// Configure the defaults for search paths and excluded directories
+    Config(Box<ConfigCommand>),
+    /// Initialize tmux with the default sessions
+    Start(StartCommand),
+    /// Display other sessions with a fuzzy finder and a preview window
+    Switch(SwitchCommand),
+    /// Display the current session's windows with a fuzzy finder and a preview window
+    Windows(WindowsCommand),
+    /// Kill the current tmux session and jump to another
+    Kill(KillCommand),
+    /// Switch back to the session you were in before the current one, like `cd -`
+    Last(LastCommand),
+    /// Show running tmux sessions with asterisk on the current session
+    Sessions(SessionsCommand),
+    /// List the projects the picker shows, one per line
+    ListProjects(ListProjectsCommand),
+    #[command(arg_required_else_help = true)]
+    /// Rename the active session, and with `--move-dir` its directory
+    Rename(RenameCommand),
+    /// Creates new worktree windows for the selected session
+    Refresh(RefreshCommand),
+    /// Clone repository and create a new session for it
+    CloneRepo(CloneRepoCommand),
+    /// Initialize empty repository
+    InitRepo(InitRepoCommand),
+    /// Open the worktrees of the current repository as sessions, adding new ones for branches
+    Worktree(WorktreeCommand),
+    /// Bookmark a directory so it is available to select along with the Git repositories
+    Bookmark(BookmarkCommand),
+    /// Open a session
+    OpenSession(OpenSessionCommand),
+    /// Manage list of sessions that can be instantly accessed by their index
+    Marks(MarksCommand),
+    /// Run a file of tms operations to set up sessions and windows
+    Run(RunCommand),
+    /// Write the config, with its bookmarks, marks and session layouts, to one file for `import`
+    Export(ExportCommand),
+    #[command(arg_required_else_help = true)]
+    /// Replace the config with one written by `export`, moving its paths to this machine
+    Import(ImportCommand),
+    /// Install tmux hooks so tms keeps track of sessions switched to without it
+    InstallHooks(InstallHooksCommand),
+    /// Show the log of sessions created, switched to and killed, when `event_log` is enabled
+    History(HistoryCommand),
+    /// Print the script that sets up Ctrl-F, `tms_cd` and the completions in the shell
+    ShellInit(ShellInitCommand),
+    #[command(hide = true)]
+    /// Bookkeeping called by the tmux hooks set up with `install-hooks`
+    Hook(HookCommand),
+}
+
+/// How subcommands print what they list
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Print the value as JSON, for `--output json`
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value).change_context(TmsError::IoError)?;
+    println!("{json}");
+    Ok(())
+}
+
+/// A subcommand of tms
+pub trait TmsCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()>;
+}
+
+/// What subcommands need to run, the configuration is only loaded once a command asks for it
+pub struct Context<'a> {
+    pub tmux: &'a Tmux,
+    output: OutputFormat,
+    color: Option<ColorMode>,
+    overrides: &'a [String],
+    profile: Option<&'a str>,
+    config: Option<Config>,
+    /// Held from loading the stored config until the command is done, so the config saved is the
+    /// one loaded
+    config_lock: Option<FileLock>,
+}
+
+impl<'a> Context<'a> {
+    fn new(
+        tmux: &'a Tmux,
+        output: OutputFormat,
+        color: Option<ColorMode>,
+        overrides: &'a [String],
+        profile: Option<&'a str>,
+    ) -> Self {
+        set_colors(color.unwrap_or_default().enabled());
+
+        Context {
+            tmux,
+            output,
+            color,
+            overrides,
+            profile,
+            config: None,
+            config_lock: None,
+        }
+    }
+
+    /// How listings are printed
+    pub fn output(&self) -> OutputFormat {
+        self.output
+    }
+
+    /// The configuration, including the overrides given on the command line and the team config,
+    /// restricted to the active profile
+    pub fn config(&mut self) -> Result<&Config> {
+        if self.config.is_none() {
+            let mut config = self.load(Config::with_overrides(self.overrides, self.profile))?;
+            config
+                .merge_team_config()
+                .change_context(TmsError::ConfigError)?;
+            config
+                .apply_profile()
+                .change_context(TmsError::ConfigError)?;
+            if let Some(color) = self.color {
+                config.color = Some(color);
+            }
+            self.config = Some(config);
+        }
+
+        Ok(self.config.as_ref().expect("the config was just loaded"))
+    }
+
+    /// Take ownership of the configuration, including the overrides given on the command line
+    pub fn take_config(&mut self) -> Result<Config> {
+        self.config()?;
+
+        Ok(self.config.take().expect("the config was just loaded"))
+    }
+
+    /// The configuration as stored in the config file, for commands that change and save it so the
+    /// included files, the overrides for this invocation and the team config don't end up in the
+    /// file. Other tms invocations can't change the file until this one is done
+    pub fn stored_config(&mut self) -> Result<Config> {
+        if self.config_lock.is_none() {
+            self.config_lock = Some(Config::lock().change_context(TmsError::ConfigError)?);
+        }
+        self.load(Config::stored())
+    }
+
+    fn load(&self, config: error_stack::Result<Config, ConfigError>) -> Result<Config> {
+        let config = config.change_context(TmsError::ConfigError)?;
+        set_colors(self.color.or(config.color).unwrap_or_default().enabled());
+
+        Ok(config)
+    }
+}
+
+impl Cli {
+    /// The query to open the best match for without showing the picker
+    pub fn immediate(&self) -> Option<&str> {
+        self.immediate
+            .as_deref()
+            .or(self.filter.as_deref().filter(|_| self.first))
+    }
+
+    /// The directory to open as a project without searching
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Whether the picked project's session should only be created
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    /// The file to write the directory of the picked project to instead of opening it
+    pub fn cwd_file(&self) -> Option<&Path> {
+        self.cwd_file.as_deref()
+    }
+
+    /// The query the picker starts with
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    pub fn handle_sub_commands(&self, tmux: &Tmux) -> Result<SubCommandGiven> {
+        if self.version {
+            println!("{}", build_info::version(self.verbose, tmux));
+            return Ok(SubCommandGiven::Yes);
+        }
+
+        let mut ctx = Context::new(
+            tmux,
+            self.output,
+            self.color,
+            &self.overrides,
+            self.profile.as_deref(),
+        );
+
+        let command: &dyn TmsCommand = match &self.command {
+            Some(CliCommand::Config(args)) => args.as_ref(),
+            Some(CliCommand::Start(args)) => args,
+            Some(CliCommand::Switch(args)) => args,
+            Some(CliCommand::Windows(args)) => args,
+            Some(CliCommand::Kill(args)) => args,
+            Some(CliCommand::Last(args)) => args,
+            Some(CliCommand::Sessions(args)) => args,
+            Some(CliCommand::ListProjects(args)) => args,
+            Some(CliCommand::Rename(args)) => args,
+            Some(CliCommand::Refresh(args)) => args,
+            Some(CliCommand::CloneRepo(args)) => args,
+            Some(CliCommand::InitRepo(args)) => args,
+            Some(CliCommand::Worktree(args)) => args,
+            Some(CliCommand::Bookmark(args)) => args,
+            Some(CliCommand::OpenSession(args)) => args,
+            Some(CliCommand::Marks(args)) => args,
+            Some(CliCommand::Run(args)) => args,
+            Some(CliCommand::Export(args)) => args,
+            Some(CliCommand::Import(args)) => args,
+            Some(CliCommand::InstallHooks(args)) => args,
+            Some(CliCommand::History(args)) => args,
+            Some(CliCommand::ShellInit(args)) => args,
+            Some(CliCommand::Hook(args)) => args,
+            None => return Ok(SubCommandGiven::No(Box::new(ctx.take_config()?))),
+        };
+        command.run(&mut ctx)?;
+
+        Ok(SubCommandGiven::Yes)
+    }
+}
+
+fn pick_search_path(config: &Config, tmux: &Tmux) -> Result<Option<PathBuf>> {
+    let search_dirs = config
+        .search_dirs
+        .as_ref()
+        .ok_or(TmsError::ConfigError)
+        .attach_printable("No search path configured")?
+        .iter()
+        .map(|dir| dir.path.to_string())
+        .filter_map(|path| path.ok())
+        .collect::<Vec<String>>();
+
+    let path = if search_dirs.len() > 1 {
+        get_single_selection(&search_dirs, Preview::Directory, config, tmux)?
+    } else {
+        let first = search_dirs
+            .first()
+            .ok_or(TmsError::ConfigError)
+            .attach_printable("No search path configured")?;
+        Some(first.clone())
+    };
+
+    let expanded = path
+        .as_ref()
+        .map(|path| shellexpand::full(path).change_context(TmsError::IoError))
+        .transpose()?
+        .map(|path| PathBuf::from(path.as_ref()));
+    Ok(expanded)
+}
+
+pub enum SubCommandGiven {
+    Yes,
+    No(Box<Config>),
+}
@@ -0,0 +1,79 @@
+use clap::Args;
+use clap_complete::{ArgValueCandidates, CompletionCandidate};
+use error_stack::ResultExt;
+use git2::Repository;
+
+use crate::{
+    configs::Config,
+    repos::{worktree_for_ref, worktrees_dir},
+    session::{create_sessions, dir_name, Session, SessionContainer, SessionType},
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct OpenSessionCommand {
+    #[arg(add = ArgValueCandidates::new(open_session_completion_candidates))]
+    /// Name of the session to open.
+    session: Box<str>,
+    #[arg(long = "ref", value_name = "branch | tag")]
+    /// Open a worktree of the session's repository with this branch or tag checked out, adding
+    /// the worktree if there is none yet
+    reference: Option<String>,
+}
+
+impl TmsCommand for OpenSessionCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        open_session_command(self, ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn open_session_command(args: &OpenSessionCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let sessions = create_sessions(&config)?;
+
+    let Some(session) = sessions.find_session(&args.session) else {
+        return Err(TmsError::SessionNotFound(args.session.to_string()).into());
+    };
+
+    match &args.reference {
+        Some(reference) => open_ref(session, reference, &config, tmux),
+        None => session.switch_to(tmux, &config),
+    }
+}
+
+fn open_ref(session: &Session, reference: &str, config: &Config, tmux: &Tmux) -> Result<()> {
+    let SessionType::Git(repo) = &session.session_type else {
+        return Err(TmsError::GitError)
+            .attach_printable(format!("{} is not a git repository", session.name));
+    };
+
+    let project_path = session.path();
+    let path = worktree_for_ref(repo, reference, &worktrees_dir(project_path, config)?)?;
+    if path.canonicalize().ok() == project_path.canonicalize().ok() {
+        return session.switch_to(tmux, config);
+    }
+
+    let worktree_name = dir_name(&path)?;
+    let worktree = Repository::open(&path).change_context(TmsError::GitError)?;
+    Session::new(
+        format!("{}[{worktree_name}]", session.name),
+        SessionType::Git(worktree),
+    )
+    .switch_to(tmux, config)
+}
+
+fn open_session_completion_candidates() -> Vec<CompletionCandidate> {
+    Config::new()
+        .change_context(TmsError::ConfigError)
+        .and_then(|config| create_sessions(&config))
+        .map(|sessions| {
+            sessions
+                .list()
+                .iter()
+                .map(CompletionCandidate::new)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
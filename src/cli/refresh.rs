@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use clap::Args;
+use error_stack::ResultExt;
+use git2::Repository;
+
+use crate::{
+    configs::{Config, Window},
+    dirty_paths::DirtyUtf8Path,
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct RefreshCommand {
+    /// The session's name. If not provided gets current session
+    name: Option<String>,
+}
+
+impl TmsCommand for RefreshCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        let tmux = ctx.tmux;
+        refresh_command(self, ctx.config()?, tmux)
+    }
+}
+
+fn refresh_command(args: &RefreshCommand, config: &Config, tmux: &Tmux) -> Result<()> {
+    let session_name = args
+        .name
+        .clone()
+        .unwrap_or(tmux.display_message("'#S'"))
+        .trim()
+        .replace('\'', "");
+    // For each window there should be the branch names
+    let session_path = tmux.session_path(&session_name);
+
+    // Projects with a layout template get its closed windows back instead of worktree windows, the
+    // same as when their session is created
+    if let Some(windows) = config.session_windows(&session_name) {
+        tmux.restore_layout(&session_name, Path::new(&session_path), windows)?;
+        return Ok(());
+    }
+
+    if let Ok(repository) = Repository::open(&session_path) {
+        let mut worktree_windows = Vec::new();
+        if let Ok(worktrees) = repository.worktrees() {
+            for worktree_name in worktrees.iter().flatten() {
+                let worktree = repository
+                    .find_worktree(worktree_name)
+                    .change_context(TmsError::GitError)?;
+                // prunable worktrees can have an invalid path so skip that
+                if worktree.is_prunable(None).unwrap_or_default() {
+                    continue;
+                }
+                worktree_windows.push(Window {
+                    name: Some(worktree_name.to_owned()),
+                    path: Some(worktree.path().to_string()?),
+                    ..Default::default()
+                });
+            }
+        }
+        tmux.restore_layout(&session_name, Path::new(&session_path), &worktree_windows)?;
+
+        //check if a window is needed for non worktree
+        if !repository.is_bare() {
+            let has_other_window = tmux
+                .list_windows("#{window_name}", Some(&session_name))
+                .lines()
+                .any(|name| {
+                    !worktree_windows
+                        .iter()
+                        .any(|window| window.name.as_deref() == Some(name))
+                });
+            if !has_other_window {
+                tmux.new_window(None, Some(&session_path), Some(&session_name));
+            }
+        }
+    }
+
+    Ok(())
+}
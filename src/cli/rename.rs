@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use error_stack::ResultExt;
+
+#[cfg(not(feature = "no-external-tools"))]
+use crate::execute_command;
+use crate::{dirty_paths::DirtyUtf8Path, i18n::tr_fmt, tmux::Tmux, Result, TmsError};
+
+use super::{Context, TmsCommand};
+
+/// The programs a pane can run for `cd` to be typed into it
+const SHELLS: &[&str] = &[
+    "bash", "zsh", "fish", "sh", "dash", "ksh", "mksh", "tcsh", "csh", "nu", "elvish", "xonsh",
+];
+
+#[derive(Debug, Args)]
+pub struct RenameCommand {
+    /// The new session's name
+    name: String,
+    #[arg(long)]
+    /// Also rename the session's directory to the new name, if nothing is there yet
+    move_dir: bool,
+    #[arg(long, requires = "move_dir")]
+    /// Change the directory of the panes in the moved directory to where it moved
+    update_panes: bool,
+}
+
+impl TmsCommand for RenameCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        rename_subcommand(self, ctx.tmux)
+    }
+}
+
+fn rename_subcommand(args: &RenameCommand, tmux: &Tmux) -> Result<()> {
+    let new_session_name = &args.name;
+    // Checked before anything is moved, so a failed rename doesn't leave the directory renamed
+    if tmux.session_exists(new_session_name) {
+        return Err(TmsError::IoError).attach_printable(format!(
+            "A session called {new_session_name} already exists"
+        ));
+    }
+
+    // The paths are checked before the session is renamed, and the directory is moved after it
+    // so a failed rename leaves both as they were
+    let paths = if args.move_dir {
+        let old_path = PathBuf::from(tmux.display_message("#{session_path}").trim());
+        let new_path = old_path.with_file_name(new_session_name);
+        check_move(&old_path, &new_path)?;
+        Some((old_path, new_path))
+    } else {
+        None
+    };
+    let old_session_name = tmux.display_message("#{session_name}").trim().to_owned();
+
+    let output = tmux.rename_session(new_session_name);
+    if !output.status.success() {
+        return Err(TmsError::IoError).attach_printable(format!(
+            "Could not rename the session: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    if let Some((old_path, new_path)) = paths {
+        // tmux reports the panes' directories with symlinks resolved, which can only be done
+        // before the directory is moved
+        let old_roots: Vec<PathBuf> = old_path
+            .canonicalize()
+            .ok()
+            .into_iter()
+            .chain([old_path.clone()])
+            .collect();
+        if let Err(error) = move_dir(&old_path, &new_path) {
+            // The session keeps its old name along with its directory
+            tmux.rename_session(&old_session_name);
+            return Err(error);
+        }
+        let new_path = new_path.to_string()?;
+        if !tmux.set_session_path(new_session_name, &new_path) {
+            println!(
+                "{}",
+                tr_fmt(
+                    "Could not change the directory of the session, new windows still start in {}",
+                    &[&old_path.display()]
+                )
+            );
+        }
+        if args.update_panes {
+            update_panes(&old_roots, Path::new(&new_path), tmux)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the session's directory can be moved to `new_path`, checked before anything changes
+fn check_move(old_path: &Path, new_path: &Path) -> Result<()> {
+    if !old_path.is_dir() {
+        return Err(TmsError::IoError).attach_printable(format!(
+            "The session's directory {} doesn't exist",
+            old_path.display()
+        ));
+    }
+    if new_path.exists() {
+        return Err(TmsError::IoError).attach_printable(format!(
+            "Could not move the session's directory, {} already exists",
+            new_path.display()
+        ));
+    }
+
+    Ok(())
+}
+
+fn move_dir(old_path: &Path, new_path: &Path) -> Result<()> {
+    #[cfg(not(feature = "no-external-tools"))]
+    {
+        let output = execute_command("mv", vec![old_path.to_string()?, new_path.to_string()?]);
+        if !output.status.success() {
+            return Err(TmsError::IoError).attach_printable(format!(
+                "Could not move the session's directory: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    #[cfg(feature = "no-external-tools")]
+    std::fs::rename(old_path, new_path)
+        .change_context(TmsError::IoError)
+        .attach_printable("Could not move the session's directory")?;
+
+    Ok(())
+}
+
+/// Type `cd` into the shells of the session whose directory was in the moved one, leaving the panes
+/// running other programs alone. `old_roots` are the paths the moved directory was known by
+fn update_panes(old_roots: &[PathBuf], new_path: &Path, tmux: &Tmux) -> Result<()> {
+    let session = tmux.display_message("#{session_name}");
+    let panes = tmux.list_panes(
+        "#{pane_id},#{pane_current_command},#{pane_current_path}",
+        session.trim(),
+    );
+
+    for pane in panes.lines() {
+        let mut fields = pane.splitn(3, ',');
+        let (Some(pane_id), Some(command), Some(cwd)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let Some(moved_cwd) = moved_path(Path::new(cwd), old_roots, new_path) else {
+            continue;
+        };
+        if !SHELLS.contains(&command) {
+            println!(
+                "{}",
+                tr_fmt(
+                    "Not changing the directory of pane {}, it is running {}",
+                    &[&pane_id, &command]
+                )
+            );
+            continue;
+        }
+
+        let change_dir_cmd = format!("cd {}", shell_words::quote(&moved_cwd.to_string()?));
+        tmux.send_keys(&change_dir_cmd, Some(pane_id));
+    }
+
+    Ok(())
+}
+
+/// Where a directory is after the one at any of `old_roots` moved to `new_root`, if it was inside
+/// it. Only the root is replaced, so a directory further down named like the session stays as it
+/// is
+fn moved_path(path: &Path, old_roots: &[PathBuf], new_root: &Path) -> Option<PathBuf> {
+    old_roots
+        .iter()
+        .find_map(|old_root| path.strip_prefix(old_root).ok())
+        .map(|inner_path| new_root.join(inner_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_replaces_the_root_of_moved_paths() {
+        let old_roots = [PathBuf::from("/home/foo/foo")];
+        let new_root = Path::new("/home/foo/bar");
+        let moved = |path: &str| moved_path(Path::new(path), &old_roots, new_root);
+
+        assert_eq!(moved("/home/foo/foo"), Some(new_root.to_path_buf()));
+        assert_eq!(
+            moved("/home/foo/foo/src/foo"),
+            Some(PathBuf::from("/home/foo/bar/src/foo"))
+        );
+        assert_eq!(moved("/home/foo/foobar"), None);
+        assert_eq!(moved("/home/foo"), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::io::{self, Write};
+
+use clap::Args;
+use crossterm::{cursor, terminal, ExecutableCommand};
+use error_stack::ResultExt;
+use serde_derive::Serialize;
+
+use crate::{configs::Config, tmux::Tmux, Result, TmsError};
+
+use super::{print_json, Context, OutputFormat, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct SessionsCommand {
+    #[arg(long, short)]
+    /// Keep showing the sessions, one per line, refreshing when they change
+    watch: bool,
+    #[arg(
+        long,
+        value_name = "seconds",
+        default_value_t = 2,
+        requires = "watch",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    /// How often to check for changes in watch mode, at least every second
+    interval: u64,
+}
+
+impl TmsCommand for SessionsCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        let tmux = ctx.tmux;
+        let output = ctx.output();
+        // Listing the sessions only needs tmux, the config just gives them their aliases
+        let default = Config::default();
+        let config = ctx.config().unwrap_or(&default);
+        sessions_subcommand(self, output, config, tmux)
+    }
+}
+
+/// A running session, as listed by `--output json`
+#[derive(Debug, PartialEq, Serialize)]
+struct RunningSession {
+    name: String,
+    /// The configured alias the session is shown as, if it has one
+    alias: Option<String>,
+    path: String,
+    /// Whether it's the session of the client tms runs in
+    current: bool,
+    /// Whether a client is attached to it
+    attached: bool,
+}
+
+impl RunningSession {
+    /// The session as shown in the text output, by its alias with an asterisk when it's the
+    /// current one
+    fn label(&self) -> String {
+        let name = self.alias.as_deref().unwrap_or(&self.name);
+        if self.current {
+            format!("{name}*")
+        } else {
+            name.to_owned()
+        }
+    }
+}
+
+fn sessions_subcommand(
+    args: &SessionsCommand,
+    output: OutputFormat,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<()> {
+    if args.watch {
+        return watch_sessions(args.interval, output, config, tmux);
+    }
+
+    let sessions = session_list(config, tmux);
+    if output == OutputFormat::Json {
+        return print_json(&sessions);
+    }
+
+    let mut new_string = String::new();
+
+    for session in sessions {
+        new_string.push_str(&session.label());
+        new_string.push(' ')
+    }
+    println!("{new_string}");
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    tmux.refresh_client();
+
+    Ok(())
+}
+
+fn session_list(config: &Config, tmux: &Tmux) -> Vec<RunningSession> {
+    let mut current_session = tmux.display_message("'#S'");
+    current_session.retain(|x| x != '\'' && x != '\n');
+
+    tmux.list_sessions("#{session_name}\t#{session_path}\t#{session_attached}")
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?;
+            let path = fields.next()?;
+            let attached = fields.next()?;
+            let alias = config.session_alias(name);
+            Some(RunningSession {
+                name: name.to_owned(),
+                alias: (alias != name).then(|| alias.to_string()),
+                path: path.to_owned(),
+                current: name == current_session,
+                attached: attached != "0",
+            })
+        })
+        .collect()
+}
+
+/// Keep showing the sessions, one per line, or with `--output json` a line of JSON each time they
+/// change
+fn watch_sessions(interval: u64, output: OutputFormat, config: &Config, tmux: &Tmux) -> Result<()> {
+    let mut stdout = io::stdout();
+    let mut previous = Vec::new();
+
+    loop {
+        let sessions = session_list(config, tmux);
+        if sessions != previous {
+            if output == OutputFormat::Json {
+                let json = serde_json::to_string(&sessions).change_context(TmsError::IoError)?;
+                writeln!(stdout, "{json}").change_context(TmsError::IoError)?;
+            } else {
+                stdout
+                    .execute(terminal::Clear(terminal::ClearType::All))
+                    .change_context(TmsError::IoError)?
+                    .execute(cursor::MoveTo(0, 0))
+                    .change_context(TmsError::IoError)?;
+                for session in &sessions {
+                    writeln!(stdout, "{}", session.label()).change_context(TmsError::IoError)?;
+                }
+            }
+            stdout.flush().change_context(TmsError::IoError)?;
+            previous = sessions;
+        }
+        std::thread::sleep(std::time::Duration::from_secs(interval));
+    }
+}
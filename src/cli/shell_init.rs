@@ -0,0 +1,32 @@
+use std::env;
+
+use clap::Args;
+
+use crate::{
+    shell::{init_script, Shell},
+    Result,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct ShellInitCommand {
+    /// The shell to print the script for
+    shell: Shell,
+}
+
+impl TmsCommand for ShellInitCommand {
+    fn run(&self, _ctx: &mut Context) -> Result<()> {
+        // The same name the completions are generated for
+        let command = env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.file_name()
+                    .map(|exe| exe.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "tms".to_owned());
+        print!("{}", init_script(self.shell, &command));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use clap::Args;
+use error_stack::ResultExt;
+
+use crate::{configs::Config, tmux::Tmux, Result, TmsError};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct StartCommand {}
+
+impl TmsCommand for StartCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        start_command(ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn start_command(config: Config, tmux: &Tmux) -> Result<()> {
+    if let Some(sessions) = &config.sessions {
+        for session in sessions {
+            let session_path = session
+                .path
+                .as_ref()
+                .map(shellexpand::full)
+                .transpose()
+                .change_context(TmsError::IoError)?;
+
+            tmux.new_session(session.name.as_deref(), session_path.as_deref());
+
+            if let Some(windows) = &session.windows {
+                for window in windows {
+                    let window_path = window
+                        .path
+                        .as_ref()
+                        .map(shellexpand::full)
+                        .transpose()
+                        .change_context(TmsError::IoError)?;
+
+                    tmux.new_window(window.name.as_deref(), window_path.as_deref(), None);
+
+                    if let Some(window_command) = &window.command {
+                        tmux.send_keys(window_command, None);
+                    }
+                }
+                tmux.kill_window(":1");
+            }
+        }
+        tmux.attach_session(None, None)
+            .change_context(TmsError::IoError)
+            .attach_printable("Could not attach to the sessions that were started")?;
+    } else {
+        tmux.tmux();
+    }
+
+    Ok(())
+}
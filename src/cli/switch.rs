@@ -0,0 +1,173 @@
+use std::{collections::HashMap, path::Path};
+
+use clap::Args;
+
+use crate::{
+    configs::{Config, SessionEvent, SessionSortOrderConfig},
+    error::short_message,
+    frecency::sort_by_frecency,
+    i18n::{tr, tr_fmt},
+    picker::{ItemColumn, Picker, Preview},
+    session::{create_sessions, tmux_session_name, SessionContainer},
+    state::{now, State},
+    tmux::Tmux,
+    Result,
+};
+
+use super::{
+    kill::{close_session, kill_stale_sessions},
+    last::switch_to_last,
+    Context, TmsCommand,
+};
+
+#[derive(Debug, Args)]
+pub struct SwitchCommand {}
+
+impl TmsCommand for SwitchCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        switch_command(ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn switch_command(config: Config, tmux: &Tmux) -> Result<()> {
+    tmux.require_server()?;
+    kill_stale_sessions(&config, tmux);
+    let current_session = tmux.display_message("#S");
+    let current_session = current_session.trim();
+    let sessions = tmux
+        .list_sessions("'#{session_name},#{session_last_attached}'")
+        .replace('\'', "");
+    let session_paths = tmux.running_sessions();
+
+    let mut sessions: Vec<(&str, &str)> = sessions
+        .trim()
+        .split('\n')
+        .filter_map(|s| s.split_once(','))
+        .filter(|(name, _)| *name != current_session)
+        .collect();
+
+    if let Some(SessionSortOrderConfig::LastAttached) = config.session_sort_order {
+        sessions.sort_by(|a, b| b.1.cmp(a.1));
+    }
+
+    let mut sessions: Vec<String> = sessions.into_iter().map(|s| s.0.to_string()).collect();
+    if let Some(SessionSortOrderConfig::Frecency) = config.session_sort_order {
+        let weights = config.session_sort_weights.clone().unwrap_or_default();
+        sort_by_frecency(
+            &mut sessions,
+            str::to_owned,
+            &State::load()?,
+            &weights,
+            now(),
+        );
+    }
+    if let Some(true) = config.switch_filter_unknown {
+        let configured = create_sessions(&config)?;
+
+        sessions = sessions
+            .into_iter()
+            .filter(|session| configured.find_session(session).is_some())
+            .collect::<Vec<String>>();
+    }
+
+    // Aliases are shown in place of the session names, so picked items are mapped back to them
+    let aliased: HashMap<String, String> = sessions
+        .iter()
+        .map(|session| (config.session_alias(session).to_owned(), session.to_owned()))
+        .collect();
+    let items = sessions
+        .iter()
+        .map(|session| config.session_alias(session).to_owned())
+        .collect::<Vec<String>>();
+    let details = attached_clients(tmux)
+        .into_iter()
+        .map(|(session, details)| (config.session_alias(&session).to_owned(), details))
+        .collect();
+
+    let mut picker = Picker::new(
+        &items,
+        Preview::SessionPane,
+        config.shortcuts.as_ref(),
+        tmux,
+    )
+    .set_colors(config.picker_colors.as_ref())
+    .set_color_mode(config.color)
+    .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+    .set_pane_scrollback(config.preview_scrollback.unwrap_or_default())
+    .set_item_details(details)
+    .set_item_columns(|selected| {
+        let session = aliased.get(selected).map_or(selected, String::as_str);
+        session_paths
+            .get(session)
+            .map(|path| vec![display_path(path)])
+            .unwrap_or_default()
+    })
+    // Sessions can be found by their directory too
+    .set_column_options(vec![ItemColumn {
+        style: None,
+        matched: true,
+    }])
+    .set_allow_detach_others(true)
+    .set_allow_switch_last(true)
+    .set_confirm_actions(config.confirm_destructive_actions != Some(false))
+    .set_kill_session(|selected| {
+        let session = aliased.get(selected).map_or(selected, String::as_str);
+        if config.is_protected(session) {
+            return Err(tr("it is protected").to_owned());
+        }
+        let path = session_paths
+            .get(session)
+            .ok_or_else(|| tr("it is no longer running").to_owned())?;
+        close_session(session.to_owned(), path.clone(), &config, tmux)
+            .map_err(|report| short_message(&report))
+    });
+
+    if let Some(selected) = picker.run()? {
+        let target_session = aliased.get(&selected).unwrap_or(&selected);
+        let target_session = tmux_session_name(target_session);
+        if picker.confirmed_detach_others() {
+            tmux.detach_clients(&target_session);
+        }
+        if let Some(path) = session_paths.get(&target_session) {
+            tmux.run_session_hooks(SessionEvent::Attach, path, &target_session, &config)?;
+        }
+        tmux.switch_to_session(&target_session)?;
+    } else if picker.confirmed_switch_last() {
+        switch_to_last(tmux)?;
+    }
+
+    Ok(())
+}
+
+/// The path with the home directory shortened to `~`
+fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_owned(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// The number and terminals of the clients attached to each session that has any
+fn attached_clients(tmux: &Tmux) -> HashMap<String, String> {
+    let mut clients: HashMap<String, Vec<String>> = HashMap::new();
+    for line in tmux
+        .list_clients("#{client_session}\t#{client_tty}")
+        .lines()
+    {
+        if let Some((session, tty)) = line.split_once('\t') {
+            clients
+                .entry(session.to_owned())
+                .or_default()
+                .push(tty.to_owned());
+        }
+    }
+
+    clients
+        .into_iter()
+        .map(|(session, ttys)| {
+            let details = tr_fmt("({} attached: {})", &[&ttys.len(), &ttys.join(", ")]);
+            (session, details)
+        })
+        .collect()
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    fs::canonicalize,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use error_stack::ResultExt;
+use git2::{Repository, StatusOptions};
+use ratatui::style::Style;
+
+use crate::{
+    configs::Config,
+    picker::{ItemColumn, Picker, Preview},
+    preview::window_target,
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+/// The command running in a window's active pane and the pane's title, which is left out while it's
+/// tmux's default of the host name
+const PANE_COLUMNS: &str =
+    "#{pane_current_command}\t#{?#{==:#{pane_title},#{host}},,#{pane_title}}";
+
+#[derive(Debug, Args)]
+pub struct WindowsCommand {
+    #[arg(long, short)]
+    /// List the windows of every session, switching to the session of the selected one
+    all: bool,
+}
+
+impl TmsCommand for WindowsCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        ctx.tmux.require_server()?;
+        if self.all {
+            return all_windows_command(&ctx.take_config()?, ctx.tmux);
+        }
+        windows_command(&ctx.take_config()?, ctx.tmux)
+    }
+}
+
+/// Pick from the windows of all sessions as `session:index name`, leaving out the current one
+fn all_windows_command(config: &Config, tmux: &Tmux) -> Result<()> {
+    let current_window = tmux.display_message("'#S:#I'").trim().replace('\'', "");
+    let mut columns = HashMap::new();
+    let windows: Vec<String> = tmux
+        .list_all_windows(&format!(
+            "#{{session_name}}:#{{window_index}} #{{window_name}}\t{PANE_COLUMNS}"
+        ))
+        .lines()
+        .filter(|window| window_target(window).is_none_or(|target| target != current_window))
+        .map(|line| {
+            let mut fields = line.split('\t');
+            let window = fields.next().unwrap_or_default().to_owned();
+            columns.insert(window.clone(), fields.map(str::to_owned).collect());
+            window
+        })
+        .collect();
+
+    if let Some(target_window) = pick_window(&windows, columns, config, tmux)? {
+        if let Some(target) = window_target(&target_window) {
+            tmux.select_window(target);
+            if let Some((session, _)) = target.rsplit_once(':') {
+                tmux.attach_session(Some(session), None)
+                    .change_context(TmsError::IoError)
+                    .attach_printable_lazy(|| format!("Could not attach to session {session}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn windows_command(config: &Config, tmux: &Tmux) -> Result<()> {
+    let windows = tmux.list_windows(
+        &format!(
+            "'#{{?window_attached,,#{{window_id}} #{{window_name}}\t#{{pane_current_path}}\t{PANE_COLUMNS}}}'"
+        ),
+        None,
+    );
+    let session_path = tmux
+        .display_message("'#{session_path}'")
+        .trim()
+        .replace('\'', "");
+    let worktrees = session_worktrees(&session_path);
+
+    let mut columns = HashMap::new();
+    let windows: Vec<String> = windows
+        .replace('\'', "")
+        .replace("\n\n", "\n")
+        .trim()
+        .split('\n')
+        .map(|s| {
+            let mut fields = s.split('\t');
+            let window = fields.next().unwrap_or_default();
+            let window = match fields
+                .next()
+                .and_then(|path| worktree_status(&worktrees, Path::new(path)))
+            {
+                Some(status) => format!("{window} {status}"),
+                None => window.to_string(),
+            };
+            columns.insert(window.clone(), fields.map(str::to_owned).collect());
+            window
+        })
+        .collect();
+
+    if let Some(target_window) = pick_window(&windows, columns, config, tmux)? {
+        if let Some(windex) = window_target(&target_window) {
+            tmux.select_window(windex);
+        }
+    }
+    Ok(())
+}
+
+/// Pick one of the windows, with the columns of each after it
+fn pick_window(
+    windows: &[String],
+    columns: HashMap<String, Vec<String>>,
+    config: &Config,
+    tmux: &Tmux,
+) -> Result<Option<String>> {
+    Picker::new(
+        windows,
+        Preview::WindowPane,
+        config.shortcuts.as_ref(),
+        tmux,
+    )
+    .set_colors(config.picker_colors.as_ref())
+    .set_color_mode(config.color)
+    .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+    .set_pane_scrollback(config.preview_scrollback.unwrap_or_default())
+    .set_item_columns(move |window| columns.get(window).cloned().unwrap_or_default())
+    // Windows can be found by what runs in them, which stands out from the pane titles
+    .set_column_options(vec![ItemColumn {
+        style: Some(Style::default()),
+        matched: true,
+    }])
+    .run()
+}
+
+/// The working directories of the repository discovered from `path`, the main one along with all
+/// of its worktrees
+fn session_worktrees(path: &str) -> Vec<(PathBuf, Repository)> {
+    let Ok(repository) = Repository::discover(path) else {
+        return Vec::new();
+    };
+    let mut worktrees = Vec::new();
+    if let Ok(names) = repository.worktrees() {
+        for name in names.iter().flatten() {
+            if let Ok(worktree) = repository.find_worktree(name) {
+                if let Ok(repo) = Repository::open_from_worktree(&worktree) {
+                    worktrees.push((canonicalize_or_keep(worktree.path()), repo));
+                }
+            }
+        }
+    }
+    if let Some(workdir) = repository.workdir() {
+        worktrees.push((canonicalize_or_keep(workdir), repository));
+    }
+    worktrees
+}
+
+fn canonicalize_or_keep(path: &Path) -> PathBuf {
+    canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The branch and a `*` if there are uncommitted changes for the worktree containing `path`
+fn worktree_status(worktrees: &[(PathBuf, Repository)], path: &Path) -> Option<String> {
+    let (_, repo) = worktrees
+        .iter()
+        .filter(|(worktree, _)| path.starts_with(worktree))
+        .max_by_key(|(worktree, _)| worktree.components().count())?;
+    let head = repo.head().ok()?;
+    let branch = head.shorthand()?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    let dirty = repo
+        .statuses(Some(&mut options))
+        .is_ok_and(|statuses| !statuses.is_empty());
+
+    Some(format!("[{branch}{}]", if dirty { "*" } else { "" }))
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use clap::Args;
+use error_stack::ResultExt;
+use git2::Repository;
+
+use crate::{
+    configs::Config,
+    get_single_selection,
+    picker::Preview,
+    repos::{linked_worktrees, main_repository, worktree_for_ref, worktrees_dir},
+    session::{dir_name, running_sessions, Session, SessionType},
+    tmux::Tmux,
+    Result, TmsError,
+};
+
+use super::{Context, TmsCommand};
+
+#[derive(Debug, Args)]
+pub struct WorktreeCommand {
+    #[arg(value_name = "branch")]
+    /// Open the worktree with this branch checked out, adding one if there is none yet. Without
+    /// it the worktrees of the current session's repository are shown in the picker. Sessions are
+    /// named `repo[worktree]` after the worktree's directory, like the picker lists worktrees, so
+    /// the branch `feature/x` opens as `repo[feature-x]`
+    branch: Option<String>,
+}
+
+impl TmsCommand for WorktreeCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        worktree_command(self, ctx.take_config()?, ctx.tmux)
+    }
+}
+
+fn worktree_command(args: &WorktreeCommand, config: Config, tmux: &Tmux) -> Result<()> {
+    let session_path = tmux
+        .display_message("'#{session_path}'")
+        .trim()
+        .replace('\'', "");
+    // Worktrees are managed through their main repository, also when run from one of them
+    let repo = Repository::open(&session_path)
+        .change_context(TmsError::GitError)
+        .attach_printable_lazy(|| format!("{session_path} is not a git repository"))
+        .and_then(main_repository)?;
+    let project_path = repo.workdir().unwrap_or(repo.path()).to_owned();
+    let project_name = dir_name(&project_path)?;
+
+    if let Some(branch) = &args.branch {
+        let path = worktree_for_ref(&repo, branch, &worktrees_dir(&project_path, &config)?)?;
+        let worktree = Repository::open(&path).change_context(TmsError::GitError)?;
+        let name = if worktree.is_worktree() {
+            format!("{project_name}[{}]", dir_name(&path)?)
+        } else {
+            project_name
+        };
+        return Session::new(name, SessionType::Git(worktree)).switch_to(tmux, &config);
+    }
+
+    let mut worktrees: HashMap<String, Session> = linked_worktrees(&repo, &project_name)
+        .into_iter()
+        .map(|session| (session.name.clone(), session))
+        .collect();
+    if !repo.is_bare() {
+        worktrees.insert(
+            project_name.clone(),
+            Session::new(project_name, SessionType::Git(repo)),
+        );
+    }
+    let mut names: Vec<String> = worktrees.keys().cloned().collect();
+    names.sort();
+
+    let running = running_sessions(tmux);
+    let plan = |name: &str| {
+        worktrees
+            .get(name)
+            .map(|session| session.plan(&running, &config))
+            .unwrap_or_default()
+    };
+    let selected = get_single_selection(&names, Preview::Text(Box::new(plan)), &config, tmux)?;
+    if let Some(selected) = selected {
+        if let Some(session) = worktrees.get(&selected) {
+            session.switch_to(tmux, &config)?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,17 +1,43 @@
 use std::{
     fmt::Display,
-    io::{stdout, Stdout, Write},
+    io::{stdout, IsTerminal, Stdout, Write},
     path::Path,
     time::{Duration, Instant},
 };
 
-use crate::{error::TmsError, Result};
+use crate::{credentials::Credentials, error::TmsError, Result};
 
 use crossterm::{cursor, terminal, ExecutableCommand};
 use error_stack::ResultExt;
 use git2::{build::RepoBuilder, FetchOptions, Progress, RemoteCallbacks, Repository};
 
 const UPDATE_INTERVAL: Duration = Duration::from_millis(300);
+/// How often a line is printed when stdout isn't a terminal, so logs don't fill up with progress
+const LINE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How the progress of a clone is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneProgress {
+    /// Redrawn in place, for terminals
+    Terminal,
+    /// A plain line every so often, for when stdout is a file or a pipe
+    Lines,
+    /// Not at all
+    Quiet,
+}
+
+impl CloneProgress {
+    /// Redraw the progress when stdout is a terminal, otherwise print plain lines
+    pub fn detect(quiet: bool) -> Self {
+        if quiet {
+            Self::Quiet
+        } else if stdout().is_terminal() {
+            Self::Terminal
+        } else {
+            Self::Lines
+        }
+    }
+}
 
 struct Rate(usize);
 
@@ -28,6 +54,7 @@ impl Display for Rate {
 }
 
 struct CloneSnapshot {
+    mode: CloneProgress,
     time: Instant,
     bytes_transferred: usize,
     stdout: Stdout,
@@ -35,9 +62,10 @@ struct CloneSnapshot {
 }
 
 impl CloneSnapshot {
-    pub fn new() -> Self {
+    pub fn new(mode: CloneProgress) -> Self {
         let stdout = stdout();
         Self {
+            mode,
             time: Instant::now(),
             bytes_transferred: 0,
             stdout,
@@ -48,7 +76,12 @@ impl CloneSnapshot {
     pub fn update(&mut self, progress: &Progress) -> Result<()> {
         let now = Instant::now();
         let difference = now.duration_since(self.time);
-        if difference < UPDATE_INTERVAL {
+        let interval = match self.mode {
+            CloneProgress::Terminal => UPDATE_INTERVAL,
+            CloneProgress::Lines => LINE_INTERVAL,
+            CloneProgress::Quiet => return Ok(()),
+        };
+        if difference < interval || progress.total_objects() == 0 {
             return Ok(());
         }
 
@@ -60,6 +93,13 @@ impl CloneSnapshot {
 
         let total = (network_pct + index_pct) / 2;
 
+        if self.mode == CloneProgress::Lines {
+            self.print_line(progress, network_pct, index_pct, &rate)?;
+            self.time = Instant::now();
+            self.bytes_transferred = progress.received_bytes();
+            return Ok(());
+        }
+
         if self.lines > 0 {
             self.stdout
                 .execute(cursor::MoveUp(self.lines))
@@ -101,7 +141,9 @@ impl CloneSnapshot {
         }
 
         if progress.total_objects() > 0 && progress.received_objects() == progress.total_objects() {
-            let delta_pct = (100 * progress.indexed_deltas()) / progress.total_deltas();
+            let delta_pct = (100 * progress.indexed_deltas())
+                .checked_div(progress.total_deltas())
+                .unwrap_or(100);
             writeln!(
                 self.stdout,
                 "Resolving deltas {:3}% ({:5}/{:5})",
@@ -124,36 +166,62 @@ impl CloneSnapshot {
 
         Ok(())
     }
-}
 
-pub fn git_clone(repo: &str, target: &Path) -> Result<Repository> {
-    let mut callbacks = RemoteCallbacks::new();
-    callbacks.credentials(git_credentials_callback);
-
-    let mut state = CloneSnapshot::new();
-    callbacks.transfer_progress(move |progress| {
-        state.update(&progress).ok();
-        true
-    });
-    let mut fo = FetchOptions::new();
-    fo.remote_callbacks(callbacks);
-    let mut builder = RepoBuilder::new();
-    builder.fetch_options(fo);
-
-    builder
-        .clone(repo, target)
-        .change_context(TmsError::GitError)
+    /// The progress on a single line, without moving the cursor or drawing a bar
+    fn print_line(
+        &mut self,
+        progress: &Progress,
+        network_pct: usize,
+        index_pct: usize,
+        rate: &Rate,
+    ) -> Result<()> {
+        if progress.received_objects() < progress.total_objects() {
+            writeln!(
+                self.stdout,
+                "Received {}% ({}/{}), indexed {}%, {}",
+                network_pct,
+                progress.received_objects(),
+                progress.total_objects(),
+                index_pct,
+                rate,
+            )
+        } else {
+            let delta_pct = (100 * progress.indexed_deltas())
+                .checked_div(progress.total_deltas())
+                .unwrap_or(100);
+            writeln!(
+                self.stdout,
+                "Resolving deltas {}% ({}/{})",
+                delta_pct,
+                progress.indexed_deltas(),
+                progress.total_deltas(),
+            )
+        }
+        .change_context(TmsError::IoError)
+    }
 }
 
-fn git_credentials_callback(
-    user: &str,
-    user_from_url: Option<&str>,
-    _cred: git2::CredentialType,
-) -> std::result::Result<git2::Cred, git2::Error> {
-    let user = match user_from_url {
-        Some(user) => user,
-        None => user,
+pub fn git_clone(repo: &str, target: &Path, progress: CloneProgress) -> Result<Repository> {
+    let mut credentials = Credentials::default();
+    let cloned = {
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| credentials.get(url, username, allowed));
+
+        let mut state = CloneSnapshot::new(progress);
+        callbacks.transfer_progress(move |progress| {
+            state.update(&progress).ok();
+            true
+        });
+        let mut fo = FetchOptions::new();
+        fo.remote_callbacks(callbacks);
+        let mut builder = RepoBuilder::new();
+        builder.fetch_options(fo);
+
+        builder
+            .clone(repo, target)
+            .change_context(TmsError::GitError)?
     };
+    credentials.approve();
 
-    git2::Cred::ssh_key_from_agent(user)
+    Ok(cloned)
 }
@@ -0,0 +1,107 @@
+//! The state of the repositories shown next to their names in the picker
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use git2::{Repository, StatusOptions};
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ValueEnum, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum PickerColumn {
+    /// The checked out branch, or the short commit id when the head is detached
+    Branch,
+    /// How long ago the checked out commit was made
+    LastCommit,
+    /// `*` when there are changes that aren't committed
+    Dirty,
+    /// How many commits the branch is ahead and behind its upstream
+    AheadBehind,
+}
+
+impl PickerColumn {
+    /// The value of the column for the repository, empty when it doesn't have one
+    pub fn value(self, repo: &Repository) -> String {
+        match self {
+            PickerColumn::Branch => branch(repo),
+            PickerColumn::LastCommit => last_commit(repo),
+            PickerColumn::Dirty => dirty(repo),
+            PickerColumn::AheadBehind => ahead_behind(repo),
+        }
+        .unwrap_or_default()
+    }
+}
+
+fn branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    if head.is_branch() {
+        return head.shorthand().map(str::to_owned);
+    }
+    let id = head.peel_to_commit().ok()?.id().to_string();
+    Some(id[..7].to_owned())
+}
+
+fn last_commit(repo: &Repository) -> Option<String> {
+    let time = repo.head().ok()?.peel_to_commit().ok()?.time().seconds();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let age = now.saturating_sub(u64::try_from(time).ok()?);
+    Some(format_age(age))
+}
+
+fn dirty(repo: &Repository) -> Option<String> {
+    if repo.is_bare() {
+        return None;
+    }
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options)).ok()?;
+    Some(if statuses.is_empty() { "" } else { "*" }.to_owned())
+}
+
+fn ahead_behind(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    let branch = git2::Branch::wrap(head);
+    let local = branch.get().target()?;
+    let upstream = branch.upstream().ok()?.get().target()?;
+    let (ahead, behind) = repo.graph_ahead_behind(local, upstream).ok()?;
+    let value = match (ahead, behind) {
+        (0, 0) => String::new(),
+        (ahead, 0) => format!("↑{ahead}"),
+        (0, behind) => format!("↓{behind}"),
+        (ahead, behind) => format!("↑{ahead}↓{behind}"),
+    };
+    Some(value)
+}
+
+/// A number of seconds in the largest unit that fits, like `5m`, `3h` or `2w`
+pub(crate) fn format_age(seconds: u64) -> String {
+    let units = [
+        ("y", 365 * 24 * 60 * 60),
+        ("mo", 30 * 24 * 60 * 60),
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+    ];
+    units
+        .iter()
+        .find(|(_, length)| seconds >= *length)
+        .map(|(unit, length)| format!("{}{unit}", seconds / length))
+        .unwrap_or_else(|| "now".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_ages_in_the_largest_unit() {
+        assert_eq!(format_age(30), "now");
+        assert_eq!(format_age(5 * 60 + 59), "5m");
+        assert_eq!(format_age(3 * 24 * 60 * 60), "3d");
+        assert_eq!(format_age(45 * 24 * 60 * 60), "1mo");
+        assert_eq!(format_age(800 * 24 * 60 * 60), "2y");
+    }
+}
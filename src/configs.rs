@@ -1,11 +1,30 @@
 use clap::ValueEnum;
+use config::{builder::DefaultState, ConfigBuilder};
 use error_stack::ResultExt;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
-use std::{collections::HashMap, env, fmt::Display, fs::canonicalize, io::Write, path::PathBuf};
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs::canonicalize,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
 
 use ratatui::style::{Color, Style, Stylize};
 
-use crate::{error::Suggestion, keymap::Keymap};
+use crate::{
+    columns::PickerColumn,
+    error::Suggestion,
+    files::{write_atomic, FileLock},
+    forge::{CloneProtocol, Forge},
+    i18n::{tr, tr_fmt},
+    keymap::Keymap,
+    remote::RemoteConfig,
+    sort::{parse_sort_keys, SortKey},
+};
 
 type Result<T> = error_stack::Result<T, ConfigError>;
 
@@ -24,17 +43,17 @@ impl std::error::Error for ConfigError {}
 impl Display for ConfigError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::NoDefaultSearchPath => write!(f, "No default search path was found"),
-            Self::NoValidSearchPath => write!(f, "No valid search path was found"),
-            Self::TomlError => write!(f, "Could not serialize config to TOML"),
-            Self::FileWriteError => write!(f, "Could not write to config file"),
-            Self::LoadError => write!(f, "Could not load configuration"),
-            Self::IoError => write!(f, "IO error"),
+            Self::NoDefaultSearchPath => f.write_str(tr("No default search path was found")),
+            Self::NoValidSearchPath => f.write_str(tr("No valid search path was found")),
+            Self::TomlError => f.write_str(tr("Could not serialize config to TOML")),
+            Self::FileWriteError => f.write_str(tr("Could not write to config file")),
+            Self::LoadError => f.write_str(tr("Could not load configuration")),
+            Self::IoError => f.write_str(tr("IO error")),
         }
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Config {
     pub default_session: Option<String>,
     pub display_full_path: Option<bool>,
@@ -42,6 +61,7 @@ pub struct Config {
     pub recursive_submodules: Option<bool>,
     pub switch_filter_unknown: Option<bool>,
     pub session_sort_order: Option<SessionSortOrderConfig>,
+    pub session_sort_weights: Option<SortWeights>,
     pub excluded_dirs: Option<Vec<String>>,
     pub search_paths: Option<Vec<String>>, // old format, deprecated
     pub search_dirs: Option<Vec<SearchDirectory>>,
@@ -52,6 +72,36 @@ pub struct Config {
     pub session_configs: Option<HashMap<String, SessionConfig>>,
     pub marks: Option<HashMap<String, String>>,
     pub clone_repo_switch: Option<CloneRepoSwitchConfig>,
+    pub color: Option<ColorMode>,
+    pub explicit_search_mode: Option<bool>,
+    pub worktree_windows: Option<bool>,
+    pub pick_worktrees: Option<bool>,
+    pub project_colors: Option<bool>,
+    pub session_aliases: Option<HashMap<String, String>>,
+    pub worktrees_dir: Option<String>,
+    pub team_config: Option<String>,
+    pub skip_hidden_dirs: Option<bool>,
+    pub respect_gitignore: Option<bool>,
+    pub hooks: Option<HooksConfig>,
+    pub auto_kill_stale: Option<String>,
+    pub protected_sessions: Option<Vec<String>>,
+    pub event_log: Option<bool>,
+    pub remotes: Option<Vec<RemoteConfig>>,
+    pub clone_protocol: Option<CloneProtocol>,
+    pub forges: Option<Vec<Forge>>,
+    pub workspace_dirs: Option<Vec<String>>,
+    pub picker_columns: Option<Vec<PickerColumn>>,
+    pub init_repo_branch: Option<String>,
+    pub picker_sort: Option<String>,
+    pub profile: Option<String>,
+    pub profiles: Option<HashMap<String, Profile>>,
+    pub preview_scrollback: Option<usize>,
+    pub confirm_destructive_actions: Option<bool>,
+    pub include: Option<Vec<String>>,
+    pub templates: Option<HashMap<String, Template>>,
+    pub show_git_status: Option<bool>,
+    pub merge_search_dirs: Option<bool>,
+    pub match_paths: Option<bool>,
 }
 
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -62,6 +112,7 @@ pub struct ConfigExport {
     pub recursive_submodules: bool,
     pub switch_filter_unknown: bool,
     pub session_sort_order: SessionSortOrderConfig,
+    pub session_sort_weights: SortWeights,
     pub excluded_dirs: Vec<String>,
     pub search_dirs: Vec<SearchDirectory>,
     pub sessions: Vec<Session>,
@@ -71,6 +122,36 @@ pub struct ConfigExport {
     pub session_configs: HashMap<String, SessionConfig>,
     pub marks: HashMap<String, String>,
     pub clone_repo_switch: CloneRepoSwitchConfig,
+    pub color: ColorMode,
+    pub explicit_search_mode: bool,
+    pub worktree_windows: bool,
+    pub pick_worktrees: bool,
+    pub project_colors: bool,
+    pub session_aliases: HashMap<String, String>,
+    pub worktrees_dir: Option<String>,
+    pub team_config: Option<String>,
+    pub skip_hidden_dirs: bool,
+    pub respect_gitignore: bool,
+    pub hooks: HooksConfig,
+    pub auto_kill_stale: Option<String>,
+    pub protected_sessions: Vec<String>,
+    pub event_log: bool,
+    pub remotes: Vec<RemoteConfig>,
+    pub clone_protocol: CloneProtocol,
+    pub forges: Vec<Forge>,
+    pub workspace_dirs: Vec<String>,
+    pub picker_columns: Vec<PickerColumn>,
+    pub init_repo_branch: Option<String>,
+    pub picker_sort: Option<String>,
+    pub profile: Option<String>,
+    pub profiles: HashMap<String, Profile>,
+    pub preview_scrollback: usize,
+    pub confirm_destructive_actions: bool,
+    pub include: Vec<String>,
+    pub templates: HashMap<String, Template>,
+    pub show_git_status: bool,
+    pub merge_search_dirs: bool,
+    pub match_paths: bool,
 }
 
 impl From<Config> for ConfigExport {
@@ -82,6 +163,7 @@ impl From<Config> for ConfigExport {
             recursive_submodules: value.recursive_submodules.unwrap_or_default(),
             switch_filter_unknown: value.switch_filter_unknown.unwrap_or_default(),
             session_sort_order: value.session_sort_order.unwrap_or_default(),
+            session_sort_weights: value.session_sort_weights.unwrap_or_default(),
             excluded_dirs: value.excluded_dirs.unwrap_or_default(),
             search_dirs: value.search_dirs.unwrap_or_default(),
             sessions: value.sessions.unwrap_or_default(),
@@ -97,76 +179,105 @@ impl From<Config> for ConfigExport {
             session_configs: value.session_configs.unwrap_or_default(),
             marks: value.marks.unwrap_or_default(),
             clone_repo_switch: value.clone_repo_switch.unwrap_or_default(),
+            color: value.color.unwrap_or_default(),
+            explicit_search_mode: value.explicit_search_mode.unwrap_or_default(),
+            worktree_windows: value.worktree_windows.unwrap_or(true),
+            pick_worktrees: value.pick_worktrees.unwrap_or_default(),
+            project_colors: value.project_colors.unwrap_or_default(),
+            session_aliases: value.session_aliases.unwrap_or_default(),
+            worktrees_dir: value.worktrees_dir,
+            team_config: value.team_config,
+            skip_hidden_dirs: value.skip_hidden_dirs.unwrap_or_default(),
+            respect_gitignore: value.respect_gitignore.unwrap_or_default(),
+            hooks: value.hooks.unwrap_or_default(),
+            auto_kill_stale: value.auto_kill_stale,
+            protected_sessions: value.protected_sessions.unwrap_or_default(),
+            event_log: value.event_log.unwrap_or_default(),
+            remotes: value.remotes.unwrap_or_default(),
+            clone_protocol: value.clone_protocol.unwrap_or_default(),
+            forges: value.forges.unwrap_or_else(|| vec![Forge::Github]),
+            workspace_dirs: value.workspace_dirs.unwrap_or_default(),
+            picker_columns: value.picker_columns.unwrap_or_default(),
+            init_repo_branch: value.init_repo_branch,
+            picker_sort: value.picker_sort,
+            profile: value.profile,
+            profiles: value.profiles.unwrap_or_default(),
+            preview_scrollback: value.preview_scrollback.unwrap_or_default(),
+            confirm_destructive_actions: value.confirm_destructive_actions.unwrap_or(true),
+            include: value.include.unwrap_or_default(),
+            templates: value.templates.unwrap_or_default(),
+            show_git_status: value.show_git_status.unwrap_or_default(),
+            merge_search_dirs: value.merge_search_dirs.unwrap_or_default(),
+            match_paths: value.match_paths.unwrap_or_default(),
         }
     }
 }
 
 impl Config {
     pub(crate) fn new() -> Result<Self> {
-        let config_builder = match env::var("TMS_CONFIG_FILE") {
-            Ok(path) => {
-                config::Config::builder().add_source(config::File::with_name(&path).required(false))
+        let profile = env::var("TMS_PROFILE").ok();
+        Self::with_overrides(&[], profile.as_deref())
+    }
+
+    /// The configuration made of layers, each overriding the keys of the ones before it:
+    ///
+    /// 1. the files listed in the config file's `include`, in order
+    /// 2. the config file itself
+    /// 3. the override file of the `profile`, given with `--profile` or `TMS_PROFILE`
+    /// 4. `key=value` overrides, given with `--set` on the command line
+    ///
+    /// Tables are merged key by key, any other value replaces the one below it. The profile also
+    /// becomes the active one when the config has a `[profiles.<name>]` table for it, and it's an
+    /// error when there is neither the table nor the file
+    pub(crate) fn with_overrides(overrides: &[String], profile: Option<&str>) -> Result<Self> {
+        let stored = Self::stored()?;
+        let mut config_builder = config::Config::builder();
+        for include in stored.include.iter().flatten() {
+            config_builder = config_builder.add_source(toml_file(&include_path(include)?));
+        }
+        config_builder = add_stored_sources(config_builder)?;
+        let profile = profile.filter(|name| !name.is_empty());
+        let override_file = profile.map(profile_override_file).transpose()?.flatten();
+        if let Some(path) = &override_file {
+            config_builder = config_builder.add_source(toml_file(path));
+        }
+        if !overrides.is_empty() {
+            config_builder = config_builder.add_source(config::File::from_str(
+                &overrides_toml(overrides)?,
+                config::FileFormat::Toml,
+            ));
+        }
+        let mut config = deserialize(config_builder)?;
+
+        if let Some(name) = profile {
+            let has_table = config
+                .profiles
+                .as_ref()
+                .is_some_and(|profiles| profiles.contains_key(name));
+            if has_table {
+                config.profile = Some(name.to_owned());
+            } else if override_file.is_none() {
+                return Err(ConfigError::LoadError)
+                    .attach_printable(format!("There is no profile named {name}"))
+                    .attach(Suggestion(
+                        "Add it to the config file as a `[profiles.<name>]` table, or create a <name>.toml file next to the config file",
+                    ));
             }
-            Err(e) => match e {
-                env::VarError::NotPresent => {
-                    let mut builder = config::Config::builder();
-                    let mut config_found = false; // Stores whether a valid config file was found
-                    if let Some(home_path) = dirs::home_dir() {
-                        config_found = true;
-                        let path = home_path.as_path().join(".config/tms/config.toml");
-                        builder = builder.add_source(config::File::from(path).required(false));
-                    }
-                    if let Some(config_path) = dirs::config_dir() {
-                        config_found = true;
-                        let path = config_path.as_path().join("tms/config.toml");
-                        builder = builder.add_source(config::File::from(path).required(false));
-                    }
-                    if !config_found {
-                        return Err(ConfigError::LoadError)
-                            .attach_printable("Could not find a valid location for config file (both home and config dirs cannot be found)")
-                            .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."));
-                    }
-                    builder
-                }
-                env::VarError::NotUnicode(_) => {
-                    return Err(ConfigError::LoadError).attach_printable(
-                        "Invalid non-unicode value for TMS_CONFIG_FILE env variable",
-                    );
-                }
-            },
-        };
-        let config = config_builder
-            .build()
-            .change_context(ConfigError::LoadError)
-            .attach_printable("Could not parse configuration")?;
-        config
-            .try_deserialize()
-            .change_context(ConfigError::LoadError)
-            .attach_printable("Could not deserialize configuration")
+        }
+        Ok(config)
+    }
+
+    /// The configuration as stored in the config file, without the files it includes or any
+    /// overrides, for changing and saving it
+    pub(crate) fn stored() -> Result<Self> {
+        deserialize(add_stored_sources(config::Config::builder())?)
     }
 
     pub(crate) fn save(&self) -> Result<()> {
         let toml_pretty = toml::to_string_pretty(self)
             .change_context(ConfigError::TomlError)?
             .into_bytes();
-        // The TMS_CONFIG_FILE envvar should be set, either by the user or when the config is
-        // loaded. However, there is a possibility it becomes unset between loading and saving
-        // the config. In this case, it will fall back to the platform-specific config folder, and
-        // if that can't be found then it's good old ~/.config
-        let path = match env::var("TMS_CONFIG_FILE") {
-            Ok(path) => PathBuf::from(path),
-            Err(_) => {
-                if let Some(config_path) = dirs::config_dir() {
-                    config_path.as_path().join("tms/config.toml")
-                } else if let Some(home_path) = dirs::home_dir() {
-                    home_path.as_path().join(".config/tms/config.toml")
-                } else {
-                    return Err(ConfigError::LoadError)
-                        .attach_printable("Could not find a valid location to write config file (both home and config dirs cannot be found)")
-                        .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."));
-                }
-            }
-        };
+        let path = config_file()?;
         let parent = path
             .parent()
             .ok_or(ConfigError::FileWriteError)
@@ -178,15 +289,192 @@ impl Config {
         std::fs::create_dir_all(parent)
             .change_context(ConfigError::FileWriteError)
             .attach_printable("Unable to create tms config folder")?;
-        let mut file = std::fs::File::create(path).change_context(ConfigError::FileWriteError)?;
-        file.write_all(&toml_pretty)
-            .change_context(ConfigError::FileWriteError)?;
+        write_atomic(&path, toml_pretty).change_context(ConfigError::FileWriteError)
+    }
+
+    /// Copy the config file to a `.bak` file next to it, returning where the copy is when there was
+    /// a file to copy
+    pub(crate) fn backup() -> Result<Option<PathBuf>> {
+        let path = config_file()?;
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let mut backup = path.clone().into_os_string();
+        backup.push(".bak");
+        let backup = PathBuf::from(backup);
+        std::fs::copy(&path, &backup)
+            .change_context(ConfigError::FileWriteError)
+            .attach_printable_lazy(|| {
+                format!("Could not copy the config to {}", backup.display())
+            })?;
+        Ok(Some(backup))
+    }
+
+    /// Keep other tms invocations from changing the config file until the lock is dropped, so
+    /// nothing they save between loading the config and saving it again is lost
+    pub(crate) fn lock() -> Result<FileLock> {
+        let path = config_file()?;
+        FileLock::acquire(&path)
+            .change_context(ConfigError::FileWriteError)
+            .attach_printable_lazy(|| format!("Could not lock config file {}", path.display()))
+    }
+
+    /// Add the search directories and excluded directories of the shared `team_config` file, which
+    /// is a path or an http(s) URL. Downloads are cached for an hour and the cached copy is used
+    /// when downloading fails
+    pub(crate) fn merge_team_config(&mut self) -> Result<()> {
+        let Some(location) = &self.team_config else {
+            return Ok(());
+        };
+        let contents = if location.starts_with("http://") || location.starts_with("https://") {
+            // Not being able to reach the team config shouldn't keep tms from working
+            match download_team_config(location)? {
+                Some(contents) => contents,
+                None => {
+                    eprintln!(
+                        "{}",
+                        tr_fmt(
+                            "Warning: could not download the team config from {}, going on without it",
+                            &[&location],
+                        )
+                    );
+                    return Ok(());
+                }
+            }
+        } else {
+            let path = shellexpand::full(location).change_context(ConfigError::IoError)?;
+            std::fs::read_to_string(path.as_ref())
+                .change_context(ConfigError::IoError)
+                .attach_printable_lazy(|| format!("Could not read the team config {location}"))?
+        };
+        let team: TeamConfig = toml::from_str(&contents)
+            .change_context(ConfigError::LoadError)
+            .attach_printable_lazy(|| format!("Invalid team config {location}"))?;
+
+        if let Some(search_dirs) = team.search_dirs {
+            self.search_dirs
+                .get_or_insert_with(Vec::new)
+                .extend(search_dirs);
+        }
+        if let Some(excluded_dirs) = team.excluded_dirs {
+            self.excluded_dirs
+                .get_or_insert_with(Vec::new)
+                .extend(excluded_dirs);
+        }
+        Ok(())
+    }
+
+    /// Restrict the projects to the ones of the active `profile`, or without one list the bookmarks
+    /// of every group along with the others
+    pub(crate) fn apply_profile(&mut self) -> Result<()> {
+        let Some(name) = self.profile.clone() else {
+            let grouped: Vec<String> = self
+                .profiles
+                .iter()
+                .flatten()
+                .flat_map(|(_, profile)| profile.bookmarks.iter().flatten().cloned())
+                .collect();
+            if !grouped.is_empty() {
+                self.bookmarks.get_or_insert_with(Vec::new).extend(grouped);
+            }
+            return Ok(());
+        };
+        let Some(profile) = self
+            .profiles
+            .as_mut()
+            .and_then(|profiles| profiles.remove(&name))
+        else {
+            return Err(ConfigError::LoadError)
+                .attach_printable(format!("There is no profile named {name}"))
+                .attach(Suggestion(
+                    "Add it to the config file as a `[profiles.<name>]` table, or bookmark a directory with `tms bookmark --group <name>`",
+                ));
+        };
+
+        if let Some(search_dirs) = profile.search_dirs {
+            self.search_dirs = Some(search_dirs);
+            self.search_paths = None;
+            self.workspace_dirs = None;
+        }
+        self.bookmarks = profile.bookmarks;
+        if let Some(sessions) = profile.sessions {
+            self.sessions = Some(sessions);
+        }
+        if let Some(default_session) = profile.default_session {
+            self.default_session = Some(default_session);
+        }
+        if let Some(excluded_dirs) = profile.excluded_dirs {
+            self.excluded_dirs
+                .get_or_insert_with(Vec::new)
+                .extend(excluded_dirs);
+        }
         Ok(())
     }
 
+    /// The directories to search, where those another one already searches as deep are left out
+    /// with `merge_search_dirs`
     pub fn search_dirs(&self) -> Result<Vec<SearchDirectory>> {
+        let search_dirs = self.listed_search_dirs()?;
+        if self.merge_search_dirs == Some(true) {
+            return Ok(merge_nested_search_dirs(search_dirs));
+        }
+        Ok(search_dirs)
+    }
+
+    /// Warnings about search directories inside another one that reaches into them, or listed twice.
+    /// Such a directory is searched as deep as the deepest of them goes, and its projects are listed
+    /// once with the settings of the search directory that gets to them first. There are none when
+    /// `merge_search_dirs` leaves those directories out
+    pub fn search_dir_warnings(&self) -> Vec<String> {
+        if self.merge_search_dirs == Some(true) {
+            return Vec::new();
+        }
+        let search_dirs = self.listed_search_dirs().unwrap_or_default();
+        let mut warnings = Vec::new();
+        for (i, outer) in search_dirs.iter().enumerate() {
+            for (j, inner) in search_dirs.iter().enumerate() {
+                let Some(levels) = levels_below(&outer.path, &inner.path) else {
+                    continue;
+                };
+                if levels == 0 && i < j {
+                    warnings.push(tr_fmt(
+                        "Warning: {} is in the search directories twice",
+                        &[&inner.path.display()],
+                    ));
+                } else if levels > 0 && outer.depth > levels {
+                    warnings.push(tr_fmt(
+                        "Warning: the search directory {} is inside {}, which already searches it {} levels deep",
+                        &[&inner.path.display(), &outer.path.display(), &(outer.depth - levels)],
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Print the [`search_dir_warnings`](Self::search_dir_warnings) to stderr, with how to fix them
+    pub(crate) fn print_search_dir_warnings(&self) {
+        let warnings = self.search_dir_warnings();
+        for warning in &warnings {
+            eprintln!("{warning}");
+        }
+        if !warnings.is_empty() {
+            eprintln!(
+                "{}",
+                tr("Remove the search directories inside other ones from search_dirs, or set `merge_search_dirs = true` to leave out the ones another search directory searches as deep")
+            );
+        }
+    }
+
+    /// The search directories as they are listed in the config, with their paths expanded
+    fn listed_search_dirs(&self) -> Result<Vec<SearchDirectory>> {
+        let has_workspaces = self
+            .workspace_dirs
+            .as_ref()
+            .is_some_and(|dirs| !dirs.is_empty());
         if self.search_dirs.as_ref().map_or(true, Vec::is_empty)
             && self.search_paths.as_ref().map_or(true, Vec::is_empty)
+            && !has_workspaces
         {
             return Err(ConfigError::NoDefaultSearchPath)
             .attach_printable(
@@ -204,7 +492,10 @@ impl Config {
 
                     let path = canonicalize(expanded_path).ok()?;
 
-                    Some(SearchDirectory::new(path, search_dir.depth))
+                    Some(SearchDirectory {
+                        path,
+                        ..search_dir.clone()
+                    })
                 })
                 .collect()
         } else {
@@ -223,7 +514,7 @@ impl Config {
             }
         }
 
-        if search_dirs.is_empty() {
+        if search_dirs.is_empty() && !has_workspaces {
             return Err(ConfigError::NoValidSearchPath)
             .attach_printable(
                 "You must configure at least one valid search path with the `config` subcommand. E.g `tms config` "
@@ -233,8 +524,19 @@ impl Config {
         Ok(search_dirs)
     }
 
-    pub fn add_bookmark(&mut self, path: String) {
-        let bookmarks = &mut self.bookmarks;
+    /// Bookmark the path, in the group of the profile called `group` when one is given
+    pub fn add_bookmark(&mut self, path: String, group: Option<&str>) {
+        let bookmarks = match group {
+            Some(group) => {
+                &mut self
+                    .profiles
+                    .get_or_insert_with(HashMap::new)
+                    .entry(group.to_owned())
+                    .or_default()
+                    .bookmarks
+            }
+            None => &mut self.bookmarks,
+        };
         match bookmarks {
             Some(ref mut bookmarks) => {
                 if !bookmarks.contains(&path) {
@@ -242,37 +544,177 @@ impl Config {
                 }
             }
             None => {
-                self.bookmarks = Some(vec![path]);
+                *bookmarks = Some(vec![path]);
             }
         }
     }
 
-    pub fn delete_bookmark(&mut self, path: String) {
-        if let Some(ref mut bookmarks) = self.bookmarks {
+    pub fn delete_bookmark(&mut self, path: String, group: Option<&str>) {
+        let bookmarks = match group {
+            Some(group) => self
+                .profiles
+                .as_mut()
+                .and_then(|profiles| profiles.get_mut(group))
+                .and_then(|profile| profile.bookmarks.as_mut()),
+            None => self.bookmarks.as_mut(),
+        };
+        if let Some(bookmarks) = bookmarks {
             if let Some(idx) = bookmarks.iter().position(|bookmark| *bookmark == path) {
                 bookmarks.remove(idx);
             }
         }
     }
 
+    /// Whether windows should be opened for the worktrees of the session's repository
+    pub fn worktree_windows(&self, session_name: &str) -> bool {
+        self.session_config(session_name)
+            .and_then(|session_config| session_config.worktree_windows)
+            .or(self.worktree_windows)
+            .unwrap_or(true)
+    }
+
+    /// Whether the worktrees opened as windows should be picked when creating the session
+    pub fn pick_worktrees(&self, session_name: &str) -> bool {
+        self.session_config(session_name)
+            .and_then(|session_config| session_config.pick_worktrees)
+            .or(self.pick_worktrees)
+            .unwrap_or_default()
+    }
+
+    /// The accent color of a session, either configured for it or, with `project_colors`
+    /// enabled, picked from the session's name so it stays the same every time
+    pub fn project_color(&self, session_name: &str) -> Option<Color> {
+        self.session_config(session_name)
+            .and_then(|session_config| session_config.color)
+            .or_else(|| (self.project_colors == Some(true)).then(|| name_color(session_name)))
+    }
+
+    /// The script to run when creating the session, `.tms-create` in the project unless another one
+    /// is configured
+    pub fn create_script(&self, session_name: &str, project_path: &Path) -> PathBuf {
+        self.session_config(session_name)
+            .and_then(|session_config| session_config.create_script.clone())
+            .unwrap_or_else(|| project_path.join(".tms-create"))
+    }
+
+    /// The commands to run on `event` for the session, the global hook before the session's own
+    pub fn hooks(&self, event: SessionEvent, session_name: &str) -> Vec<&str> {
+        [
+            self.hooks.as_ref(),
+            self.session_config(session_name)
+                .and_then(|session_config| session_config.hooks.as_ref()),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|hooks| hooks.command(event))
+        .collect()
+    }
+
+    /// How long a session can go without being attached before it is killed, if it should be
+    pub fn stale_session_age(&self) -> Result<Option<Duration>> {
+        self.auto_kill_stale
+            .as_deref()
+            .map(|age| {
+                parse_duration(age)
+                    .ok_or(ConfigError::LoadError)
+                    .attach_printable_lazy(|| format!("Invalid auto_kill_stale duration `{age}`"))
+                    .attach(Suggestion(
+                        "Use a number with a unit like `90m`, `12h`, `30d` or `2w`",
+                    ))
+            })
+            .transpose()
+    }
+
+    /// The keys the picker sorts the projects by before anything is typed, if they are set
+    pub fn picker_sort_keys(&self) -> Result<Option<Vec<SortKey>>> {
+        self.picker_sort
+            .as_deref()
+            .map(|expression| {
+                parse_sort_keys(expression)
+                    .or_else(|error| Err(ConfigError::LoadError).attach_printable(error))
+                    .attach_printable_lazy(|| format!("Invalid picker_sort `{expression}`"))
+                    .attach(Suggestion(
+                        "Use keys like `recency desc, name asc`, out of `name`, `path`, `recency`, `frequency` and `frecency`",
+                    ))
+            })
+            .transpose()
+    }
+
+    /// The template configured under `[templates.<name>]`
+    pub fn template(&self, name: &str) -> Result<&Template> {
+        self.templates
+            .as_ref()
+            .and_then(|templates| templates.get(name))
+            .ok_or(ConfigError::LoadError)
+            .attach_printable_lazy(|| format!("There is no template named {name}"))
+            .attach(Suggestion(
+                "Add it to the config file as a `[templates.<name>]` table with a `path` or a `command`",
+            ))
+    }
+
+    /// Whether the session is listed in `protected_sessions`, so it is only killed when forced
+    pub fn is_protected(&self, session_name: &str) -> bool {
+        self.protected_sessions
+            .as_ref()
+            .is_some_and(|protected| protected.iter().any(|name| name == session_name))
+    }
+
+    /// The windows to create instead of the default one when creating the session
+    pub fn session_windows(&self, session_name: &str) -> Option<&[Window]> {
+        self.session_config(session_name)
+            .and_then(|session_config| session_config.windows.as_deref())
+    }
+
+    /// The name to show for a running tmux session, its alias if one is configured
+    pub fn session_alias<'a>(&'a self, session_name: &'a str) -> &'a str {
+        self.session_aliases
+            .as_ref()
+            .and_then(|aliases| aliases.get(session_name))
+            .map_or(session_name, String::as_str)
+    }
+
+    fn session_config(&self, session_name: &str) -> Option<&SessionConfig> {
+        self.session_configs
+            .as_ref()
+            .and_then(|configs| configs.get(session_name))
+    }
+
+    /// The directories directly inside the `workspace_dirs`, which are all projects whether they
+    /// are repositories or not
+    pub fn workspace_projects(&self) -> Vec<PathBuf> {
+        let mut projects: Vec<PathBuf> = self
+            .workspace_dirs
+            .iter()
+            .flatten()
+            .filter_map(|dir| shellexpand::full(dir).ok())
+            .filter_map(|dir| std::fs::read_dir(dir.as_ref()).ok())
+            .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_dir()
+                    && !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .filter_map(|path| path.canonicalize().ok())
+            .collect();
+        projects.sort();
+        projects
+    }
+
     pub fn bookmark_paths(&self) -> Vec<PathBuf> {
-        if let Some(bookmarks) = &self.bookmarks {
-            bookmarks
-                .iter()
-                .filter_map(|b| {
-                    if let Ok(expanded) = shellexpand::full(b) {
-                        if let Ok(path) = PathBuf::from(expanded.to_string()).canonicalize() {
-                            Some(path)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        } else {
-            Vec::new()
+        expand_bookmarks(self.bookmarks.as_ref())
+    }
+
+    /// The paths bookmarked in the group of the profile called `group`, or outside of any group
+    pub fn group_bookmark_paths(&self, group: Option<&str>) -> Vec<PathBuf> {
+        match group {
+            Some(group) => expand_bookmarks(
+                self.profiles
+                    .as_ref()
+                    .and_then(|profiles| profiles.get(group))
+                    .and_then(|profile| profile.bookmarks.as_ref()),
+            ),
+            None => self.bookmark_paths(),
         }
     }
 
@@ -297,45 +739,485 @@ impl Config {
     pub fn clear_marks(&mut self) {
         self.marks = None;
     }
+
+    /// Exchange the paths of two marks, when only one of them is set it is moved to the other index
+    pub fn swap_marks(&mut self, a: usize, b: usize) {
+        let Some(marks) = &mut self.marks else {
+            return;
+        };
+        let first = marks.remove(&a.to_string());
+        let second = marks.remove(&b.to_string());
+        if let Some(path) = first {
+            marks.insert(b.to_string(), path);
+        }
+        if let Some(path) = second {
+            marks.insert(a.to_string(), path);
+        }
+    }
+
+    /// Move a mark to another index like moving an item in a list, shifting the marks in between
+    /// by one to make room. Returns false when there is no mark at `from`
+    pub fn move_mark(&mut self, from: usize, to: usize) -> bool {
+        let Some(marks) = &mut self.marks else {
+            return false;
+        };
+        let Some(path) = marks.remove(&from.to_string()) else {
+            return false;
+        };
+        let shift = |marks: &mut HashMap<String, String>, index: usize, new_index: usize| {
+            if let Some(path) = marks.remove(&index.to_string()) {
+                marks.insert(new_index.to_string(), path);
+            }
+        };
+        if to < from {
+            for index in (to..from).rev() {
+                shift(marks, index, index + 1);
+            }
+        } else {
+            for index in from + 1..=to {
+                shift(marks, index, index - 1);
+            }
+        }
+        marks.insert(to.to_string(), path);
+        true
+    }
+
+    /// Number the marks from 0 without gaps, keeping their order
+    pub fn compact_marks(&mut self) {
+        let Some(marks) = &mut self.marks else {
+            return;
+        };
+        let mut sorted: Vec<(usize, String)> = marks
+            .drain()
+            .filter_map(|(index, path)| Some((index.parse().ok()?, path)))
+            .collect();
+        sorted.sort();
+        marks.extend(
+            sorted
+                .into_iter()
+                .enumerate()
+                .map(|(index, (_, path))| (index.to_string(), path)),
+        );
+    }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct SearchDirectory {
     pub path: PathBuf,
     pub depth: usize,
+    /// List the directories directly inside the path that aren't repositories as well
+    #[serde(alias = "include_non_git")]
+    pub include_non_git_dirs: Option<bool>,
+    /// Directories to skip in this path only, on top of the `excluded_dirs`
+    pub excluded: Option<Vec<String>>,
+    /// Search the directories symlinks point to, which is the default
+    pub follow_symlinks: Option<bool>,
+    /// The kinds of repositories that are projects in this path, only git ones by default
+    pub vcs_providers: Option<Vec<VcsProvider>>,
 }
 
 impl SearchDirectory {
     pub fn new(path: PathBuf, depth: usize) -> Self {
-        SearchDirectory { path, depth }
+        SearchDirectory {
+            path,
+            depth,
+            ..Default::default()
+        }
+    }
+
+    pub fn follows_symlinks(&self) -> bool {
+        self.follow_symlinks != Some(false)
+    }
+
+    pub fn has_provider(&self, provider: VcsProvider) -> bool {
+        match &self.vcs_providers {
+            Some(providers) => providers.contains(&provider),
+            None => provider == VcsProvider::Git,
+        }
     }
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// A version control system whose repositories are found as projects when searching
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VcsProvider {
+    Git,
+    /// Jujutsu repositories without a git working tree, which are opened as plain directories
+    Jujutsu,
+}
+
+/// A named set of projects, made active with `--profile` or `profile`, to keep e.g. the work and the
+/// personal ones apart in one config
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Profile {
+    /// The directories searched while the profile is active, instead of the `search_dirs`
+    pub search_dirs: Option<Vec<SearchDirectory>>,
+    /// The profile's group of bookmarks, added with `tms bookmark --group`, which are the only ones
+    /// listed while it's active
+    pub bookmarks: Option<Vec<String>>,
+    /// The sessions `tms start` creates while the profile is active, instead of the `sessions`
+    pub sessions: Option<Vec<Session>>,
+    /// The session to switch to when the current one is killed, instead of the `default_session`
+    pub default_session: Option<String>,
+    /// Directories skipped while the profile is active, on top of the `excluded_dirs`
+    pub excluded_dirs: Option<Vec<String>>,
+}
+
+/// A starting point for new projects, used by `tms init-repo --template <name>`
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct Template {
+    /// A directory whose contents are copied into the new project
+    pub path: Option<String>,
+    /// A shell command run in the new project's directory after copying, with the project's name
+    /// and path in `TMS_PROJECT_NAME` and `TMS_PROJECT_PATH`
+    pub command: Option<String>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Session {
     pub name: Option<String>,
     pub path: Option<String>,
     pub windows: Option<Vec<Window>>,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// Shell commands to run when something happens to a session, with the session's name and path in
+/// `TMS_SESSION_NAME` and `TMS_SESSION_PATH`
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct HooksConfig {
+    pub on_session_create: Option<String>,
+    pub on_session_attach: Option<String>,
+    pub on_session_kill: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Create,
+    Attach,
+    Kill,
+}
+
+impl HooksConfig {
+    fn command(&self, event: SessionEvent) -> Option<&str> {
+        match event {
+            SessionEvent::Create => self.on_session_create.as_deref(),
+            SessionEvent::Attach => self.on_session_attach.as_deref(),
+            SessionEvent::Kill => self.on_session_kill.as_deref(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct Window {
     pub name: Option<String>,
     pub path: Option<String>,
     pub panes: Option<Vec<Pane>>,
     pub command: Option<String>,
+    pub layout: Option<String>,
 }
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
-pub struct Pane {}
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct Pane {
+    pub path: Option<String>,
+    pub command: Option<String>,
+}
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub struct PickerColorConfig {
+    #[schemars(with = "Option<String>")]
     pub highlight_color: Option<Color>,
+    #[schemars(with = "Option<String>")]
     pub highlight_text_color: Option<Color>,
+    #[schemars(with = "Option<String>")]
     pub border_color: Option<Color>,
+    #[schemars(with = "Option<String>")]
     pub info_color: Option<Color>,
+    #[schemars(with = "Option<String>")]
     pub prompt_color: Option<Color>,
+    #[schemars(with = "Option<String>")]
+    pub error_color: Option<Color>,
+}
+
+/// The settings a team can share with `team_config`
+#[derive(Debug, Deserialize)]
+struct TeamConfig {
+    search_dirs: Option<Vec<SearchDirectory>>,
+    excluded_dirs: Option<Vec<String>>,
+}
+
+const TEAM_CONFIG_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Download the team config, or use the copy cached for the URL when it is recent or the URL can't be
+/// reached, warning about the latter. `None` when there is neither
+fn download_team_config(url: &str) -> Result<Option<String>> {
+    let cache_file = dirs::cache_dir()
+        .map(|dir| {
+            dir.join("tms/team_config")
+                .join(team_config_cache_name(url))
+        })
+        .ok_or(ConfigError::IoError)
+        .attach_printable("Could not find a valid location for the team config cache")?;
+    let cached = || std::fs::read_to_string(&cache_file).ok();
+    let fresh = std::fs::metadata(&cache_file)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            modified
+                .elapsed()
+                .is_ok_and(|age| age < TEAM_CONFIG_MAX_AGE)
+        });
+    if fresh {
+        if let Some(contents) = cached() {
+            return Ok(Some(contents));
+        }
+    }
+
+    // Built without external tools there's no curl, only a copy put in the cache by hand is used
+    let downloaded = if cfg!(feature = "no-external-tools") {
+        None
+    } else {
+        process::Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location"])
+            .args(["--max-time", "5", url])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+    };
+    match downloaded {
+        Some(contents) => {
+            if let Some(dir) = cache_file.parent() {
+                // The cache only saves downloads, not being able to write it isn't an error
+                let _ =
+                    std::fs::create_dir_all(dir).and_then(|_| write_atomic(&cache_file, &contents));
+            }
+            Ok(Some(contents))
+        }
+        None => {
+            let Some(contents) = cached() else {
+                return Ok(None);
+            };
+            eprintln!(
+                "{}",
+                tr_fmt(
+                    "Warning: could not download the team config from {}, using the copy from the last download",
+                    &[&url],
+                )
+            );
+            Ok(Some(contents))
+        }
+    }
+}
+
+/// The file the team config from `url` is cached in, so changing the URL doesn't use the copy of
+/// another one
+fn team_config_cache_name(url: &str) -> String {
+    url.split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Add the config file to the sources, which is the one in `TMS_CONFIG_FILE` or else the ones in the
+/// home and platform-specific config directories. A missing file is an empty config
+fn add_stored_sources(
+    mut builder: ConfigBuilder<DefaultState>,
+) -> Result<ConfigBuilder<DefaultState>> {
+    let builder = match env::var("TMS_CONFIG_FILE") {
+        Ok(path) => builder.add_source(config::File::with_name(&path).required(false)),
+        Err(e) => match e {
+            env::VarError::NotPresent => {
+                let mut config_found = false; // Stores whether a valid config file was found
+                if let Some(home_path) = dirs::home_dir() {
+                    config_found = true;
+                    let path = home_path.as_path().join(".config/tms/config.toml");
+                    builder = builder.add_source(config::File::from(path).required(false));
+                }
+                if let Some(config_path) = dirs::config_dir() {
+                    config_found = true;
+                    let path = config_path.as_path().join("tms/config.toml");
+                    builder = builder.add_source(config::File::from(path).required(false));
+                }
+                if !config_found {
+                    return Err(ConfigError::LoadError)
+                        .attach_printable("Could not find a valid location for config file (both home and config dirs cannot be found)")
+                        .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."));
+                }
+                builder
+            }
+            env::VarError::NotUnicode(_) => {
+                return Err(ConfigError::LoadError).attach_printable(
+                    "Invalid non-unicode value for TMS_CONFIG_FILE env variable",
+                );
+            }
+        },
+    };
+    Ok(builder)
+}
+
+fn deserialize(builder: ConfigBuilder<DefaultState>) -> Result<Config> {
+    let config = builder
+        .build()
+        .change_context(ConfigError::LoadError)
+        .attach_printable("Could not parse configuration")?;
+    config
+        .try_deserialize()
+        .change_context(ConfigError::LoadError)
+        .attach_printable("Could not deserialize configuration")
+}
+
+/// A TOML file that has to exist, whatever its extension
+fn toml_file(path: &Path) -> config::File<config::FileSourceFile, config::FileFormat> {
+    config::File::from(path)
+        .format(config::FileFormat::Toml)
+        .required(true)
+}
+
+/// The path of a file in `include`, where relative paths are relative to the config file
+fn include_path(include: &str) -> Result<PathBuf> {
+    let expanded = shellexpand::full(include)
+        .change_context(ConfigError::LoadError)
+        .attach_printable_lazy(|| format!("Could not expand the included file {include}"))?;
+    let path = PathBuf::from(expanded.as_ref());
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    let config_file = config_file()?;
+    Ok(config_file
+        .parent()
+        .map_or_else(|| path.clone(), |dir| dir.join(&path)))
+}
+
+/// How many levels below `outer` the directory `inner` is, none when it isn't inside it
+fn levels_below(outer: &Path, inner: &Path) -> Option<usize> {
+    inner
+        .strip_prefix(outer)
+        .ok()
+        .map(|rest| rest.components().count())
+}
+
+/// Leave out the search directories that another one already searches at least as deep, keeping the
+/// first of the ones listed twice
+fn merge_nested_search_dirs(search_dirs: Vec<SearchDirectory>) -> Vec<SearchDirectory> {
+    let covers = |outer: &SearchDirectory, inner: &SearchDirectory| {
+        levels_below(&outer.path, &inner.path)
+            .is_some_and(|levels| outer.depth >= levels + inner.depth)
+    };
+    let mut kept = vec![true; search_dirs.len()];
+    // The last ones are left out first, so of two that cover each other the first is kept
+    for inner in (0..search_dirs.len()).rev() {
+        kept[inner] = !(0..search_dirs.len()).any(|outer| {
+            outer != inner && kept[outer] && covers(&search_dirs[outer], &search_dirs[inner])
+        });
+    }
+    search_dirs
+        .into_iter()
+        .zip(kept)
+        .filter_map(|(search_dir, kept)| kept.then_some(search_dir))
+        .collect()
+}
+
+/// The override file of the profile, either a path or the name of a `<name>.toml` file next to the
+/// config file. Only a path has to exist, a name may just be a `[profiles.<name>]` table
+fn profile_override_file(profile: &str) -> Result<Option<PathBuf>> {
+    let is_path = profile.contains(std::path::MAIN_SEPARATOR) || profile.ends_with(".toml");
+    let path = if is_path {
+        include_path(profile)?
+    } else {
+        include_path(&format!("{profile}.toml"))?
+    };
+    if path.is_file() {
+        Ok(Some(path))
+    } else if is_path {
+        Err(ConfigError::LoadError)
+            .attach_printable(format!(
+                "There is no override file {} for the profile",
+                path.display()
+            ))
+            .attach(Suggestion(
+                "Create the file, or unset TMS_PROFILE to use the config file alone",
+            ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Where the config is saved. The TMS_CONFIG_FILE envvar should be set, either by the user or when
+/// the config is loaded. However, there is a possibility it becomes unset between loading and saving
+/// the config. In this case, it will fall back to the platform-specific config folder, and if that
+/// can't be found then it's good old ~/.config
+fn config_file() -> Result<PathBuf> {
+    match env::var("TMS_CONFIG_FILE") {
+        Ok(path) => Ok(PathBuf::from(path)),
+        Err(_) => {
+            if let Some(config_path) = dirs::config_dir() {
+                Ok(config_path.as_path().join("tms/config.toml"))
+            } else if let Some(home_path) = dirs::home_dir() {
+                Ok(home_path.as_path().join(".config/tms/config.toml"))
+            } else {
+                Err(ConfigError::LoadError)
+                    .attach_printable("Could not find a valid location to write config file (both home and config dirs cannot be found)")
+                    .attach(Suggestion("Try specifying a config file with the TMS_CONFIG_FILE environment variable."))
+            }
+        }
+    }
+}
+
+/// The bookmarked directories that exist, with `~` and environment variables expanded
+fn expand_bookmarks(bookmarks: Option<&Vec<String>>) -> Vec<PathBuf> {
+    bookmarks
+        .into_iter()
+        .flatten()
+        .filter_map(|bookmark| {
+            let expanded = shellexpand::full(bookmark).ok()?;
+            PathBuf::from(expanded.as_ref()).canonicalize().ok()
+        })
+        .collect()
+}
+
+/// A duration like `90m`, `12h`, `30d` or `2w`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let unit_start = value.find(|c: char| !c.is_ascii_digit())?;
+    let amount: u64 = value[..unit_start].parse().ok()?;
+    let unit = match value[unit_start..].trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(Duration::from_secs(amount.checked_mul(unit)?))
+}
+
+/// A TOML document setting the keys of `key=value` overrides. Values that aren't valid TOML, like
+/// bare words, are taken as strings
+fn overrides_toml(overrides: &[String]) -> Result<String> {
+    let mut document = String::new();
+    for item in overrides {
+        let Some((key, value)) = item.split_once('=') else {
+            return Err(ConfigError::LoadError)
+                .attach_printable(format!("Expected key=value but got `{item}`"));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let value = if toml::from_str::<toml::Table>(&format!("value = {value}")).is_ok() {
+            value.to_owned()
+        } else {
+            toml::Value::String(value.to_owned()).to_string()
+        };
+        document.push_str(&format!("{key} = {value}\n"));
+    }
+
+    toml::from_str::<toml::Table>(&document)
+        .change_context(ConfigError::LoadError)
+        .attach_printable("Invalid override given with --set")?;
+    Ok(document)
 }
 
 const HIGHLIGHT_COLOR_DEFAULT: Color = Color::LightBlue;
@@ -343,6 +1225,30 @@ const HIGHLIGHT_TEXT_COLOR_DEFAULT: Color = Color::Black;
 const BORDER_COLOR_DEFAULT: Color = Color::DarkGray;
 const INFO_COLOR_DEFAULT: Color = Color::LightYellow;
 const PROMPT_COLOR_DEFAULT: Color = Color::LightGreen;
+const ERROR_COLOR_DEFAULT: Color = Color::LightRed;
+
+const PROJECT_COLORS: [Color; 12] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+];
+
+fn name_color(name: &str) -> Color {
+    // FNV-1a, as the standard library's hasher may change between Rust versions
+    let hash = name.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    PROJECT_COLORS[(hash % PROJECT_COLORS.len() as u64) as usize]
+}
 
 impl PickerColorConfig {
     pub fn default_colors() -> Self {
@@ -352,6 +1258,7 @@ impl PickerColorConfig {
             border_color: Some(BORDER_COLOR_DEFAULT),
             info_color: Some(INFO_COLOR_DEFAULT),
             prompt_color: Some(PROMPT_COLOR_DEFAULT),
+            error_color: Some(ERROR_COLOR_DEFAULT),
         }
     }
 
@@ -364,6 +1271,7 @@ impl PickerColorConfig {
             border_color: self.border_color.or(Some(BORDER_COLOR_DEFAULT)),
             info_color: self.info_color.or(Some(INFO_COLOR_DEFAULT)),
             prompt_color: self.prompt_color.or(Some(PROMPT_COLOR_DEFAULT)),
+            error_color: self.error_color.or(Some(ERROR_COLOR_DEFAULT)),
         }
     }
 
@@ -407,18 +1315,29 @@ impl PickerColorConfig {
             PROMPT_COLOR_DEFAULT
         }
     }
+
+    pub fn error_color(&self) -> Color {
+        if let Some(color) = self.error_color {
+            color
+        } else {
+            ERROR_COLOR_DEFAULT
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, JsonSchema)]
 pub enum SessionSortOrderConfig {
     #[default]
     Alphabetical,
     LastAttached,
+    /// By how recently and how often tms switched to the sessions, weighed with
+    /// `session_sort_weights`
+    Frecency,
 }
 
 impl ValueEnum for SessionSortOrderConfig {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Alphabetical, Self::LastAttached]
+        &[Self::Alphabetical, Self::LastAttached, Self::Frecency]
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
@@ -429,11 +1348,37 @@ impl ValueEnum for SessionSortOrderConfig {
             SessionSortOrderConfig::LastAttached => {
                 Some(clap::builder::PossibleValue::new("LastAttached"))
             }
+            SessionSortOrderConfig::Frecency => Some(clap::builder::PossibleValue::new("Frecency")),
         }
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// How much each way of ordering sessions counts when they are sorted by `Frecency`
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct SortWeights {
+    /// Sessions used lately first, 1 by default
+    pub recency: Option<u32>,
+    /// Sessions used often first, 1 by default
+    pub frequency: Option<u32>,
+    /// Sessions by name, 0 by default
+    pub alphabetical: Option<u32>,
+}
+
+impl SortWeights {
+    pub fn recency(&self) -> u32 {
+        self.recency.unwrap_or(1)
+    }
+
+    pub fn frequency(&self) -> u32 {
+        self.frequency.unwrap_or(1)
+    }
+
+    pub fn alphabetical(&self) -> u32 {
+        self.alphabetical.unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
 pub enum CloneRepoSwitchConfig {
     #[default]
     Always,
@@ -457,7 +1402,244 @@ impl ValueEnum for CloneRepoSwitchConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+/// Whether colors are used in the picker and other output, `Auto` disables them when the
+/// `NO_COLOR` environment variable is set
+#[derive(
+    Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorMode::Auto => env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
 pub struct SessionConfig {
     pub create_script: Option<PathBuf>,
+    pub worktree_windows: Option<bool>,
+    pub pick_worktrees: Option<bool>,
+    #[schemars(with = "Option<String>")]
+    pub color: Option<Color>,
+    pub windows: Option<Vec<Window>>,
+    pub hooks: Option<HooksConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_keep_toml_values_and_quote_words() {
+        let document = overrides_toml(&[
+            "session_sort_order=LastAttached".into(),
+            "display_full_path = true".into(),
+            "picker_colors.border_color=#aaaaaa".into(),
+            r#"search_dirs=[{ path = "~/code", depth = 3 }]"#.into(),
+        ])
+        .unwrap();
+        let table: toml::Table = toml::from_str(&document).unwrap();
+
+        assert_eq!(table["session_sort_order"].as_str(), Some("LastAttached"));
+        assert_eq!(table["display_full_path"].as_bool(), Some(true));
+        assert_eq!(
+            table["picker_colors"]["border_color"].as_str(),
+            Some("#aaaaaa")
+        );
+        assert_eq!(table["search_dirs"][0]["depth"].as_integer(), Some(3));
+    }
+
+    #[test]
+    fn overrides_need_a_key_and_value() {
+        assert!(overrides_toml(&["display_full_path".into()]).is_err());
+        assert!(overrides_toml(&["=true".into()]).is_err());
+    }
+
+    #[test]
+    fn runs_global_hooks_before_the_session_ones() {
+        let config = Config {
+            hooks: Some(HooksConfig {
+                on_session_create: Some("global".into()),
+                ..Default::default()
+            }),
+            session_configs: Some(HashMap::from([(
+                "app".into(),
+                SessionConfig {
+                    hooks: Some(HooksConfig {
+                        on_session_create: Some("app".into()),
+                        on_session_kill: Some("app kill".into()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        };
+
+        assert_eq!(config.hooks(SessionEvent::Create, "app"), ["global", "app"]);
+        assert_eq!(config.hooks(SessionEvent::Kill, "app"), ["app kill"]);
+        assert_eq!(config.hooks(SessionEvent::Create, "other"), ["global"]);
+        assert!(config.hooks(SessionEvent::Attach, "app").is_empty());
+    }
+
+    #[test]
+    fn parses_durations_with_a_unit() {
+        assert_eq!(parse_duration("30d"), Some(Duration::from_secs(30 * 86400)));
+        assert_eq!(parse_duration("12h"), Some(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("3 months"), None);
+    }
+
+    #[test]
+    fn reorders_marks() {
+        let mut config = Config {
+            marks: Some(HashMap::from([
+                ("0".into(), "a".into()),
+                ("1".into(), "b".into()),
+                ("3".into(), "c".into()),
+            ])),
+            ..Default::default()
+        };
+        let marks = |config: &Config| {
+            let mut marks: Vec<(String, String)> =
+                config.marks.clone().unwrap().into_iter().collect();
+            marks.sort();
+            marks
+                .into_iter()
+                .map(|(index, path)| format!("{index}:{path}"))
+                .collect::<Vec<_>>()
+        };
+
+        config.swap_marks(0, 1);
+        assert_eq!(marks(&config), ["0:b", "1:a", "3:c"]);
+        assert!(config.move_mark(3, 0));
+        assert_eq!(marks(&config), ["0:c", "1:b", "2:a"]);
+        assert!(config.move_mark(0, 2));
+        assert_eq!(marks(&config), ["0:b", "1:a", "2:c"]);
+        assert!(!config.move_mark(5, 0));
+        config.swap_marks(2, 6);
+        config.compact_marks();
+        assert_eq!(marks(&config), ["0:b", "1:a", "2:c"]);
+    }
+
+    #[test]
+    fn merges_the_team_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let team_file = dir.path().join("team.toml");
+        std::fs::write(
+            &team_file,
+            "search_dirs = [{ path = \"/work\", depth = 2 }]\nexcluded_dirs = [\"node_modules\"]\n",
+        )
+        .unwrap();
+        let mut config = Config {
+            excluded_dirs: Some(vec![".cache".into()]),
+            team_config: Some(team_file.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+
+        config.merge_team_config().unwrap();
+
+        assert_eq!(
+            config.search_dirs,
+            Some(vec![SearchDirectory::new("/work".into(), 2)])
+        );
+        assert_eq!(
+            config.excluded_dirs,
+            Some(vec![".cache".into(), "node_modules".into()])
+        );
+    }
+
+    #[test]
+    fn merges_nested_search_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for path in ["work/api", "notes"] {
+            std::fs::create_dir_all(root.join(path)).unwrap();
+        }
+        let search_dir = |path: &str, depth| SearchDirectory::new(root.join(path), depth);
+        let mut config = Config {
+            search_dirs: Some(vec![
+                search_dir("", 3),
+                search_dir("work", 2),
+                search_dir("work/api", 4),
+                search_dir("notes", 1),
+                search_dir("notes", 1),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(config.search_dir_warnings().len(), 6);
+        config.merge_search_dirs = Some(true);
+        assert_eq!(
+            config.search_dirs().unwrap(),
+            [search_dir("", 3), search_dir("work/api", 4)]
+        );
+    }
+
+    #[test]
+    fn applies_the_settings_of_the_profile() {
+        let mut config = Config {
+            default_session: Some("main".into()),
+            excluded_dirs: Some(vec![".cache".into()]),
+            profile: Some("work".into()),
+            profiles: Some(HashMap::from([(
+                "work".into(),
+                Profile {
+                    search_dirs: Some(vec![SearchDirectory::new("/work".into(), 2)]),
+                    default_session: Some("inbox".into()),
+                    excluded_dirs: Some(vec!["vendor".into()]),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        };
+
+        config.apply_profile().unwrap();
+
+        assert_eq!(
+            config.search_dirs,
+            Some(vec![SearchDirectory::new("/work".into(), 2)])
+        );
+        assert_eq!(config.default_session.as_deref(), Some("inbox"));
+        assert_eq!(
+            config.excluded_dirs,
+            Some(vec![".cache".into(), "vendor".into()])
+        );
+    }
+
+    #[test]
+    fn reads_search_directory_settings() {
+        let config: Config = toml::from_str(
+            r#"search_dirs = [
+                { path = "/work", depth = 2 },
+                { path = "/notes", depth = 1, include_non_git = true, excluded = ["archive"], follow_symlinks = false, vcs_providers = ["jujutsu"] },
+            ]"#,
+        )
+        .unwrap();
+        let dirs = config.search_dirs.unwrap();
+
+        assert_eq!(dirs[0], SearchDirectory::new("/work".into(), 2));
+        assert!(dirs[0].follows_symlinks());
+        assert!(dirs[0].has_provider(VcsProvider::Git));
+        assert!(!dirs[0].has_provider(VcsProvider::Jujutsu));
+
+        assert_eq!(dirs[1].include_non_git_dirs, Some(true));
+        assert_eq!(dirs[1].excluded, Some(vec!["archive".into()]));
+        assert!(!dirs[1].follows_symlinks());
+        assert!(!dirs[1].has_provider(VcsProvider::Git));
+        assert!(dirs[1].has_provider(VcsProvider::Jujutsu));
+    }
 }
@@ -0,0 +1,229 @@
+//! Credentials for talking to remotes: the ssh agent for SSH, and for HTTPS the git credential
+//! helpers or else asking the user
+
+use std::{
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use git2::{Cred, CredentialType};
+
+use crate::i18n::tr_fmt;
+
+/// How many times the user is asked for a username and password before giving up
+const MAX_PROMPTS: usize = 3;
+
+/// Hands out credentials each time git asks for them, trying the next way when the last ones were
+/// turned down
+#[derive(Default)]
+pub struct Credentials {
+    ssh_agent_tried: bool,
+    helper_tried: bool,
+    prompts: usize,
+    /// The username and password handed out last, reported back to the credential helpers once
+    /// it's known whether they worked
+    last: Option<UserPass>,
+}
+
+struct UserPass {
+    url: String,
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    /// The callback for `RemoteCallbacks::credentials`
+    pub fn get(
+        &mut self,
+        url: &str,
+        username_from_url: Option<&str>,
+        allowed: CredentialType,
+    ) -> Result<Cred, git2::Error> {
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username_from_url.unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) && !self.ssh_agent_tried {
+            self.ssh_agent_tried = true;
+            return Cred::ssh_key_from_agent(username_from_url.unwrap_or("git"));
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            // Being asked again means the last ones were turned down
+            if let Some(rejected) = self.last.take() {
+                credential_helper("reject", &rejected);
+            }
+            let user_pass = self
+                .ask_helpers(url, username_from_url)
+                .or_else(|| self.ask_user(url, username_from_url));
+            if let Some(user_pass) = user_pass {
+                let cred = Cred::userpass_plaintext(&user_pass.username, &user_pass.password);
+                self.last = Some(user_pass);
+                return cred;
+            }
+        }
+
+        Err(git2::Error::from_str(&tr_fmt(
+            "Could not authenticate to {}",
+            &[&url],
+        )))
+    }
+
+    /// Let the credential helpers store the username and password that worked
+    pub fn approve(&self) {
+        if let Some(user_pass) = &self.last {
+            credential_helper("approve", user_pass);
+        }
+    }
+
+    fn ask_helpers(&mut self, url: &str, username: Option<&str>) -> Option<UserPass> {
+        if self.helper_tried {
+            return None;
+        }
+        self.helper_tried = true;
+
+        let mut request = format!("url={url}\n");
+        if let Some(username) = username {
+            request.push_str(&format!("username={username}\n"));
+        }
+        let output = run_git_credential("fill", &request)?;
+        let (username, password) = parse_credential(&output)?;
+        Some(UserPass {
+            url: url.to_owned(),
+            username,
+            password,
+        })
+    }
+
+    fn ask_user(&mut self, url: &str, username: Option<&str>) -> Option<UserPass> {
+        if self.prompts >= MAX_PROMPTS || !io::stdin().is_terminal() {
+            return None;
+        }
+        self.prompts += 1;
+
+        let username = match username {
+            Some(username) => username.to_owned(),
+            None => prompt(&tr_fmt("Username for {}: ", &[&url]), false)?,
+        };
+        let password = prompt(
+            &tr_fmt("Password or token for {}@{}: ", &[&username, &url]),
+            true,
+        )?;
+        Some(UserPass {
+            url: url.to_owned(),
+            username,
+            password,
+        })
+    }
+}
+
+/// Tell the credential helpers whether the username and password worked, with `approve` or
+/// `reject`
+fn credential_helper(action: &str, user_pass: &UserPass) {
+    let request = format!(
+        "url={}\nusername={}\npassword={}\n",
+        user_pass.url, user_pass.username, user_pass.password
+    );
+    run_git_credential(action, &request);
+}
+
+/// Run `git credential`, without it asking for what the helpers don't know, since that's done by
+/// tms itself. Built without external tools there are no helpers to ask, and nothing is run
+fn run_git_credential(action: &str, request: &str) -> Option<String> {
+    if cfg!(feature = "no-external-tools") {
+        return None;
+    }
+    let mut child = Command::new("git")
+        .args(["credential", action])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_ASKPASS", "")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("{request}\n").as_bytes())
+        .ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+/// The username and password in the output of `git credential fill`
+fn parse_credential(output: &str) -> Option<(String, String)> {
+    let mut username = None;
+    let mut password = None;
+    for line in output.lines() {
+        match line.split_once('=') {
+            Some(("username", value)) => username = Some(value.to_owned()),
+            Some(("password", value)) => password = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    Some((username?, password?))
+}
+
+/// Ask the user for a line, without showing what is typed when it's `hidden`. Nothing when the
+/// user cancels with Escape or Ctrl-C
+fn prompt(message: &str, hidden: bool) -> Option<String> {
+    print!("{message}");
+    io::stdout().flush().ok()?;
+
+    if !hidden {
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).ok()?;
+        return Some(line.trim_end_matches(['\n', '\r']).to_owned());
+    }
+
+    enable_raw_mode().ok()?;
+    let input = read_hidden();
+    disable_raw_mode().ok();
+    println!();
+    input
+}
+
+fn read_hidden() -> Option<String> {
+    let mut input = String::new();
+    loop {
+        let Event::Key(key) = event::read().ok()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Some(input),
+            KeyCode::Esc => return None,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return None,
+            KeyCode::Char(char) => input.push(char),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_output_of_credential_fill() {
+        let output = "protocol=https\nhost=github.com\nusername=tms\npassword=ghp_a=b\n";
+        assert_eq!(
+            parse_credential(output),
+            Some(("tms".to_owned(), "ghp_a=b".to_owned()))
+        );
+        assert_eq!(parse_credential("protocol=https\nusername=tms\n"), None);
+    }
+}
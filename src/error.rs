@@ -1,4 +1,12 @@
-use std::{error::Error, fmt::Display};
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use error_stack::Report;
+
+use crate::i18n::{tr, tr_fmt};
 
 pub type Result<T> = error_stack::Result<T, TmsError>;
 
@@ -10,28 +18,68 @@ pub enum TmsError {
     IoError,
     ConfigError,
     SessionNotFound(String),
+    KillRefused(String),
+    ScriptError,
+    NoServer,
+    NoPreviousSession,
+    Interrupted,
+    NotInTmux,
 }
 
 impl Display for TmsError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::ConfigError => write!(f, "Config Error"),
-            Self::GitError => write!(f, "Git Error"),
-            Self::NonUtf8Path => write!(f, "Non Utf-8 Path"),
-            Self::IoError => write!(f, "IO Error"),
-            Self::TuiError(inner) => write!(f, "TUI error: {inner}"),
-            Self::SessionNotFound(inner) => write!(f, "Session {inner} not found"),
+            Self::ConfigError => f.write_str(tr("Config Error")),
+            Self::GitError => f.write_str(tr("Git Error")),
+            Self::NonUtf8Path => f.write_str(tr("Non Utf-8 Path")),
+            Self::IoError => f.write_str(tr("IO Error")),
+            Self::TuiError(inner) => f.write_str(&tr_fmt("TUI error: {}", &[inner])),
+            Self::SessionNotFound(inner) => f.write_str(&tr_fmt("Session {} not found", &[inner])),
+            Self::KillRefused(inner) => {
+                f.write_str(&tr_fmt("Refusing to kill session {}", &[inner]))
+            }
+            Self::ScriptError => f.write_str(tr("Script Error")),
+            Self::NoServer => f.write_str(tr("No tmux server is running")),
+            Self::NoPreviousSession => f.write_str(tr("There is no previous session to switch to")),
+            Self::Interrupted => f.write_str(tr("Interrupted")),
+            Self::NotInTmux => f.write_str(tr("tms isn't running inside of tmux")),
         }
     }
 }
 
 impl Error for TmsError {}
 
+/// The error in one line for places without room for the whole report, like the status of the
+/// picker. It's the message attached last, or the error itself when there is none
+pub fn short_message(report: &Report<TmsError>) -> String {
+    report
+        .downcast_ref::<String>()
+        .cloned()
+        .unwrap_or_else(|| report.current_context().to_string())
+}
+
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable colors in error reports for the rest of the process
+pub fn set_colors(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+    Report::set_color_mode(if enabled {
+        error_stack::fmt::ColorMode::Color
+    } else {
+        error_stack::fmt::ColorMode::None
+    });
+}
+
 #[derive(Debug)]
 pub struct Suggestion(pub &'static str);
 impl Display for Suggestion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use crossterm::style::Stylize;
-        f.write_str(&format!("Suggestion: {}", self.0).green().bold().to_string())
+        let suggestion = tr_fmt("Suggestion: {}", &[&tr(self.0)]);
+        if COLORS_ENABLED.load(Ordering::Relaxed) {
+            f.write_str(&suggestion.green().bold().to_string())
+        } else {
+            f.write_str(&suggestion)
+        }
     }
 }
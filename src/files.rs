@@ -0,0 +1,117 @@
+//! Writing the files several tms invocations share, like the config and the state file, without
+//! them running into each other when e.g. a popup and a shell change them at once
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Replace the file's contents by writing them next to it and renaming them over it, so a file is
+/// never left half written for others to read. A symlink, like a config file kept in a dotfiles
+/// repository, stays in place and the file it points to is replaced instead
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = real_path(path);
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name(&path)?);
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let written = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(&path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        fs::rename(&temp_path, &path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+/// An advisory lock on a file, which other tms invocations wait for until it's dropped
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Wait until no one else holds the lock for `path`. The lock is taken on a `.lock` file next to
+    /// it, since the file itself is replaced when it's written
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        let path = real_path(path);
+        let mut lock_name = file_name(&path)?.to_owned();
+        lock_name.push(".lock");
+        let lock_path = path.with_file_name(lock_name);
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        lock(&file)?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Wait for an exclusive lock on the file, which is released when it's closed, even when tms doesn't
+/// exit cleanly
+#[cfg(unix)]
+fn lock(file: &File) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    while unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lock(_file: &File) -> io::Result<()> {
+    Ok(())
+}
+
+/// The file a symlink points to, or the path itself when it isn't one or doesn't exist yet
+fn real_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn file_name(path: &Path) -> io::Result<&std::ffi::OsStr> {
+    path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_through_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dotfiles = dir.path().join("dotfiles.toml");
+        let config = dir.path().join("config.toml");
+        fs::write(&dotfiles, "old").unwrap();
+        std::os::unix::fs::symlink(&dotfiles, &config).unwrap();
+
+        write_atomic(&config, "new").unwrap();
+
+        assert!(config.is_symlink());
+        assert_eq!(fs::read_to_string(&dotfiles).unwrap(), "new");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
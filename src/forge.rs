@@ -0,0 +1,224 @@
+//! Searching GitHub and GitLab for repositories to clone
+
+use std::{env, process};
+
+use clap::ValueEnum;
+use error_stack::ResultExt;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::Suggestion, Result, TmsError};
+
+/// How many repositories to ask each forge for
+const RESULTS: usize = 30;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ValueEnum, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Forge {
+    Github,
+    Gitlab,
+}
+
+/// The URL repositories picked from a search are cloned with
+#[derive(
+    Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ValueEnum, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneProtocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+impl Forge {
+    pub fn name(self) -> &'static str {
+        match self {
+            Forge::Github => "github",
+            Forge::Gitlab => "gitlab",
+        }
+    }
+
+    /// The repositories matching the term, which can also be `owner/repo` to search the
+    /// repositories of that owner
+    pub fn search(self, term: &str) -> Result<Vec<ForgeRepo>> {
+        let (url, token_header) = match self {
+            Forge::Github => {
+                let query = match term.split_once('/') {
+                    Some((owner, repo)) => format!("{repo} in:name user:{owner}"),
+                    None => term.to_owned(),
+                };
+                let url = format!(
+                    "https://api.github.com/search/repositories?per_page={RESULTS}&q={}",
+                    url_encode(&query)
+                );
+                let token = env::var("GITHUB_TOKEN").ok();
+                (
+                    url,
+                    token.map(|token| format!("Authorization: Bearer {token}")),
+                )
+            }
+            Forge::Gitlab => {
+                let url = format!(
+                    "https://gitlab.com/api/v4/projects?per_page={RESULTS}&order_by=star_count&search_namespaces=true&search={}",
+                    url_encode(term)
+                );
+                let token = env::var("GITLAB_TOKEN").ok();
+                (url, token.map(|token| format!("PRIVATE-TOKEN: {token}")))
+            }
+        };
+        let response = fetch(&url, token_header.as_deref())
+            .attach_printable_lazy(|| format!("Could not search {}", self.name()))?;
+        self.parse(&response)
+            .attach_printable_lazy(|| format!("Unexpected response from {}", self.name()))
+    }
+
+    fn parse(self, response: &[u8]) -> Result<Vec<ForgeRepo>> {
+        let repos = match self {
+            Forge::Github => serde_json::from_slice::<GithubSearch>(response)
+                .change_context(TmsError::IoError)?
+                .items
+                .into_iter()
+                .map(|repo| ForgeRepo {
+                    forge: self,
+                    full_name: repo.full_name,
+                    description: repo.description,
+                    https_url: repo.clone_url,
+                    ssh_url: repo.ssh_url,
+                })
+                .collect(),
+            Forge::Gitlab => serde_json::from_slice::<Vec<GitlabProject>>(response)
+                .change_context(TmsError::IoError)?
+                .into_iter()
+                .map(|project| ForgeRepo {
+                    forge: self,
+                    full_name: project.path_with_namespace,
+                    description: project.description,
+                    https_url: project.http_url_to_repo,
+                    ssh_url: project.ssh_url_to_repo,
+                })
+                .collect(),
+        };
+        Ok(repos)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ForgeRepo {
+    pub forge: Forge,
+    /// The repository with its owner, as `owner/repo`
+    pub full_name: String,
+    pub description: Option<String>,
+    pub https_url: String,
+    pub ssh_url: String,
+}
+
+impl ForgeRepo {
+    /// How the repository is listed in the picker
+    pub fn item(&self) -> String {
+        format!("{}:{}", self.forge.name(), self.full_name)
+    }
+
+    pub fn clone_url(&self, protocol: CloneProtocol) -> &str {
+        match protocol {
+            CloneProtocol::Https => &self.https_url,
+            CloneProtocol::Ssh => &self.ssh_url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct GithubSearch {
+    items: Vec<GithubRepo>,
+}
+
+#[derive(Deserialize)]
+struct GithubRepo {
+    full_name: String,
+    description: Option<String>,
+    clone_url: String,
+    ssh_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitlabProject {
+    path_with_namespace: String,
+    description: Option<String>,
+    http_url_to_repo: String,
+    ssh_url_to_repo: String,
+}
+
+/// Whether a repository given to clone-repo is something to search for rather than a URL or path
+/// to clone from
+pub fn is_search_term(repository: &str) -> bool {
+    !repository.contains(':')
+        && !repository.starts_with(['/', '.', '~'])
+        && repository.matches('/').count() <= 1
+}
+
+/// The body of a GET request, made with curl so tms doesn't need an HTTP client of its own
+fn fetch(url: &str, header: Option<&str>) -> Result<Vec<u8>> {
+    if cfg!(feature = "no-external-tools") {
+        return Err(TmsError::IoError)
+            .attach_printable("Searching for repositories needs curl, which tms is built without")
+            .attach(Suggestion("Clone the repository by its URL instead"));
+    }
+    let mut curl = process::Command::new("curl");
+    curl.args(["--silent", "--show-error", "--fail", "--location"]);
+    if let Some(header) = header {
+        curl.args(["--header", header]);
+    }
+    let output = curl
+        .arg(url)
+        .stdin(process::Stdio::null())
+        .output()
+        .change_context(TmsError::IoError)
+        .attach_printable("Could not run curl")?;
+    if !output.status.success() {
+        return Err(TmsError::IoError)
+            .attach_printable(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(output.stdout)
+}
+
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_search_terms_from_repositories() {
+        assert!(is_search_term("sessionizer"));
+        assert!(is_search_term("jrmoulton/tmux-sessionizer"));
+        assert!(!is_search_term(
+            "https://github.com/jrmoulton/tmux-sessionizer"
+        ));
+        assert!(!is_search_term(
+            "git@github.com:jrmoulton/tmux-sessionizer.git"
+        ));
+        assert!(!is_search_term("/srv/git/app.git"));
+    }
+
+    #[test]
+    fn reads_github_search_results() {
+        let response = br#"{"total_count":1,"items":[{"full_name":"jrmoulton/tmux-sessionizer","description":null,"clone_url":"https://github.com/jrmoulton/tmux-sessionizer.git","ssh_url":"git@github.com:jrmoulton/tmux-sessionizer.git","stargazers_count":800}]}"#;
+
+        let repos = Forge::Github.parse(response).unwrap();
+        assert_eq!(repos[0].item(), "github:jrmoulton/tmux-sessionizer");
+        assert_eq!(
+            repos[0].clone_url(CloneProtocol::Ssh),
+            "git@github.com:jrmoulton/tmux-sessionizer.git"
+        );
+        assert_eq!(url_encode("app user:me"), "app%20user%3Ame");
+    }
+}
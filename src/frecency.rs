@@ -0,0 +1,137 @@
+//! Sorting sessions by how recently and how often they were used, for the `Frecency` sort order
+
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::{configs::SortWeights, state::State};
+
+/// How long it takes for how recently a session was used to count half as much
+const RECENCY_HALF_LIFE: f64 = 24.0 * 60.0 * 60.0;
+
+/// Sort the items, the best scoring first, where `session_name` is the name of an item's session,
+/// which its uses are recorded by
+pub fn sort_by_frecency(
+    items: &mut [String],
+    session_name: impl Fn(&str) -> String,
+    state: &State,
+    weights: &SortWeights,
+    now: u64,
+) {
+    let names: Vec<String> = items.iter().map(|item| session_name(item)).collect();
+    let scores = scores(&names, &state.last_used, &state.use_counts, weights, now);
+    let mut scored: Vec<(f64, String)> = scores.into_iter().zip(items.iter().cloned()).collect();
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .partial_cmp(a_score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| a.cmp(b))
+    });
+    for (item, (_, scored)) in items.iter_mut().zip(scored) {
+        *item = scored;
+    }
+}
+
+/// The score of each session, adding up how recently it was used, how often compared to the most
+/// used one and where its name comes alphabetically, each between 0 and 1 and multiplied by its
+/// weight
+pub(crate) fn scores(
+    names: &[String],
+    last_used: &BTreeMap<String, u64>,
+    use_counts: &BTreeMap<String, u64>,
+    weights: &SortWeights,
+    now: u64,
+) -> Vec<f64> {
+    let most_uses = names
+        .iter()
+        .filter_map(|name| use_counts.get(name))
+        .max()
+        .copied()
+        .unwrap_or_default();
+    let mut alphabetical: Vec<&String> = names.iter().collect();
+    alphabetical.sort();
+    alphabetical.dedup();
+    let last_rank = alphabetical.len().saturating_sub(1).max(1) as f64;
+
+    names
+        .iter()
+        .map(|name| {
+            let recency = last_used.get(name).map_or(0.0, |last_used| {
+                let age = now.saturating_sub(*last_used) as f64;
+                0.5f64.powf(age / RECENCY_HALF_LIFE)
+            });
+            let frequency = match (use_counts.get(name), most_uses) {
+                (Some(uses), most_uses) if most_uses > 0 => *uses as f64 / most_uses as f64,
+                _ => 0.0,
+            };
+            let rank = alphabetical.binary_search(&name).unwrap_or_default() as f64;
+            let alphabetical = 1.0 - rank / last_rank;
+
+            f64::from(weights.recency()) * recency
+                + f64::from(weights.frequency()) * frequency
+                + f64::from(weights.alphabetical()) * alphabetical
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60;
+    const NOW: u64 = 1000 * HOUR;
+
+    fn state() -> State {
+        State {
+            // `api` was used a lot last week, `notes` once just now
+            last_used: BTreeMap::from([("api".into(), NOW - 7 * 24 * HOUR), ("notes".into(), NOW)]),
+            use_counts: BTreeMap::from([("api".into(), 40), ("notes".into(), 1)]),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(weights: SortWeights) -> Vec<String> {
+        let mut items = vec!["zsh".into(), "notes".into(), "api".into(), "blog".into()];
+        sort_by_frecency(&mut items, str::to_owned, &state(), &weights, NOW);
+        items
+    }
+
+    #[test]
+    fn weighs_recency_against_frequency() {
+        assert_eq!(
+            sorted(SortWeights::default()),
+            ["notes", "api", "blog", "zsh"]
+        );
+        assert_eq!(
+            sorted(SortWeights {
+                frequency: Some(3),
+                ..Default::default()
+            }),
+            ["api", "notes", "blog", "zsh"]
+        );
+        assert_eq!(
+            sorted(SortWeights {
+                recency: Some(0),
+                frequency: Some(0),
+                alphabetical: Some(1),
+            }),
+            ["api", "blog", "notes", "zsh"]
+        );
+    }
+
+    #[test]
+    fn scores_the_parts_between_zero_and_one() {
+        let state = state();
+        let names = ["api".to_owned(), "notes".to_owned()];
+        let only = |recency, frequency, alphabetical| SortWeights {
+            recency: Some(recency),
+            frequency: Some(frequency),
+            alphabetical: Some(alphabetical),
+        };
+        let scores = |weights| scores(&names, &state.last_used, &state.use_counts, &weights, NOW);
+
+        let recency = scores(only(1, 0, 0));
+        assert!((recency[0] - 0.5f64.powi(7)).abs() < 1e-9);
+        assert_eq!(recency[1], 1.0);
+        assert_eq!(scores(only(0, 1, 0)), [1.0, 1.0 / 40.0]);
+        assert_eq!(scores(only(0, 0, 2)), [2.0, 0.0]);
+    }
+}
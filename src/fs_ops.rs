@@ -0,0 +1,90 @@
+//! Changes to the projects' directories themselves, which tms only makes when explicitly asked to
+
+use std::{fs, path::Path};
+
+use error_stack::ResultExt;
+
+use crate::{error::TmsError, Result};
+
+/// Move the directory to the system's trash, from where it can be restored. The home directory and
+/// the root of the file system are never moved
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    let path = fs::canonicalize(path)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not find {}", path.display()))?;
+    let is_home = dirs::home_dir()
+        .and_then(|home| fs::canonicalize(home).ok())
+        .is_some_and(|home| home == path);
+    if is_home || path.parent().is_none() {
+        return Err(TmsError::IoError)
+            .attach_printable(format!("Refusing to move {} to the trash", path.display()));
+    }
+
+    trash::delete(&path)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not move {} to the trash", path.display()))
+}
+
+/// Copy the contents of the directory `from` into `to`, creating it if needed. Symlinks are copied
+/// as links and a `.git` directory at the top is left out, so a template can be a repository itself
+pub fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    copy_entries(from, to, true)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not copy {} to {}", from.display(), to.display()))
+}
+
+fn copy_entries(from: &Path, to: &Path, top: bool) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if top && entry.file_name() == ".git" {
+                continue;
+            }
+            copy_entries(&source, &target, false)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&source, &target)?;
+        } else {
+            fs::copy(&source, &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+/// Without unix symlinks, the file a link points to is copied instead
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    fs::copy(source, target).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_a_template_without_its_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template");
+        fs::create_dir_all(template.join("src")).unwrap();
+        fs::create_dir_all(template.join(".git/objects")).unwrap();
+        fs::write(template.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(template.join(".gitignore"), "target").unwrap();
+        let project = dir.path().join("projects/api");
+
+        copy_dir(&template, &project).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(project.join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert!(project.join(".gitignore").is_file());
+        assert!(!project.join(".git").exists());
+    }
+}
@@ -0,0 +1,85 @@
+//! Glob patterns like the ones in `.gitignore` files, used to skip directories while searching
+
+/// Whether `pattern` contains anything that makes it a glob rather than a plain string
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Whether `text` matches `pattern` as a whole. `*` matches anything but `/`, `**` anything
+/// including `/`, `?` a single character other than `/` and `[...]` one of a set of characters
+/// like `[abc]`, `[a-z]` or `[!0-9]`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `a/**/b` also matches `a/b`
+            let rest_after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            matches(rest_after_slash, text) || (0..=text.len()).any(|i| matches(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            let segment_end = text.iter().position(|c| *c == '/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| matches(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, text)| *c != '/' && matches(rest, text)),
+        ['[', rest @ ..] => {
+            let Some(end) = rest.iter().skip(1).position(|c| *c == ']').map(|i| i + 1) else {
+                // An unclosed `[` is an ordinary character
+                return text
+                    .split_first()
+                    .is_some_and(|(c, text)| *c == '[' && matches(rest, text));
+            };
+            let Some((c, text)) = text.split_first() else {
+                return false;
+            };
+            in_class(&rest[..end], *c) && matches(&rest[end + 1..], text)
+        }
+        [p, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, text)| c == p && matches(rest, text)),
+    }
+}
+
+fn in_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class {
+        ['!' | '^', class @ ..] => (true, class),
+        class => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards_and_classes() {
+        assert!(glob_match("node_modules", "node_modules"));
+        assert!(glob_match("*.bak", "project.bak"));
+        assert!(!glob_match("*.bak", "old/project.bak"));
+        assert!(glob_match("**/target", "/home/me/code/app/target"));
+        assert!(glob_match("/home/**/build", "/home/build"));
+        assert!(glob_match("cache-?", "cache-1"));
+        assert!(glob_match("v[0-9]*", "v12"));
+        assert!(!glob_match("v[!0-9]*", "v12"));
+        assert!(!glob_match("target", "targets"));
+    }
+}
@@ -0,0 +1,185 @@
+//! An opt-in log of what tms did, one JSON object per line so other tools can read it too
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use error_stack::ResultExt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configs::{Config, SessionEvent},
+    state::{data_file, now},
+    Result, TmsError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Create,
+    Switch,
+    Kill,
+    Scan,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Switch => "switch",
+            Action::Kill => "kill",
+            Action::Scan => "scan",
+        }
+    }
+}
+
+impl From<SessionEvent> for Action {
+    fn from(event: SessionEvent) -> Self {
+        match event {
+            SessionEvent::Create => Action::Create,
+            SessionEvent::Attach => Action::Switch,
+            SessionEvent::Kill => Action::Kill,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    pub action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+impl Entry {
+    pub fn new(action: Action) -> Self {
+        Entry {
+            time: now(),
+            action,
+            session: None,
+            path: None,
+            details: None,
+        }
+    }
+
+    pub fn session(mut self, name: &str, path: &Path) -> Self {
+        self.session = Some(name.to_owned());
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn details(mut self, details: String) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Append the entry to the log when `event_log` is enabled
+pub fn record(config: &Config, entry: Entry) -> Result<()> {
+    if config.event_log != Some(true) {
+        return Ok(());
+    }
+    let path = log_file()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .change_context(TmsError::IoError)
+            .attach_printable("Unable to create tms state folder")?;
+    }
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not open {}", path.display()))?;
+    let json = serde_json::to_string(&entry).change_context(TmsError::IoError)?;
+    writeln!(log, "{json}").change_context(TmsError::IoError)
+}
+
+/// The logged entries, oldest first, skipping lines that can't be read
+pub fn entries() -> Result<Vec<Entry>> {
+    let path = log_file()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(&path)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not read {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+pub fn log_file() -> Result<PathBuf> {
+    data_file("history.jsonl")
+}
+
+/// The time as `YYYY-MM-DD HH:MM:SS` in UTC
+pub fn format_time(time: u64) -> String {
+    let (days, seconds) = (time / 86400, time % 86400);
+    // Days to a date in the proleptic Gregorian calendar, counting in 400 year eras from 0000-03-01
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_survive_a_round_trip() {
+        let entry = Entry {
+            time: 1_700_000_000,
+            action: Action::Kill,
+            session: Some("my \"app\"".into()),
+            path: Some("/home/me/code/app\\x".into()),
+            details: Some("line\nbreak".into()),
+        };
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<Entry>(&json).unwrap(), entry);
+        let scan = Entry {
+            time: 1,
+            ..Entry::new(Action::Scan)
+        };
+        assert_eq!(
+            serde_json::to_string(&scan).unwrap(),
+            r#"{"time":1,"action":"scan"}"#
+        );
+        assert!(serde_json::from_str::<Entry>("not json").is_err());
+    }
+
+    #[test]
+    fn formats_times_as_utc_dates() {
+        assert_eq!(format_time(0), "1970-01-01 00:00:00");
+        assert_eq!(format_time(951_825_600), "2000-02-29 12:00:00");
+        assert_eq!(format_time(1_792_153_617), "2026-10-16 12:26:57");
+    }
+}
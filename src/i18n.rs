@@ -0,0 +1,93 @@
+//! Translations of the messages shown to users.
+//!
+//! Messages are looked up by their English text in a TOML file named after the language from
+//! `LC_ALL`, `LC_MESSAGES` or `LANG`, e.g. `de_DE.toml` or `de.toml`, inside `TMS_LOCALE_DIR` or
+//! the `tms/locales` folder of the platform's data directory. Messages without a translation are
+//! shown in English.
+
+use std::{collections::HashMap, env, fmt::Display, fs, path::PathBuf, sync::OnceLock};
+
+static TRANSLATIONS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// The message in the user's language
+pub fn tr(message: &'static str) -> &'static str {
+    TRANSLATIONS
+        .get_or_init(load_translations)
+        .get(message)
+        .map(String::as_str)
+        .unwrap_or(message)
+}
+
+/// The message in the user's language with its `{}` placeholders replaced by `args` in order
+pub fn tr_fmt(message: &'static str, args: &[&dyn Display]) -> String {
+    fill(tr(message), args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_owned();
+    let mut args = args.iter();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+fn load_translations() -> HashMap<String, String> {
+    let Some(dir) = env::var_os("TMS_LOCALE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("tms/locales")))
+    else {
+        return HashMap::new();
+    };
+
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|locale| language_candidates(&locale))
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|language| fs::read_to_string(dir.join(format!("{language}.toml"))).ok())
+        // A broken translation shouldn't make tms unusable, so fall back to English
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// File names to try for a locale like `pt_BR.UTF-8`, from the most to the least specific
+fn language_candidates(locale: &str) -> Vec<String> {
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    if locale.is_empty() || locale == "C" || locale == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut candidates = vec![locale.to_owned()];
+    if let Some((language, _)) = locale.split_once(['_', '-']) {
+        candidates.push(language.to_owned());
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders_in_order() {
+        assert_eq!(
+            fill("Sitzung {} von {} nicht gefunden", &[&"foo", &2]),
+            "Sitzung foo von 2 nicht gefunden"
+        );
+        assert_eq!(fill("No placeholders", &[&"unused"]), "No placeholders");
+    }
+
+    #[test]
+    fn derives_languages_from_locale() {
+        assert_eq!(language_candidates("pt_BR.UTF-8"), vec!["pt_BR", "pt"]);
+        assert_eq!(language_candidates("de"), vec!["de"]);
+        assert!(language_candidates("C.UTF-8").is_empty());
+    }
+}
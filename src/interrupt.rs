@@ -0,0 +1,73 @@
+//! Stopping the scan for projects and the picker when ctrl-c is pressed, so tms exits promptly and
+//! leaves the terminal as it found it. Everywhere else ctrl-c ends tms right away like it always did
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{error::TmsError, Result};
+
+/// The exit code of a process that was interrupted with ctrl-c, like shells report it
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// How many parts of tms are catching ctrl-c at the moment
+static CATCHING: AtomicUsize = AtomicUsize::new(0);
+
+/// Handle ctrl-c for the rest of the process. It's only noted while a [`Catch`] is alive, for the
+/// work it covers to stop with [`check`], and otherwise ends tms
+#[cfg(unix)]
+pub fn install_handler() {
+    extern "C" fn on_interrupt(signal: libc::c_int) {
+        if CATCHING.load(Ordering::SeqCst) > 0 {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        } else {
+            // Only async-signal-safe functions may be called here
+            unsafe {
+                libc::signal(signal, libc::SIG_DFL);
+                libc::raise(signal);
+            }
+        }
+    }
+
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install_handler() {}
+
+/// Catch ctrl-c until this is dropped, instead of ending tms
+pub struct Catch(());
+
+impl Catch {
+    pub fn new() -> Self {
+        CATCHING.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Default for Catch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Catch {
+    fn drop(&mut self) {
+        CATCHING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether ctrl-c was pressed while it was caught
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fail once ctrl-c was pressed, for long running work to stop
+pub fn check() -> Result<()> {
+    if interrupted() {
+        return Err(TmsError::Interrupted.into());
+    }
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::fmt::Display;
 use std::{collections::HashMap, fmt::Debug};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use schemars::JsonSchema;
 use serde::de::Error as DeError;
 use serde::{Deserialize, Serialize, Serializer};
 
@@ -178,8 +179,12 @@ impl From<KeyEvent> for Key {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct Keymap(#[serde(serialize_with = "sorted_map")] pub HashMap<Key, PickerAction>);
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Keymap(
+    #[serde(serialize_with = "sorted_map")]
+    #[schemars(with = "HashMap<String, PickerAction>")]
+    pub HashMap<Key, PickerAction>,
+);
 
 fn sorted_map<S: Serializer, K: Serialize + Ord + Debug, V: Serialize + Debug>(
     value: &HashMap<K, V>,
@@ -319,6 +324,97 @@ impl Default for Keymap {
                 },
                 PickerAction::MoveToLineEnd,
             ),
+            (
+                Key {
+                    code: KeyCode::Char('/'),
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::EnterSearchMode,
+            ),
+            (
+                Key {
+                    code: KeyCode::Tab,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::ToggleSelection,
+            ),
+            (
+                Key {
+                    code: KeyCode::Enter,
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::ConfirmBackground,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('d'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::ConfirmDetachOthers,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('t'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::TogglePreview,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('t'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::NextPreview,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('x'),
+                    modifiers: KeyModifiers::CONTROL,
+                },
+                PickerAction::KillSession,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('m'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::SetMark,
+            ),
+            (
+                Key {
+                    code: KeyCode::Char('l'),
+                    modifiers: KeyModifiers::ALT,
+                },
+                PickerAction::SwitchLast,
+            ),
+            (
+                Key {
+                    code: KeyCode::PageUp,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::PageUp,
+            ),
+            (
+                Key {
+                    code: KeyCode::PageDown,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::PageDown,
+            ),
+            (
+                Key {
+                    code: KeyCode::Home,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::MoveToTop,
+            ),
+            (
+                Key {
+                    code: KeyCode::End,
+                    modifiers: KeyModifiers::empty(),
+                },
+                PickerAction::MoveToBottom,
+            ),
         ]))
     }
 }
@@ -333,7 +429,7 @@ impl Keymap {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 pub enum PickerAction {
     #[serde(rename = "")]
     Noop,
@@ -349,6 +445,14 @@ pub enum PickerAction {
     MoveUp,
     #[serde(rename = "move_down")]
     MoveDown,
+    #[serde(rename = "page_up")]
+    PageUp,
+    #[serde(rename = "page_down")]
+    PageDown,
+    #[serde(rename = "move_to_top")]
+    MoveToTop,
+    #[serde(rename = "move_to_bottom")]
+    MoveToBottom,
     #[serde(rename = "cursor_left")]
     CursorLeft,
     #[serde(rename = "cursor_right")]
@@ -363,4 +467,45 @@ pub enum PickerAction {
     MoveToLineStart,
     #[serde(rename = "move_to_line_end")]
     MoveToLineEnd,
+    #[serde(rename = "enter_search_mode")]
+    EnterSearchMode,
+    #[serde(rename = "toggle_selection")]
+    ToggleSelection,
+    #[serde(rename = "confirm_background")]
+    ConfirmBackground,
+    #[serde(rename = "confirm_detach_others")]
+    ConfirmDetachOthers,
+    #[serde(rename = "toggle_preview")]
+    TogglePreview,
+    #[serde(rename = "next_preview")]
+    NextPreview,
+    #[serde(rename = "kill_session")]
+    KillSession,
+    #[serde(rename = "delete_project")]
+    DeleteProject,
+    #[serde(rename = "set_mark")]
+    SetMark,
+    #[serde(rename = "switch_last")]
+    SwitchLast,
+    #[serde(rename = "toggle_debug_overlay")]
+    ToggleDebugOverlay,
+}
+
+impl PickerAction {
+    /// Whether the action changes the filter or moves its cursor, which only works while searching
+    /// with `explicit_search_mode`
+    pub fn edits_filter(self) -> bool {
+        matches!(
+            self,
+            PickerAction::Backspace
+                | PickerAction::Delete
+                | PickerAction::DeleteWord
+                | PickerAction::DeleteToLineStart
+                | PickerAction::DeleteToLineEnd
+                | PickerAction::CursorLeft
+                | PickerAction::CursorRight
+                | PickerAction::MoveToLineStart
+                | PickerAction::MoveToLineEnd
+        )
+    }
 }
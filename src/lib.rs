@@ -1,13 +1,32 @@
+pub mod batch;
+mod build_info;
+mod cache;
 pub mod cli;
 mod clone;
+pub mod columns;
 pub mod configs;
+mod credentials;
 pub mod dirty_paths;
 pub mod error;
+mod files;
+pub mod forge;
+mod frecency;
+mod fs_ops;
+mod glob;
+pub mod history;
+pub mod i18n;
+pub mod interrupt;
 pub mod keymap;
 pub mod marks;
 pub mod picker;
+pub mod preview;
+pub mod remote;
 pub mod repos;
 pub mod session;
+pub mod shell;
+mod sort;
+mod sparse;
+pub mod state;
 pub mod tmux;
 
 use configs::Config;
@@ -29,12 +48,15 @@ pub fn execute_command(command: &str, args: Vec<String>) -> process::Output {
 
 pub fn get_single_selection(
     list: &[String],
-    preview: Preview,
+    preview: Preview<'_>,
     config: &Config,
     tmux: &Tmux,
 ) -> Result<Option<String>> {
     let mut picker = Picker::new(list, preview, config.shortcuts.as_ref(), tmux)
-        .set_colors(config.picker_colors.as_ref());
+        .set_colors(config.picker_colors.as_ref())
+        .set_color_mode(config.color)
+        .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+        .set_pane_scrollback(config.preview_scrollback.unwrap_or_default());
 
     picker.run()
 }
@@ -1,15 +1,34 @@
-use std::env;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
 
 use clap::{CommandFactory, Parser};
 use clap_complete::CompleteEnv;
-use error_stack::Report;
+use error_stack::{Report, ResultExt};
+use git2::Repository;
 
 use tms::{
-    cli::{Cli, SubCommandGiven},
-    error::{Result, Suggestion},
-    get_single_selection,
-    picker::Preview,
-    session::{create_sessions, SessionContainer},
+    cli::{kill_stale_sessions, switch_to_last, Cli, SubCommandGiven},
+    columns::PickerColumn,
+    configs::Config,
+    dirty_paths::DirtyUtf8Path,
+    error::{short_message, Result, Suggestion, TmsError},
+    i18n::tr,
+    interrupt,
+    marks::mark_path,
+    picker::{best_match, Picker, Preview, RequestRedraw},
+    preview::recent_files,
+    remote::{remote_projects, RemoteProject},
+    session::{
+        cached_sessions, create_sessions, recently_closed_first, tmux_session_name, Session,
+        SessionContainer, SessionType,
+    },
     tmux::Tmux,
 };
 
@@ -21,6 +40,16 @@ fn main() -> Result<()> {
     #[cfg(any(not(debug_assertions), test))]
     Report::install_debug_hook::<std::panic::Location>(|_value, _context| {});
 
+    // Whatever was interrupted has stopped and cleaned up, there is nothing left to report
+    interrupt::install_handler();
+    let result = run();
+    if interrupt::interrupted() {
+        process::exit(interrupt::EXIT_CODE);
+    }
+    result
+}
+
+fn run() -> Result<()> {
     let bin_name = std::env::current_exe()
         .ok()
         .and_then(|exe| exe.file_name().map(|exe| exe.to_string_lossy().to_string()))
@@ -46,19 +75,340 @@ fn main() -> Result<()> {
         SubCommandGiven::No(config) => config, // continue
     };
 
-    let sessions = create_sessions(&config)?;
-    let session_strings = sessions.list();
+    let mode = match cli_args.cwd_file() {
+        Some(file) => OpenMode::WriteCwd(file),
+        None if cli_args.detached() => OpenMode::Detached,
+        None => OpenMode::Switch,
+    };
+
+    if let Some(path) = cli_args.path() {
+        return open(&Session::from_path(path)?, None, mode, &tmux, &config);
+    }
+    // `tms_cd` only changes the directory of the shell and leaves tmux alone
+    if !matches!(mode, OpenMode::WriteCwd(_)) {
+        kill_stale_sessions(&config, &tmux);
+    }
+
+    // Listing the running sessions waits for tmux about as long as loading the projects takes, so
+    // both are done at the same time. The clones share the listed sessions, so opening the picked
+    // project right away doesn't list them again
+    let running = {
+        let tmux = tmux.clone();
+        thread::spawn(move || tmux.running_sessions())
+    };
+
+    if let Some(query) = cli_args.immediate() {
+        let sessions = create_sessions(&config)?;
+        let running = running.join().unwrap_or_default();
+        let (session_strings, _) = recently_closed_first(&sessions, &running, &config)?;
+        let selected = best_match(&session_strings, query)
+            .ok_or_else(|| TmsError::SessionNotFound(query.to_owned()))?;
+        if let Some(session) = sessions.find_session(selected) {
+            open(session, None, mode, &tmux, &config)?;
+        }
+        return Ok(());
+    }
+
+    // The picker opens with the repositories found last time while they are scanned for again,
+    // the ones that are new are added to it when the scan is done
+    let config: Arc<Config> = config.into();
+    let (sessions, cached) = cached_sessions(&config)?;
+    let running = running.join().unwrap_or_default();
+    let (session_strings, recent) = recently_closed_first(&sessions, &running, &config)?;
+    let (sender, updates) = mpsc::channel();
+    let mut scanned = None;
+    // The directories of the projects the scan finds, known before they are sent to the picker
+    let scanned_paths: Arc<Mutex<HashMap<String, PathBuf>>> = Arc::default();
+
+    let item_colors = session_strings
+        .iter()
+        .filter_map(|name| {
+            let session = sessions.find_session(name)?;
+            let color = config.project_color(&tmux_session_name(&session.name))?;
+            Some((name.clone(), color))
+        })
+        .collect();
+
+    // The plan of a remote project waits for ssh, so it's only made once for each
+    let plans_of_remotes = RefCell::new(HashMap::new());
+    // The plans are made for every frame, so the sessions are only listed again after the picker
+    // created one
+    let running = RefCell::new(running);
+    let plan = |name: &str| match sessions.find_session(name) {
+        Some(session) => session.plan(&running.borrow(), &config),
+        None => plans_of_remotes
+            .borrow_mut()
+            .entry(name.to_owned())
+            .or_insert_with(|| {
+                RemoteProject::parse(name, &config)
+                    .map(|project| project.plan(&running.borrow()))
+                    .unwrap_or_default()
+            })
+            .clone(),
+    };
+
+    // Listing the files modified last reads the whole project, so it's only done once for each
+    let recent_files_of_project = RefCell::new(HashMap::new());
+    let recent_files_preview = |name: &str| {
+        let Some(session) = sessions.find_session(name) else {
+            return String::new();
+        };
+        recent_files_of_project
+            .borrow_mut()
+            .entry(name.to_owned())
+            .or_insert_with(|| recent_files(session.path()))
+            .clone()
+    };
+
+    // Only the projects the picker opened with can be marked, the ones added by the scan are new
+    let mark = |name: &str| {
+        let session = sessions.find_session(name)?;
+        mark_path(session.path()).ok()
+    };
+
+    // Deleting has no default key, it's only possible when `delete_project` is bound to one
+    let delete = |name: &str| {
+        let Some(session) = sessions.find_session(name) else {
+            return Err(tr("only the projects the picker opened with can be deleted").to_owned());
+        };
+        session
+            .delete(&tmux, &config)
+            .map_err(|error| short_message(&error))
+    };
+
+    // Opening a project whose name is taken by a session in another directory asks for a new name
+    let suggest_name = |name: &str| sessions.find_session(name)?.name_conflict(&tmux);
+    let check_name = |name: &str, session_name: &str| {
+        sessions
+            .find_session(name)?
+            .check_session_name(session_name, &tmux)
+    };
 
-    let selected_str =
-        if let Some(str) = get_single_selection(&session_strings, Preview::None, &config, &tmux)? {
-            str
+    let columns = config.picker_columns.clone().unwrap_or_default();
+    let column_values = |name: &str| {
+        sessions
+            .find_session(name)
+            .map(|session| session.column_values(&columns))
+            .unwrap_or_default()
+    };
+
+    let picker = Picker::new(
+        &session_strings,
+        Preview::Text(Box::new(plan)),
+        config.shortcuts.as_ref(),
+        &tmux,
+    )
+    .set_colors(config.picker_colors.as_ref())
+    .set_color_mode(config.color)
+    .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+    .set_group(tr("Recently closed"), recent)
+    .set_scope(config.profile.as_deref())
+    // The picker may run in a popup that closes with it, so overlapping search directories are
+    // pointed out in it rather than on stderr
+    .set_warning(config.search_dir_warnings().into_iter().next())
+    .set_item_colors(item_colors)
+    .add_preview_tab(
+        tr("Plan"),
+        tr("Recent files"),
+        Preview::Text(Box::new(recent_files_preview)),
+    )
+    .set_preview_visible(false)
+    .set_allow_background(true)
+    .set_allow_switch_last(true)
+    .set_mark_item(mark)
+    .set_delete_item(delete)
+    .set_confirm_actions(config.confirm_destructive_actions != Some(false))
+    .set_name_prompt(suggest_name, check_name)
+    .set_item_updates(updates);
+    let picker = if columns.is_empty() {
+        picker
+    } else {
+        picker.set_item_columns(column_values)
+    };
+    // With full paths shown the names are the paths already
+    let picker = if config.match_paths == Some(true) && config.display_full_path != Some(true) {
+        picker.set_match_text(|name| {
+            let path = match sessions.find_session(name) {
+                Some(session) => session.path().to_owned(),
+                None => scanned_paths.lock().ok()?.get(name)?.clone(),
+            };
+            Some(path.to_string_lossy().into_owned())
+        })
+    } else {
+        picker
+    };
+    // The status is made of the columns that aren't shown already. The scan sends the repositories
+    // it finds to read theirs as well
+    let status_columns: Vec<PickerColumn> = [PickerColumn::Branch, PickerColumn::Dirty]
+        .into_iter()
+        .filter(|column| !columns.contains(column))
+        .collect();
+    let (repositories, picker) =
+        if config.show_git_status == Some(true) && !status_columns.is_empty() {
+            let (repositories, to_read) = mpsc::channel();
+            let _ = repositories.send(git_repositories(&session_strings, &sessions));
+            let updates = git_status_updates(status_columns, to_read, picker.request_redraw());
+            (Some(repositories), picker.set_detail_updates(updates))
         } else {
-            return Ok(());
+            (None, picker)
         };
+    let redraw = picker.request_redraw();
+    let mut scan = cached.then(|| {
+        let config = Arc::clone(&config);
+        let sender = sender.clone();
+        let redraw = Arc::clone(&redraw);
+        let scanned_paths = Arc::clone(&scanned_paths);
+        thread::spawn(move || {
+            let sessions = create_sessions(&config)?;
+            if let Ok(mut paths) = scanned_paths.lock() {
+                paths.extend(sessions.list().into_iter().filter_map(|name| {
+                    let path = sessions.find_session(&name)?.path().to_owned();
+                    Some((name, path))
+                }));
+            }
+            // The picker may already be closed, in which case nobody needs the updates
+            if let Some(repositories) = repositories {
+                let _ = repositories.send(git_repositories(&sessions.list(), &sessions));
+            }
+            let _ = sender.send(sessions.list());
+            redraw();
+            Ok::<_, Report<TmsError>>(sessions)
+        })
+    });
+    // Listing remote projects waits for ssh, so they are added to the picker when they arrive
+    if config.remotes.is_some() {
+        let config = Arc::clone(&config);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send(remote_projects(&config));
+            redraw();
+        });
+    }
+    // The updates stop once every thread that can send them is done
+    drop(sender);
+    let mut picker = match cli_args.filter() {
+        Some(query) => picker.set_filter(query),
+        None => picker,
+    };
 
-    if let Some(session) = sessions.find_session(&selected_str) {
-        session.switch_to(&tmux, &config)?;
+    while let Some(selected_str) = picker.run()? {
+        // The scan writes the cache of the repositories when it's done, which has to happen before
+        // tms is replaced by tmux for the next picker to open with them. Only the scan knows about
+        // new repositories, it is done once it has sent them
+        if let Some(scan) = scan.take() {
+            scanned = scan.join().ok().and_then(Result::ok);
+        }
+        if let Some(project) = RemoteProject::parse(&selected_str, &config) {
+            if picker.confirmed_in_background() {
+                project.create(&tmux)?;
+                *running.borrow_mut() = tmux.running_sessions();
+                continue;
+            }
+            match mode {
+                OpenMode::Switch => project.switch_to(&tmux)?,
+                OpenMode::Detached => println!("{}", project.create(&tmux)?),
+                // There is no local directory to write
+                OpenMode::WriteCwd(_) => {}
+            }
+            break;
+        }
+        let session = sessions
+            .find_session(&selected_str)
+            .or_else(|| scanned.as_ref()?.find_session(&selected_str));
+        let Some(session) = session else {
+            break;
+        };
+        if picker.confirmed_in_background() {
+            session.create_as(picker.session_name(), &tmux, &config)?;
+            *running.borrow_mut() = tmux.running_sessions();
+        } else {
+            open(session, picker.session_name(), mode, &tmux, &config)?;
+            break;
+        }
+    }
+    // Closing the picker without opening a project still updates the cache
+    if let Some(scan) = scan.take() {
+        let _ = scan.join();
+    }
+    if picker.confirmed_switch_last() {
+        switch_to_last(&tmux)?;
     }
 
     Ok(())
 }
+
+/// What is done with the picked project
+#[derive(Clone, Copy)]
+enum OpenMode<'a> {
+    /// Switch or attach to its session
+    Switch,
+    /// Only create its session and print its name, for `--detached`
+    Detached,
+    /// Write its directory to the file without touching tmux, for `--cwd-file`
+    WriteCwd(&'a Path),
+}
+
+/// The names and directories of the git repositories among the projects
+fn git_repositories(names: &[String], sessions: &impl SessionContainer) -> Vec<(String, PathBuf)> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let session = sessions.find_session(name)?;
+            matches!(session.session_type, SessionType::Git(_))
+                .then(|| (name.clone(), session.path().to_owned()))
+        })
+        .collect()
+}
+
+/// Read the `columns` of the repositories sent on `repositories` in the background, in the order
+/// they're sent, as reading them all would hold up opening the picker. Each repository is read once
+fn git_status_updates(
+    columns: Vec<PickerColumn>,
+    repositories: mpsc::Receiver<Vec<(String, PathBuf)>>,
+    redraw: RequestRedraw,
+) -> mpsc::Receiver<(String, String)> {
+    let (sender, updates) = mpsc::channel();
+    thread::spawn(move || {
+        let mut read = HashSet::new();
+        for (name, path) in repositories.iter().flatten() {
+            if !read.insert(name.clone()) {
+                continue;
+            }
+            let Ok(repository) = Repository::open(&path) else {
+                continue;
+            };
+            let status: Vec<String> = columns
+                .iter()
+                .map(|column| column.value(&repository))
+                .filter(|value| !value.is_empty())
+                .collect();
+            // The picker is closed once nobody receives the statuses
+            if sender.send((name, status.join(" "))).is_err() {
+                break;
+            }
+            redraw();
+        }
+    });
+    updates
+}
+
+/// Open the project the way the mode says. The session is named `session_name` when one was typed
+/// because the project's own name was taken
+fn open(
+    session: &Session,
+    session_name: Option<&str>,
+    mode: OpenMode,
+    tmux: &Tmux,
+    config: &Config,
+) -> Result<()> {
+    match mode {
+        OpenMode::Switch => session.switch_to_as(session_name, tmux, config),
+        OpenMode::Detached => {
+            println!("{}", session.create_as(session_name, tmux, config)?);
+            Ok(())
+        }
+        OpenMode::WriteCwd(file) => fs::write(file, session.path().to_string()?)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not write to {}", file.display())),
+    }
+}
@@ -1,14 +1,19 @@
-use std::{env::current_dir, path::PathBuf};
+use std::{
+    env::current_dir,
+    path::{Path, PathBuf},
+};
 
 use clap::{Args, Subcommand};
 use clap_complete::{ArgValueCandidates, CompletionCandidate};
 use error_stack::ResultExt;
+use serde_derive::Serialize;
 
 use crate::{
+    cli::{print_json, Context, OutputFormat, TmsCommand},
     configs::Config,
     dirty_paths::DirtyUtf8Path,
     error::{Result, TmsError},
-    session::Session,
+    session::{project_name, running_sessions, Session},
     tmux::Tmux,
 };
 
@@ -32,6 +37,12 @@ pub enum MarksSubCommand {
     Open(MarksOpenCommand),
     /// Delete marks
     Delete(MarksDeleteCommand),
+    /// Exchange the sessions of two marks
+    Swap(MarksSwapCommand),
+    /// Move a mark to another index, shifting the marks in between
+    Move(MarksMoveCommand),
+    /// Renumber the marks from 0, closing the gaps between them
+    Compact,
 }
 
 #[derive(Debug, Args)]
@@ -61,6 +72,25 @@ pub struct MarksDeleteCommand {
     all: bool,
 }
 
+#[derive(Debug, Args)]
+pub struct MarksSwapCommand {
+    #[arg(add  = ArgValueCandidates::new(get_completion_candidates))]
+    /// Index of the first mark
+    a: usize,
+    #[arg(add  = ArgValueCandidates::new(get_completion_candidates))]
+    /// Index of the second mark
+    b: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct MarksMoveCommand {
+    #[arg(add  = ArgValueCandidates::new(get_completion_candidates))]
+    /// Index of the mark to move
+    from: usize,
+    /// Index to move the mark to
+    to: usize,
+}
+
 fn get_completion_candidates() -> Vec<CompletionCandidate> {
     let config = Config::new().unwrap_or_default();
     let marks = get_marks(&config).unwrap_or_default();
@@ -72,19 +102,79 @@ fn get_completion_candidates() -> Vec<CompletionCandidate> {
         .collect::<Vec<_>>()
 }
 
-pub fn marks_command(args: &MarksCommand, config: Config, tmux: &Tmux) -> Result<()> {
+impl TmsCommand for MarksCommand {
+    fn run(&self, ctx: &mut Context) -> Result<()> {
+        // Commands changing the marks save the config, which mustn't include the overrides
+        let config = match (&self.cmd, self.index) {
+            (
+                Some(
+                    MarksSubCommand::Set(_)
+                    | MarksSubCommand::Delete(_)
+                    | MarksSubCommand::Swap(_)
+                    | MarksSubCommand::Move(_)
+                    | MarksSubCommand::Compact,
+                ),
+                None,
+            ) => ctx.stored_config()?,
+            _ => ctx.take_config()?,
+        };
+        marks_command(self, config, ctx.output(), ctx.tmux)
+    }
+}
+
+pub fn marks_command(
+    args: &MarksCommand,
+    mut config: Config,
+    output: OutputFormat,
+    tmux: &Tmux,
+) -> Result<()> {
     match (&args.cmd, args.index) {
-        (None, None) => list(config),
+        (None, None) => list(&config, output, tmux),
         (_, Some(index)) => open(index, &config, tmux),
-        (Some(MarksSubCommand::List), _) => list(config),
+        (Some(MarksSubCommand::List), _) => list(&config, output, tmux),
         (Some(MarksSubCommand::Set(args)), _) => set(args, config),
         (Some(MarksSubCommand::Open(args)), _) => open(args.index, &config, tmux),
         (Some(MarksSubCommand::Delete(args)), _) => delete(args, config),
+        (Some(MarksSubCommand::Swap(args)), _) => {
+            config.swap_marks(args.a, args.b);
+            config.save().change_context(TmsError::ConfigError)
+        }
+        (Some(MarksSubCommand::Move(args)), _) => move_mark(args, config),
+        (Some(MarksSubCommand::Compact), _) => {
+            config.compact_marks();
+            config.save().change_context(TmsError::ConfigError)
+        }
     }
 }
 
-fn list(config: Config) -> Result<()> {
-    let items = get_marks(&config).unwrap_or_default();
+/// A mark, as listed by `--output json`
+#[derive(Debug, Serialize)]
+struct MarkEntry {
+    index: usize,
+    name: String,
+    path: String,
+    /// The tmux session open in the marked directory, if there is one
+    running_session: Option<String>,
+}
+
+fn list(config: &Config, output: OutputFormat, tmux: &Tmux) -> Result<()> {
+    let items = get_marks(config).unwrap_or_default();
+    if output == OutputFormat::Json {
+        let running = running_sessions(tmux);
+        let entries = items
+            .iter()
+            .map(|(index, session)| {
+                Ok(MarkEntry {
+                    index: *index,
+                    name: session.name.clone(),
+                    path: session.path().to_string()?,
+                    running_session: session.running_session(&running),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        return print_json(&entries);
+    }
+
     items.iter().for_each(|(index, session)| {
         println!("{index}: {} ({})", session.name, session.path().display());
     });
@@ -92,14 +182,7 @@ fn list(config: Config) -> Result<()> {
 }
 
 fn set(args: &MarksSetCommand, mut config: Config) -> Result<()> {
-    let index = args.index.unwrap_or_else(|| {
-        let items = get_marks(&config).unwrap_or_default();
-        items
-            .iter()
-            .enumerate()
-            .take_while(|(i, (index, _))| i == index)
-            .count()
-    });
+    let index = args.index.unwrap_or_else(|| next_free_index(&config));
 
     let path = if let Some(path) = &args.path {
         path.to_owned()
@@ -113,6 +196,25 @@ fn set(args: &MarksSetCommand, mut config: Config) -> Result<()> {
     config.save().change_context(TmsError::ConfigError)
 }
 
+/// Mark the project at `path` with the first free index of the stored config, returning the index
+pub fn mark_path(path: &Path) -> Result<usize> {
+    let _lock = Config::lock().change_context(TmsError::ConfigError)?;
+    let mut config = Config::stored().change_context(TmsError::ConfigError)?;
+    let index = next_free_index(&config);
+    config.add_mark(path.to_string()?, index);
+    config.save().change_context(TmsError::ConfigError)?;
+    Ok(index)
+}
+
+fn next_free_index(config: &Config) -> usize {
+    let items = get_marks(config).unwrap_or_default();
+    items
+        .iter()
+        .enumerate()
+        .take_while(|(i, (index, _))| i == index)
+        .count()
+}
+
 fn get_marks(config: &Config) -> Option<Vec<(usize, Session)>> {
     let items = config.marks.as_ref()?;
     let mut items = items
@@ -135,7 +237,8 @@ fn open(index: usize, config: &Config, tmux: &Tmux) -> Result<()> {
         .ok_or(TmsError::ConfigError)
         .attach_printable(format!("Session with index {} not found in marks", index))?;
 
-    let session = path_to_session(path)?;
+    let mut session = path_to_session(path)?;
+    session.name = project_name(session.path())?;
 
     session.switch_to(tmux, config)
 }
@@ -149,12 +252,17 @@ fn path_to_session(path: &String) -> Result<Session> {
                 .change_context(TmsError::IoError)
         })?;
 
-    let session_name = path
-        .file_name()
-        .expect("The file name doesn't end in `..`")
-        .to_string()?;
-    let session = Session::new(session_name, crate::session::SessionType::Bookmark(path));
-    Ok(session)
+    Session::bookmark(path)
+}
+
+fn move_mark(args: &MarksMoveCommand, mut config: Config) -> Result<()> {
+    if !config.move_mark(args.from, args.to) {
+        return Err(TmsError::ConfigError).attach_printable(format!(
+            "Session with index {} not found in marks",
+            args.from
+        ));
+    }
+    config.save().change_context(TmsError::ConfigError)
 }
 
 fn delete(args: &MarksDeleteCommand, mut config: Config) -> Result<()> {
@@ -1,19 +1,34 @@
 use std::{
-    io::{self, Stdout},
-    process,
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    io::{self, IsTerminal, Stdout},
     rc::Rc,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind},
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
     style::Colored,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
+use error_stack::ResultExt;
 use nucleo::{
-    pattern::{CaseMatching, Normalization},
-    Nucleo,
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher, Nucleo,
 };
 use ratatui::{
     backend::CrosstermBackend,
@@ -21,48 +36,215 @@ use ratatui::{
     style::{Color, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
-        block::Position, Block, Borders, HighlightSpacing, List, ListDirection, ListItem,
-        ListState, Paragraph, Wrap,
+        block::Position, Block, Borders, Clear, HighlightSpacing, List, ListDirection, ListItem,
+        ListState, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
     },
     Frame, Terminal,
 };
 
 use crate::{
-    configs::PickerColorConfig,
+    configs::{ColorMode, PickerColorConfig},
+    error::Suggestion,
+    i18n::{tr, tr_fmt},
+    interrupt,
     keymap::{Keymap, PickerAction},
+    preview::{
+        DirectoryPreview, PaneCapture, PreviewProvider, SessionPanePreview, WindowPanePreview,
+    },
     tmux::Tmux,
     Result, TmsError,
 };
 
-pub enum Preview {
+pub enum Preview<'a> {
     SessionPane,
     WindowPane,
     None,
     Directory,
+    /// Text built for the selected item by the given function
+    Text(Box<dyn Fn(&str) -> String + 'a>),
+    /// Any other kind of preview
+    Custom(Box<dyn PreviewProvider + 'a>),
+}
+
+/// Below this size only a message is shown, there is no room for a list and the prompt
+const MIN_WIDTH: u16 = 10;
+const MIN_HEIGHT: u16 = 3;
+/// Below this size the preview is hidden to leave the room to the list
+const MIN_PREVIEW_WIDTH: u16 = 30;
+const MIN_PREVIEW_HEIGHT: u16 = 8;
+/// How long a status message stays in the picker
+const STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often the thread reading the terminal checks whether the picker was closed
+const INPUT_STOP_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the picker checks whether tms was interrupted, when nothing else wakes it up
+const INTERRUPT_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// Set to show the debug overlay when the picker opens, it can be toggled with the
+/// `toggle_debug_overlay` action
+const DEBUG_OVERLAY_VAR: &str = "TMS_DEBUG_OVERLAY";
+
+/// How long the parts of the last frame took, shown in the debug overlay to find out what makes the
+/// picker slow
+#[derive(Debug, Default, Clone, Copy)]
+struct FrameTimes {
+    /// Drawing the whole frame, the list and the preview
+    frame: Duration,
+    /// Matching the items against the filter
+    tick: Duration,
+    /// Getting the preview of the highlighted item
+    preview: Duration,
+}
+
+/// Leaves the alternate screen and raw mode the picker runs in when it's dropped, so the terminal is
+/// restored when the picker fails as well as when it closes
+struct RestoreTerminal {
+    /// Whether the keyboard enhancement flags were pushed and have to be popped
+    keyboard_enhancement: bool,
+}
+
+impl Drop for RestoreTerminal {
+    fn drop(&mut self) {
+        // Nothing else can be done when restoring fails, the picker is closing either way
+        let mut stdout = io::stdout();
+        if self.keyboard_enhancement {
+            let _ = execute!(stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout, LeaveAlternateScreen, cursor::Show);
+    }
+}
+
+/// Wakes the picker up to show what changed in the background, like items sent while it runs
+pub type RequestRedraw = Arc<dyn Fn() + Send + Sync>;
+
+/// What the picker waits for between frames
+enum Wakeup {
+    /// A key press or another event of the terminal
+    Input(io::Result<Event>),
+    /// Items, details or matches changed in the background
+    Redraw,
+}
+
+/// Kills what an item stands for, returning why it wasn't killed
+type KillItem<'a> = Box<dyn Fn(&str) -> std::result::Result<(), String> + 'a>;
+/// Deletes what an item stands for once it's confirmed, returning why it wasn't deleted
+type DeleteItem<'a> = Box<dyn Fn(&str) -> std::result::Result<(), String> + 'a>;
+/// Marks what an item stands for, returning the index of the mark it got
+type MarkItem<'a> = Box<dyn Fn(&str) -> Option<usize> + 'a>;
+/// The values of the columns shown after an item
+type ItemColumns<'a> = Box<dyn Fn(&str) -> Vec<String> + 'a>;
+
+/// Text an item is matched by besides its name, which isn't shown
+type MatchText<'a> = Box<dyn Fn(&str) -> Option<String> + 'a>;
+
+/// How one of the columns shown after the items looks and whether the filter matches it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ItemColumn {
+    /// The style of the column's values, which are dimmed without one
+    pub style: Option<Style>,
+    /// Match the filter against the column's values along with the item. The values of matched
+    /// columns are read for every item when it's added to the picker, not only for those in view
+    pub matched: bool,
 }
+/// The session name to suggest for an item whose own name is taken, to ask for another one
+type SuggestName<'a> = Box<dyn Fn(&str) -> Option<String> + 'a>;
+/// Why a session name typed for an item can't be used
+type CheckName<'a> = Box<dyn Fn(&str, &str) -> Option<String> + 'a>;
 
 pub struct Picker<'a> {
     matcher: Nucleo<String>,
-    preview: Preview,
+    items: Vec<String>,
+    item_updates: Option<Receiver<Vec<String>>>,
+    /// Details of items sent while the picker runs
+    detail_updates: Option<Receiver<(String, String)>>,
+    /// The item to keep selected while added items are being matched, as they can move it
+    keep_selected: Option<String>,
+    kill_item: Option<KillItem<'a>>,
+    delete_item: Option<DeleteItem<'a>>,
+    /// The question whether to kill or delete an item, shown in place of the filter while it's open
+    confirm: Option<ConfirmPrompt>,
+    /// Whether killing and deleting items is confirmed first
+    confirm_actions: bool,
+    /// Items that were killed or deleted, which aren't added again when items are sent
+    removed_items: HashSet<String>,
+    mark_item: Option<MarkItem<'a>>,
+    name_prompt: Option<(SuggestName<'a>, CheckName<'a>)>,
+    /// The prompt for a session name shown over the list, while it's open
+    prompt: Option<NamePrompt>,
+    session_name: Option<String>,
+    preview: Preview<'a>,
+    /// Other previews, with a title for each and for the first one, switched between with the
+    /// `next_preview` action
+    preview_tabs: Vec<(String, Preview<'a>)>,
+    /// The index of the preview shown, 0 for the first one and the others for the tabs after it
+    preview_tab: usize,
+    preview_visible: bool,
+    /// The lines of scrollback shown in pane previews above the visible part of the pane
+    pane_scrollback: usize,
+    /// How many lines fit in the preview, as of the last time it was rendered
+    preview_height: usize,
 
     colors: Option<&'a PickerColorConfig>,
+    styled: bool,
+    /// The name of what the items are limited to, like the active profile, shown after the count
+    scope: Option<String>,
+    group: Option<(&'a str, usize)>,
+    item_colors: HashMap<String, Color>,
+    item_details: HashMap<String, String>,
+    item_columns: Option<ItemColumns<'a>>,
+    column_options: Vec<ItemColumn>,
+    match_text: Option<MatchText<'a>>,
+    /// The columns of the items that were shown so far, as they can be slow to get
+    column_values: HashMap<String, Vec<String>>,
 
     selection: ListState,
+    /// How many items fit in the list, as of the last time it was rendered
+    page_size: usize,
+    multi_select: bool,
+    selected_items: Vec<String>,
+    allow_background: bool,
+    confirmed_in_background: bool,
+    allow_detach_others: bool,
+    confirmed_detach_others: bool,
+    allow_switch_last: bool,
+    confirmed_switch_last: bool,
     filter: String,
     cursor_pos: u16,
+    explicit_search_mode: bool,
+    searching: bool,
     keymap: Keymap,
     tmux: &'a Tmux,
+    /// The message, whether it reports an error and when it was shown
+    status: Option<(String, bool, Instant)>,
+    preview_failed_for: Option<String>,
+    debug_overlay: bool,
+    frame_times: FrameTimes,
+    wakeups: Receiver<Wakeup>,
+    wakeup_sender: Sender<Wakeup>,
+    request_redraw: RequestRedraw,
+    /// Whether a redraw was requested since the last frame, so a burst of requests wakes the picker
+    /// up once
+    redraw_requested: Arc<AtomicBool>,
 }
 
 impl<'a> Picker<'a> {
-    pub fn new(list: &[String], preview: Preview, keymap: Option<&Keymap>, tmux: &'a Tmux) -> Self {
-        let matcher = Nucleo::new(nucleo::Config::DEFAULT, Arc::new(request_redraw), None, 1);
-
-        let injector = matcher.injector();
-
-        for str in list {
-            injector.push(str.to_owned(), |_, dst| dst[0] = str.to_owned().into());
-        }
+    pub fn new(
+        list: &[String],
+        preview: Preview<'a>,
+        keymap: Option<&Keymap>,
+        tmux: &'a Tmux,
+    ) -> Self {
+        let (wakeup_sender, wakeups) = mpsc::channel();
+        let redraw_requested = Arc::new(AtomicBool::new(false));
+        let request_redraw: RequestRedraw = {
+            let sender = wakeup_sender.clone();
+            let requested = redraw_requested.clone();
+            Arc::new(move || {
+                if !requested.swap(true, Ordering::AcqRel) {
+                    let _ = sender.send(Wakeup::Redraw);
+                }
+            })
+        };
+        let matcher = Nucleo::new(nucleo::Config::DEFAULT, request_redraw.clone(), None, 1);
 
         let keymap = if let Some(keymap) = keymap {
             Keymap::with_defaults(keymap)
@@ -70,16 +252,66 @@ impl<'a> Picker<'a> {
             Keymap::default()
         };
 
-        Picker {
+        let mut picker = Picker {
             matcher,
+            items: list.to_vec(),
+            item_updates: None,
+            detail_updates: None,
+            keep_selected: None,
+            kill_item: None,
+            delete_item: None,
+            confirm: None,
+            confirm_actions: true,
+            removed_items: HashSet::new(),
+            mark_item: None,
+            name_prompt: None,
+            prompt: None,
+            session_name: None,
             preview,
+            preview_tabs: Vec::new(),
+            preview_tab: 0,
+            preview_visible: true,
+            pane_scrollback: 0,
+            preview_height: usize::MAX,
             colors: None,
+            styled: true,
+            scope: None,
+            group: None,
+            item_colors: HashMap::new(),
+            item_details: HashMap::new(),
+            item_columns: None,
+            column_options: Vec::new(),
+            match_text: None,
+            column_values: HashMap::new(),
             selection: ListState::default(),
+            page_size: 1,
+            multi_select: false,
+            selected_items: Vec::new(),
+            allow_background: false,
+            confirmed_in_background: false,
+            allow_detach_others: false,
+            confirmed_detach_others: false,
+            allow_switch_last: false,
+            confirmed_switch_last: false,
             filter: String::default(),
             cursor_pos: 0,
+            explicit_search_mode: false,
+            searching: true,
             keymap,
             tmux,
-        }
+            status: None,
+            preview_failed_for: None,
+            debug_overlay: std::env::var_os(DEBUG_OVERLAY_VAR)
+                .is_some_and(|value| !value.is_empty()),
+            frame_times: FrameTimes::default(),
+            wakeups,
+            wakeup_sender,
+            request_redraw,
+            redraw_requested,
+        };
+        picker.inject(list);
+
+        picker
     }
 
     pub fn set_colors(mut self, colors: Option<&'a PickerColorConfig>) -> Self {
@@ -88,23 +320,291 @@ impl<'a> Picker<'a> {
         self
     }
 
+    pub fn set_color_mode(mut self, color: Option<ColorMode>) -> Self {
+        self.styled = color.unwrap_or_default().enabled();
+
+        self
+    }
+
+    /// Require entering search mode before typed characters go into the filter
+    pub fn set_explicit_search_mode(mut self, explicit: bool) -> Self {
+        self.explicit_search_mode = explicit;
+        self.searching = !explicit;
+
+        self
+    }
+
+    /// Whether the preview is shown when the picker opens, it can be toggled with the
+    /// `toggle_preview` action
+    pub fn set_preview_visible(mut self, visible: bool) -> Self {
+        self.preview_visible = visible;
+
+        self
+    }
+
+    /// Add a preview to switch to with the `next_preview` action, titled `title`. The preview the
+    /// picker was created with is titled `first_title`
+    pub fn add_preview_tab(mut self, first_title: &str, title: &str, preview: Preview<'a>) -> Self {
+        if self.preview_tabs.is_empty() {
+            self.preview_tabs
+                .push((first_title.to_owned(), Preview::None));
+        }
+        self.preview_tabs.push((title.to_owned(), preview));
+
+        self
+    }
+
+    /// Add the items sent on `updates` while the picker is running, skipping the ones it already
+    /// lists. They are shown once `request_redraw` is called
+    pub fn set_item_updates(mut self, updates: Receiver<Vec<String>>) -> Self {
+        self.item_updates = Some(updates);
+        self
+    }
+
+    /// Show details of items sent while the picker runs, like the ones of `set_item_details`, for
+    /// details that take a while to get. Details of items that aren't listed are kept for when they
+    /// are added. They are shown once `request_redraw` is called
+    pub fn set_detail_updates(mut self, updates: Receiver<(String, String)>) -> Self {
+        self.detail_updates = Some(updates);
+
+        self
+    }
+
+    /// Start with `query` in the filter
+    pub fn set_filter(mut self, query: &str) -> Self {
+        let prev_filter = std::mem::take(&mut self.filter);
+        self.filter = query.chars().take(u16::MAX as usize).collect();
+        self.cursor_pos = self.filter.len() as u16;
+        self.update_matcher_pattern(&prev_filter);
+        self
+    }
+
+    /// Show the first `len` items of the list under a `title` header while the filter is empty
+    pub fn set_group(mut self, title: &'a str, len: usize) -> Self {
+        self.group = Some((title, len));
+
+        self
+    }
+
+    /// Show a marker in the given color before items, for example the accent color of a project
+    pub fn set_item_colors(mut self, colors: HashMap<String, Color>) -> Self {
+        self.item_colors = colors;
+
+        self
+    }
+
+    /// Show details after items in a dimmed style, they aren't matched by the filter
+    pub fn set_item_details(mut self, details: HashMap<String, String>) -> Self {
+        self.item_details = details;
+
+        self
+    }
+
+    /// Show a warning below the list when the picker opens, until it times out like other messages
+    pub fn set_warning(mut self, warning: Option<String>) -> Self {
+        self.status = warning.map(|warning| (warning, true, Instant::now()));
+
+        self
+    }
+
+    /// Show the name of what the items are limited to, like the active profile, after the count of
+    /// the items
+    pub fn set_scope(mut self, scope: Option<&str>) -> Self {
+        self.scope = scope.map(str::to_owned);
+
+        self
+    }
+
+    /// Show columns after the items, aligned between the items in view. `columns` is only called
+    /// for the items that are shown, once per item
+    pub fn set_item_columns(mut self, columns: impl Fn(&str) -> Vec<String> + 'a) -> Self {
+        self.item_columns = Some(Box::new(columns));
+        self.column_values.clear();
+        self.inject_again();
+
+        self
+    }
+
+    /// Style the columns of `set_item_columns` one by one and choose which of them the filter
+    /// matches. Columns after the ones given are dimmed and not matched
+    pub fn set_column_options(mut self, options: Vec<ItemColumn>) -> Self {
+        self.column_options = options;
+        self.inject_again();
+
+        self
+    }
+
+    /// Match the filter against the text `match_text` returns for an item as well as its name,
+    /// without showing it. It's called once for every item when it's added to the picker
+    pub fn set_match_text(mut self, match_text: impl Fn(&str) -> Option<String> + 'a) -> Self {
+        self.match_text = Some(Box::new(match_text));
+        self.inject_again();
+
+        self
+    }
+
+    /// Include this many lines of a pane's scrollback in its preview, of which only the last ones
+    /// that fit are shown
+    pub fn set_pane_scrollback(mut self, lines: usize) -> Self {
+        self.pane_scrollback = lines;
+
+        self
+    }
+
+    /// Allow killing the highlighted item with the `kill_session` action. The item is removed from
+    /// the list when `kill` succeeds, otherwise its error is shown in the status
+    pub fn set_kill_session(
+        mut self,
+        kill: impl Fn(&str) -> std::result::Result<(), String> + 'a,
+    ) -> Self {
+        self.kill_item = Some(Box::new(kill));
+
+        self
+    }
+
+    /// Allow deleting the highlighted item with the `delete_project` action. The item is removed from
+    /// the list when `delete` succeeds, otherwise its error is shown in the status
+    pub fn set_delete_item(
+        mut self,
+        delete: impl Fn(&str) -> std::result::Result<(), String> + 'a,
+    ) -> Self {
+        self.delete_item = Some(Box::new(delete));
+
+        self
+    }
+
+    /// Whether to ask before killing or deleting an item, which is done by default
+    pub fn set_confirm_actions(mut self, confirm: bool) -> Self {
+        self.confirm_actions = confirm;
+
+        self
+    }
+
+    /// Allow marking the highlighted item with the `set_mark` action, `mark` returns the index of the
+    /// mark it got to show in the status line
+    pub fn set_mark_item(mut self, mark: impl Fn(&str) -> Option<usize> + 'a) -> Self {
+        self.mark_item = Some(Box::new(mark));
+
+        self
+    }
+
+    /// Ask for a session name before confirming an item `suggest` returns a name for, as its own is
+    /// taken, see [`Self::session_name`]. `check` tells why a typed name can't be used
+    pub fn set_name_prompt(
+        mut self,
+        suggest: impl Fn(&str) -> Option<String> + 'a,
+        check: impl Fn(&str, &str) -> Option<String> + 'a,
+    ) -> Self {
+        self.name_prompt = Some((Box::new(suggest), Box::new(check)));
+
+        self
+    }
+
+    /// The session name typed for the item the last run was confirmed with, when its own was taken
+    pub fn session_name(&self) -> Option<&str> {
+        self.session_name.as_deref()
+    }
+
+    /// Allow selecting several items, starting with `selected` already selected
+    pub fn set_multi_select(mut self, selected: &[String]) -> Self {
+        self.multi_select = true;
+        self.selected_items = selected.to_vec();
+
+        self
+    }
+
+    /// Allow confirming with the `confirm_background` action, see [`Self::confirmed_in_background`]
+    pub fn set_allow_background(mut self, allow: bool) -> Self {
+        self.allow_background = allow;
+
+        self
+    }
+
+    /// Whether the last run was confirmed with `confirm_background`, meaning the selection should
+    /// be opened without switching to it and the picker run again
+    pub fn confirmed_in_background(&self) -> bool {
+        self.confirmed_in_background
+    }
+
+    /// Allow confirming with the `confirm_detach_others` action, see
+    /// [`Self::confirmed_detach_others`]
+    pub fn set_allow_detach_others(mut self, allow: bool) -> Self {
+        self.allow_detach_others = allow;
+
+        self
+    }
+
+    /// Whether the last run was confirmed with `confirm_detach_others`, meaning the other clients
+    /// attached to the selected session should be detached before switching to it
+    pub fn confirmed_detach_others(&self) -> bool {
+        self.confirmed_detach_others
+    }
+
+    /// Allow closing the picker with the `switch_last` action, see [`Self::confirmed_switch_last`]
+    pub fn set_allow_switch_last(mut self, allow: bool) -> Self {
+        self.allow_switch_last = allow;
+
+        self
+    }
+
+    /// Whether the last run was closed with `switch_last`, meaning nothing was picked and the
+    /// client should go back to its previous session
+    pub fn confirmed_switch_last(&self) -> bool {
+        self.confirmed_switch_last
+    }
+
     pub fn run(&mut self) -> Result<Option<String>> {
+        Ok(self
+            .run_picker()?
+            .and_then(|selected| selected.into_iter().next()))
+    }
+
+    /// Run the picker returning every selected item, or the highlighted one if none were selected
+    pub fn run_multi(&mut self) -> Result<Option<Vec<String>>> {
+        self.run_picker()
+    }
+
+    fn run_picker(&mut self) -> Result<Option<Vec<String>>> {
+        self.confirmed_in_background = false;
+        self.confirmed_detach_others = false;
+        self.confirmed_switch_last = false;
+        self.session_name = None;
+        if !has_terminal() {
+            return Err(TmsError::TuiError(
+                tr("there is no terminal to show the picker in").to_owned(),
+            ))
+            .attach(Suggestion(
+                "Run tms from a terminal, or list the projects with `tms list-projects`",
+            ));
+        }
+        // Interrupting tms closes the picker like cancelling it, so the terminal is restored
+        let _catch = interrupt::Catch::new();
         enable_raw_mode().map_err(|e| TmsError::TuiError(e.to_string()))?;
+        // From here on the terminal is restored however the picker closes, also when it fails
+        let mut restore = RestoreTerminal {
+            keyboard_enhancement: false,
+        };
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen).map_err(|e| TmsError::TuiError(e.to_string()))?;
+        // Terminals supporting the kitty keyboard protocol can report combinations like
+        // `ctrl-enter` which are indistinguishable from plain keys with legacy input
+        if supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )
+            .map_err(|e| TmsError::TuiError(e.to_string()))?;
+            restore.keyboard_enhancement = true;
+        }
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend).map_err(|e| TmsError::TuiError(e.to_string()))?;
 
-        let selected_str = self
-            .main_loop(&mut terminal)
-            .map_err(|e| TmsError::TuiError(e.to_string()))?;
-
-        disable_raw_mode().map_err(|e| TmsError::TuiError(e.to_string()))?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)
-            .map_err(|e| TmsError::TuiError(e.to_string()))?;
-        terminal
-            .show_cursor()
-            .map_err(|e| TmsError::TuiError(e.to_string()))?;
+        let stop_input = Arc::new(AtomicBool::new(false));
+        let input = self.read_input(stop_input.clone());
+        let selected = self.main_loop(&mut terminal);
+        stop_input.store(true, Ordering::Relaxed);
+        let _ = input.join();
+        let selected_str = selected.map_err(|e| TmsError::TuiError(e.to_string()))?;
 
         Ok(selected_str)
     }
@@ -112,21 +612,75 @@ impl<'a> Picker<'a> {
     fn main_loop(
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<Stdout>>,
-    ) -> Result<Option<String>> {
+    ) -> Result<Option<Vec<String>>> {
         loop {
-            self.matcher.tick(10);
+            if interrupt::interrupted() {
+                return Ok(None);
+            }
+            if self
+                .status
+                .as_ref()
+                .is_some_and(|(_, _, shown)| shown.elapsed() >= STATUS_TIMEOUT)
+            {
+                self.status = None;
+            }
+            self.redraw_requested.store(false, Ordering::Release);
+            self.receive_items();
+            self.receive_details();
+            let started = Instant::now();
+            let matching = self.matcher.tick(10).running;
+            self.frame_times.tick = started.elapsed();
             self.update_selection();
+            self.restore_selection(matching);
+            let started = Instant::now();
             terminal
                 .draw(|f| self.render(f))
                 .map_err(|e| TmsError::TuiError(e.to_string()))?;
+            self.frame_times.frame = started.elapsed();
+
+            // Matches, items and details sent in the background request a redraw themselves, so apart
+            // from them only key presses need one, besides hiding the status and checking for
+            // interrupts now and then
+            let timeout = self
+                .status
+                .as_ref()
+                .map(|(_, _, shown)| STATUS_TIMEOUT.saturating_sub(shown.elapsed()))
+                .map_or(INTERRUPT_CHECK_INTERVAL, |timeout| {
+                    timeout.min(INTERRUPT_CHECK_INTERVAL)
+                });
+            let event = match self.wakeups.recv_timeout(timeout) {
+                Ok(Wakeup::Input(event)) => event.map_err(|e| TmsError::TuiError(e.to_string()))?,
+                Ok(Wakeup::Redraw) | Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            };
 
-            if let Event::Key(key) = event::read().map_err(|e| TmsError::TuiError(e.to_string()))? {
-                if key.kind == KeyEventKind::Press {
-                    match self.keymap.0.get(&key.into()) {
-                        Some(PickerAction::Cancel) => return Ok(None),
+            if let Event::Key(key) = event {
+                if key.kind == KeyEventKind::Press && self.confirm.is_some() {
+                    self.handle_confirm_key(key);
+                } else if key.kind == KeyEventKind::Press && self.prompt.is_some() {
+                    if let Some(confirmed) = self.handle_prompt_key(key) {
+                        return Ok(Some(vec![confirmed]));
+                    }
+                } else if key.kind == KeyEventKind::Press {
+                    match self.action_for(key) {
+                        Some(PickerAction::Cancel) => {
+                            if self.explicit_search_mode
+                                && self.searching
+                                && key.code == KeyCode::Esc
+                            {
+                                self.searching = false;
+                            } else {
+                                return Ok(None);
+                            }
+                        }
                         Some(PickerAction::Confirm) => {
-                            if let Some(selected) = self.get_selected() {
-                                return Ok(Some(selected.to_owned()));
+                            if self.multi_select && !self.selected_items.is_empty() {
+                                return Ok(Some(self.selected_items.clone()));
+                            }
+                            if let Some(selected) = self.get_selected().cloned() {
+                                if !self.prompt_name(&selected, false) {
+                                    return Ok(Some(vec![selected]));
+                                }
                             }
                         }
                         Some(PickerAction::Backspace) => self.remove_filter(),
@@ -136,19 +690,156 @@ impl<'a> Picker<'a> {
                         Some(PickerAction::DeleteToLineEnd) => self.delete_to_line(true),
                         Some(PickerAction::MoveUp) => self.move_up(),
                         Some(PickerAction::MoveDown) => self.move_down(),
+                        Some(PickerAction::PageUp) => self.move_up_by(self.page_size),
+                        Some(PickerAction::PageDown) => self.move_down_by(self.page_size),
+                        Some(PickerAction::MoveToTop) => self.move_up_by(usize::MAX),
+                        Some(PickerAction::MoveToBottom) => self.move_down_by(usize::MAX),
                         Some(PickerAction::CursorLeft) => self.move_cursor_left(),
                         Some(PickerAction::CursorRight) => self.move_cursor_right(),
                         Some(PickerAction::MoveToLineStart) => self.move_to_start(),
                         Some(PickerAction::MoveToLineEnd) => self.move_to_end(),
+                        Some(PickerAction::EnterSearchMode) => {
+                            if !self.searching {
+                                self.searching = true;
+                            } else if let KeyCode::Char(c) = key.code {
+                                self.update_filter(c)
+                            }
+                        }
+                        Some(PickerAction::ConfirmBackground) => {
+                            if let Some(selected) = self
+                                .get_selected()
+                                .filter(|_| self.allow_background)
+                                .cloned()
+                            {
+                                if !self.prompt_name(&selected, true) {
+                                    self.confirmed_in_background = true;
+                                    return Ok(Some(vec![selected]));
+                                }
+                            }
+                        }
+                        Some(PickerAction::ConfirmDetachOthers) => {
+                            if let Some(selected) = self
+                                .get_selected()
+                                .filter(|_| self.allow_detach_others)
+                                .cloned()
+                            {
+                                self.confirmed_detach_others = true;
+                                return Ok(Some(vec![selected]));
+                            }
+                        }
+                        Some(PickerAction::ToggleSelection) => self.toggle_selection(),
+                        Some(PickerAction::KillSession) => {
+                            self.confirm_selected(ConfirmAction::Kill)
+                        }
+                        Some(PickerAction::DeleteProject) => {
+                            self.confirm_selected(ConfirmAction::Delete)
+                        }
+                        Some(PickerAction::SetMark) => self.mark_selected(),
+                        Some(PickerAction::SwitchLast) => {
+                            if self.allow_switch_last {
+                                self.confirmed_switch_last = true;
+                                return Ok(None);
+                            }
+                        }
+                        Some(PickerAction::TogglePreview) => {
+                            self.preview_visible = !self.preview_visible
+                        }
+                        Some(PickerAction::NextPreview) => self.next_preview(),
+                        Some(PickerAction::ToggleDebugOverlay) => {
+                            self.debug_overlay = !self.debug_overlay
+                        }
                         Some(PickerAction::Noop) => {}
                         None => {
                             if let KeyCode::Char(c) = key.code {
-                                self.update_filter(c)
+                                if self.searching {
+                                    self.update_filter(c)
+                                }
                             }
                         }
                     }
+                    if self.keep_selected.is_some() {
+                        self.keep_selected = self.get_selected().cloned();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Open the prompt for a session name when the item's own is taken, returning whether it was
+    /// opened
+    fn prompt_name(&mut self, item: &str, background: bool) -> bool {
+        let Some(suggested) = self
+            .name_prompt
+            .as_ref()
+            .and_then(|(suggest, _)| suggest(item))
+        else {
+            return false;
+        };
+        self.prompt = Some(NamePrompt::new(item, suggested, background));
+        true
+    }
+
+    /// Edit or confirm the name in the prompt, returning the item to confirm once the name is
+    /// accepted
+    fn handle_prompt_key(&mut self, key: KeyEvent) -> Option<String> {
+        let action = self.keymap.0.get(&key.into()).copied();
+        let prompt = self.prompt.as_mut()?;
+        match action {
+            Some(PickerAction::Cancel) => self.prompt = None,
+            Some(PickerAction::Confirm) => {
+                let name = prompt.input.trim();
+                let error = if name.is_empty() {
+                    Some(tr("The session needs a name").to_owned())
+                } else {
+                    self.name_prompt
+                        .as_ref()
+                        .and_then(|(_, check)| check(&prompt.item, name))
+                };
+                if error.is_some() {
+                    prompt.error = error;
+                    return None;
+                }
+                self.session_name = Some(name.to_owned());
+                let prompt = self.prompt.take()?;
+                self.confirmed_in_background = prompt.background;
+                return Some(prompt.item);
+            }
+            Some(PickerAction::Backspace) => prompt.backspace(),
+            Some(PickerAction::Delete) => prompt.delete(),
+            Some(PickerAction::DeleteToLineStart) => prompt.delete_to_start(),
+            Some(PickerAction::CursorLeft) => prompt.cursor = prompt.cursor.saturating_sub(1),
+            Some(PickerAction::CursorRight) => {
+                prompt.cursor = (prompt.cursor + 1).min(prompt.input.chars().count())
+            }
+            // There is no list to move in, so home and end move the cursor as well
+            Some(PickerAction::MoveToLineStart | PickerAction::MoveToTop) => prompt.cursor = 0,
+            Some(PickerAction::MoveToLineEnd | PickerAction::MoveToBottom) => {
+                prompt.cursor = prompt.input.chars().count()
+            }
+            Some(PickerAction::EnterSearchMode) | None => {
+                if let KeyCode::Char(c) = key.code {
+                    prompt.insert(c);
                 }
             }
+            Some(_) => {}
+        }
+        None
+    }
+
+    /// Kill or delete the item when the question is answered with `y`, or close it on `n` and
+    /// `cancel`
+    fn handle_confirm_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y' | 'Y') => {
+                if let Some(confirm) = self.confirm.take() {
+                    self.run_action(confirm.action, &confirm.item);
+                }
+            }
+            KeyCode::Char('n' | 'N') => self.confirm = None,
+            _ if self.keymap.0.get(&key.into()) == Some(&PickerAction::Cancel) => {
+                self.confirm = None
+            }
+            _ => {}
         }
     }
 
@@ -166,12 +857,42 @@ impl<'a> Picker<'a> {
         }
     }
 
+    /// Select the item that was selected before items were added again, by its place among the
+    /// matches once they are updated
+    fn restore_selection(&mut self, matching: bool) {
+        let Some(keep) = &self.keep_selected else {
+            return;
+        };
+        let snapshot = self.matcher.snapshot();
+        if let Some(index) = snapshot
+            .matched_items(..snapshot.matched_item_count())
+            .position(|item| item.data == keep)
+        {
+            self.selection.select(Some(index));
+        }
+        if !matching && self.item_updates.is_none() {
+            self.keep_selected = None;
+        }
+    }
+
     fn render(&mut self, f: &mut Frame) {
+        let area = f.area();
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            f.render_widget(
+                Paragraph::new(tr("Terminal too small")).wrap(Wrap { trim: true }),
+                area,
+            );
+            return;
+        }
+
         let preview_direction;
         let picker_pane;
         let preview_pane;
 
-        let preview_split = if !matches!(self.preview, Preview::None) {
+        let show_preview = self.shows_preview()
+            && area.width >= MIN_PREVIEW_WIDTH
+            && area.height >= MIN_PREVIEW_HEIGHT;
+        let preview_split = if show_preview {
             preview_direction = if f.area().width.div_ceil(2) >= f.area().height {
                 picker_pane = 0;
                 preview_pane = 1;
@@ -192,115 +913,443 @@ impl<'a> Picker<'a> {
             preview_direction = Direction::Horizontal;
             Rc::new([f.area()])
         };
+        let preview_text = if show_preview {
+            // Without the border, which takes a line when the preview is below the list
+            let border = u16::from(preview_direction == Direction::Vertical);
+            self.preview_height =
+                usize::from(preview_split[preview_pane].height.saturating_sub(border));
+            let started = Instant::now();
+            let text = self.selected_preview_text();
+            self.frame_times.preview = started.elapsed();
+            text
+        } else {
+            String::new()
+        };
 
+        let status_height = u16::from(self.status.is_some());
         let layout = Layout::new(
             Direction::Vertical,
             [
-                Constraint::Length(preview_split[picker_pane].height - 1),
+                Constraint::Length(
+                    preview_split[picker_pane]
+                        .height
+                        .saturating_sub(1 + status_height),
+                ),
+                Constraint::Length(status_height),
                 Constraint::Length(1),
             ],
         )
         .split(preview_split[picker_pane]);
 
-        let snapshot = self.matcher.snapshot();
-        let matches = snapshot
-            .matched_items(..snapshot.matched_item_count())
-            .map(|item| ListItem::new(item.data.as_str()));
-
         let colors = if let Some(colors) = self.colors {
             colors.to_owned()
         } else {
             PickerColorConfig::default_colors()
         };
 
+        // Only the matches that fit in the list are turned into items, which keeps drawing fast
+        // with thousands of them
+        let snapshot = self.matcher.snapshot();
+        let match_count = snapshot.matched_item_count() as usize;
+        let height = usize::from(layout[0].height.saturating_sub(1));
+        self.page_size = height.max(1);
+        // Scrolled no further than needed to fill the list, as matches go away while filtering
+        let mut offset = self
+            .selection
+            .offset()
+            .min(match_count.saturating_sub(height));
+        if let Some(selected) = self.selection.selected() {
+            if selected < offset {
+                offset = selected;
+            } else if selected >= offset + height {
+                offset = selected + 1 - height.max(1);
+            }
+        }
+        let visible = offset..(offset + height).min(match_count);
+        let visible_items = || snapshot.matched_items(visible.start as u32..visible.end as u32);
+
+        let mut column_widths = Vec::new();
+        if let Some(columns) = &self.item_columns {
+            for item in visible_items() {
+                let values = self
+                    .column_values
+                    .entry(item.data.clone())
+                    .or_insert_with(|| columns(item.data));
+                column_widths.resize(column_widths.len().max(values.len()), 0);
+                for (width, value) in column_widths.iter_mut().zip(values.iter()) {
+                    *width = (*width).max(value.chars().count());
+                }
+            }
+        }
+        let name_width = visible_items()
+            .map(|item| item.data.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        let mut matches = visible_items()
+            .map(|item| {
+                let mut line = Line::default();
+                if self.multi_select {
+                    let marker = if self.selected_items.contains(item.data) {
+                        "+ "
+                    } else {
+                        "  "
+                    };
+                    line.push_span(marker);
+                }
+                if self.styled && !self.item_colors.is_empty() {
+                    match self.item_colors.get(item.data) {
+                        Some(color) => {
+                            line.push_span(Span::styled("● ", Style::default().fg(*color)))
+                        }
+                        None => line.push_span("  "),
+                    }
+                }
+                line.push_span(item.data.as_str());
+                if let Some(values) = self.column_values.get(item.data) {
+                    let padding = name_width - item.data.chars().count();
+                    line.push_span(" ".repeat(padding));
+                    for (i, width) in column_widths.iter().enumerate() {
+                        let value = values.get(i).map(String::as_str).unwrap_or_default();
+                        // The last column isn't padded, so no spaces trail the line
+                        let width = if i + 1 == column_widths.len() {
+                            0
+                        } else {
+                            *width
+                        };
+                        let style = self
+                            .column_options
+                            .get(i)
+                            .and_then(|column| column.style)
+                            .unwrap_or_else(|| Style::default().dim());
+                        line.push_span(Span::styled(
+                            format!("  {value:width$}"),
+                            self.style(style),
+                        ));
+                    }
+                }
+                if let Some(details) = self.item_details.get(item.data) {
+                    line.push_span(Span::styled(
+                        format!("  {details}"),
+                        self.style(Style::default().dim()),
+                    ));
+                }
+                ListItem::new(line)
+            })
+            .collect::<Vec<_>>();
+
+        let mut selection = ListState::default()
+            .with_selected(self.selection.selected().map(|selected| selected - offset));
+        if let Some((title, len)) = self.group {
+            if self.filter.is_empty() && len > 0 && visible.contains(&len) {
+                // The list is rendered bottom to top so the header goes after the group's items
+                matches.insert(
+                    len - offset,
+                    ListItem::new(title).style(
+                        self.style(Style::default().fg(colors.info_color()))
+                            .italic(),
+                    ),
+                );
+                if let Some(selected) = selection.selected() {
+                    if selected >= len - offset {
+                        selection.select(Some(selected + 1));
+                    }
+                }
+            }
+        }
+
         let table = List::new(matches)
-            .highlight_style(colors.highlight_style())
+            .highlight_style(if self.styled {
+                colors.highlight_style()
+            } else {
+                Style::default().reversed()
+            })
             .direction(ListDirection::BottomToTop)
             .highlight_spacing(HighlightSpacing::Always)
             .highlight_symbol("> ")
             .block(
                 Block::default()
                     .borders(Borders::BOTTOM)
-                    .border_style(Style::default().fg(colors.border_color()))
-                    .title_style(Style::default().fg(colors.info_color()))
+                    .border_style(self.style(Style::default().fg(colors.border_color())))
+                    .title_style(self.style(Style::default().fg(colors.info_color())))
                     .title_position(Position::Bottom)
-                    .title(format!(
-                        "{}/{}",
-                        snapshot.matched_item_count(),
-                        snapshot.item_count()
-                    )),
+                    .title({
+                        let mut title = format!(
+                            "{}/{}",
+                            snapshot.matched_item_count(),
+                            snapshot.item_count()
+                        );
+                        if !self.selected_items.is_empty() {
+                            title.push_str(&format!(" ({} selected)", self.selected_items.len()));
+                        }
+                        if let Some(scope) = &self.scope {
+                            title.push_str(&format!(" · {scope}"));
+                        }
+                        title
+                    }),
+            );
+        f.render_stateful_widget(table, layout[0], &mut selection);
+        *self.selection.offset_mut() = offset;
+
+        if match_count > height {
+            // The best matches are at the bottom, so the scrollbar is turned upside down
+            let mut scrollbar = ScrollbarState::new(match_count - height + 1)
+                .viewport_content_length(height)
+                .position(match_count - height - offset);
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None)
+                    .style(self.style(Style::default().fg(colors.border_color()))),
+                Rect {
+                    height: layout[0].height.saturating_sub(1),
+                    ..layout[0]
+                },
+                &mut scrollbar,
             );
-        f.render_stateful_widget(table, layout[0], &mut self.selection);
+        }
 
-        let prompt = Span::styled("> ", Style::default().fg(colors.prompt_color()));
-        let input_text = Span::raw(&self.filter);
-        let input_line = Line::from(vec![prompt, input_text]);
+        let prompt = Span::styled("> ", self.style(Style::default().fg(colors.prompt_color())));
+        let input_line = match &self.confirm {
+            Some(confirm) => Line::from(vec![
+                Span::styled(
+                    confirm.question(),
+                    self.style(Style::default().fg(colors.error_color())),
+                ),
+                Span::styled(
+                    " y/n",
+                    self.style(Style::default().fg(colors.prompt_color())),
+                ),
+            ]),
+            None => Line::from(vec![prompt, Span::raw(&self.filter)]),
+        };
         let input = Paragraph::new(vec![input_line]);
-        f.render_widget(input, layout[1]);
-        f.set_cursor_position(layout::Position {
-            x: layout[1].x + self.cursor_pos + 2,
-            y: layout[1].y,
-        });
+        f.render_widget(input, layout[2]);
+        if self.searching && self.confirm.is_none() {
+            f.set_cursor_position(layout::Position {
+                x: (layout[2].x + self.cursor_pos + 2).min(layout[2].right() - 1),
+                y: layout[2].y,
+            });
+        }
 
-        if !matches!(self.preview, Preview::None) {
+        if let Some((message, error, _)) = &self.status {
+            let color = if *error {
+                colors.error_color()
+            } else {
+                colors.info_color()
+            };
+            let status =
+                Paragraph::new(message.as_str()).style(self.style(Style::default().fg(color)));
+            f.render_widget(status, layout[1]);
+        }
+
+        if show_preview {
             self.render_preview(
                 f,
+                preview_text,
                 &colors.border_color(),
                 &preview_direction,
                 preview_split[preview_pane],
             );
         }
+
+        if let Some(prompt) = &self.prompt {
+            self.render_prompt(f, prompt, &colors);
+        }
+        if self.debug_overlay {
+            self.render_debug_overlay(f, &colors);
+        }
+    }
+
+    /// Draw how long the last frame took and how many items there are in the top right corner.
+    /// The frame time is of the frame before this one, which is still being drawn
+    fn render_debug_overlay(&self, f: &mut Frame, colors: &PickerColorConfig) {
+        let snapshot = self.matcher.snapshot();
+        let milliseconds = |time: Duration| format!("{:.1}ms", time.as_secs_f64() * 1000.0);
+        let lines = [
+            format!("frame   {}", milliseconds(self.frame_times.frame)),
+            format!("match   {}", milliseconds(self.frame_times.tick)),
+            format!("preview {}", milliseconds(self.frame_times.preview)),
+            format!(
+                "items   {}/{}",
+                snapshot.matched_item_count(),
+                snapshot.item_count()
+            ),
+        ];
+
+        let area = f.area();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or_default()
+            .saturating_add(2);
+        let width = u16::try_from(width).unwrap_or(u16::MAX).min(area.width);
+        let area = Rect {
+            x: area.right() - width,
+            y: area.y,
+            width,
+            height: area.height.min(lines.len() as u16 + 2),
+        };
+        let overlay = Paragraph::new(lines.map(Line::from).to_vec()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(self.style(Style::default().fg(colors.border_color()))),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(overlay, area);
+    }
+
+    /// Draw the prompt for a session name in a small box over the middle of the picker
+    fn render_prompt(&self, f: &mut Frame, prompt: &NamePrompt, colors: &PickerColorConfig) {
+        let area = f.area();
+        let width = area.width.min(60);
+        let area = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + area.height.saturating_sub(3) / 2,
+            width,
+            height: area.height.min(3),
+        };
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.style(Style::default().fg(colors.border_color())))
+            .title(tr_fmt("{} is taken, open it as", &[&prompt.item]));
+        if let Some(error) = &prompt.error {
+            block = block.title_bottom(Span::styled(
+                error.as_str(),
+                self.style(Style::default().fg(colors.error_color())),
+            ));
+        }
+        let input = Paragraph::new(Line::from(vec![
+            Span::styled("> ", self.style(Style::default().fg(colors.prompt_color()))),
+            Span::raw(prompt.input.as_str()),
+        ]))
+        .block(block);
+
+        f.render_widget(Clear, area);
+        f.render_widget(input, area);
+        let cursor = u16::try_from(prompt.cursor).unwrap_or(u16::MAX);
+        f.set_cursor_position(layout::Position {
+            x: (area.x + 3).saturating_add(cursor).min(area.right() - 2),
+            y: area.y + 1,
+        });
     }
 
     fn render_preview(
         &self,
         f: &mut Frame,
+        text: String,
         border_color: &Color,
         direction: &Direction,
         rect: Rect,
     ) {
-        let text = if let Some(item_data) = self.get_selected() {
-            let output = match self.preview {
-                Preview::SessionPane => self.tmux.capture_pane(item_data),
-                Preview::WindowPane => self.tmux.capture_pane(
-                    item_data
-                        .split_once(' ')
-                        .map(|val| val.0)
-                        .unwrap_or_default(),
-                ),
-                Preview::Directory => process::Command::new("ls")
-                    .args(["-1", item_data])
-                    .output()
-                    .unwrap_or_else(|_| {
-                        panic!("Failed to execute the command for directory: {}", item_data)
-                    }),
-                Preview::None => panic!("preview rendering should not have occured"),
-            };
-
-            if output.status.success() {
-                String::from_utf8(output.stdout).unwrap()
-            } else {
-                "".to_string()
-            }
-        } else {
-            "".to_string()
-        };
-        let text = str_to_text(&text, (rect.width - 1).into());
+        let mut text = str_to_text(&text, rect.width.saturating_sub(1).into());
+        if !self.styled {
+            text.lines
+                .iter_mut()
+                .flat_map(|line| line.spans.iter_mut())
+                .for_each(|span| span.style = Style::default());
+        }
         let border_position = if *direction == Direction::Horizontal {
             Borders::LEFT
         } else {
             Borders::BOTTOM
         };
-        let preview = Paragraph::new(text)
-            .block(
-                Block::default()
-                    .borders(border_position)
-                    .border_style(Style::default().fg(*border_color)),
-            )
-            .wrap(Wrap { trim: false });
+        let mut block = Block::default()
+            .borders(border_position)
+            .border_style(self.style(Style::default().fg(*border_color)));
+        if !self.preview_tabs.is_empty() {
+            let tabs = self
+                .preview_tabs
+                .iter()
+                .enumerate()
+                .map(|(index, (title, _))| {
+                    let style = if index == self.preview_tab {
+                        Style::default().bold().reversed()
+                    } else {
+                        Style::default().fg(*border_color)
+                    };
+                    Span::styled(format!(" {title} "), self.style(style))
+                });
+            block = block.title(Line::from_iter(tabs));
+        }
+        let preview = Paragraph::new(text).block(block).wrap(Wrap { trim: false });
         f.render_widget(preview, rect);
     }
 
+    /// The preview of the selected item, showing why it failed once for every item it fails for
+    fn selected_preview_text(&mut self) -> String {
+        let Some(selected) = self.get_selected().cloned() else {
+            return String::new();
+        };
+        match self.preview_text(&selected) {
+            Ok(text) => text,
+            Err(message) => {
+                if self.preview_failed_for.as_ref() != Some(&selected) {
+                    self.set_status(message);
+                    self.preview_failed_for = Some(selected);
+                }
+                String::new()
+            }
+        }
+    }
+
+    fn preview_text(&self, item_data: &str) -> std::result::Result<String, String> {
+        let capture = PaneCapture {
+            scrollback: self.pane_scrollback,
+            max_lines: self.preview_height,
+        };
+        let provider: &dyn PreviewProvider = match self.current_preview() {
+            Preview::SessionPane => &SessionPanePreview(capture),
+            Preview::WindowPane => &WindowPanePreview(capture),
+            Preview::Directory => &DirectoryPreview,
+            Preview::Text(preview) => preview,
+            Preview::Custom(provider) => provider.as_ref(),
+            Preview::None => panic!("preview rendering should not have occured"),
+        };
+        provider.preview(item_data, self.tmux)
+    }
+
+    /// Show a message below the list until it times out
+    fn set_status(&mut self, message: String) {
+        self.status = Some((message, true, Instant::now()));
+    }
+
+    fn set_info(&mut self, message: String) {
+        self.status = Some((message, false, Instant::now()));
+    }
+
+    fn shows_preview(&self) -> bool {
+        self.preview_visible && !matches!(self.current_preview(), Preview::None)
+    }
+
+    fn current_preview(&self) -> &Preview<'a> {
+        match self.preview_tab {
+            0 => &self.preview,
+            tab => &self.preview_tabs[tab].1,
+        }
+    }
+
+    /// Show the next preview tab, or the first one after the last, showing the preview when it's
+    /// hidden
+    fn next_preview(&mut self) {
+        if self.preview_visible && !self.preview_tabs.is_empty() {
+            self.preview_tab = (self.preview_tab + 1) % self.preview_tabs.len();
+            self.preview_failed_for = None;
+        }
+        self.preview_visible = true;
+    }
+
+    /// The given style, or no style at all when colors are disabled
+    fn style(&self, style: Style) -> Style {
+        if self.styled {
+            style
+        } else {
+            Style::default()
+        }
+    }
+
     fn get_selected(&self) -> Option<&String> {
         if let Some(index) = self.selection.selected() {
             return self
@@ -313,7 +1362,223 @@ impl<'a> Picker<'a> {
         None
     }
 
+    /// The function to call after sending items or details while the picker runs, for it to show
+    /// them
+    pub fn request_redraw(&self) -> RequestRedraw {
+        self.request_redraw.clone()
+    }
+
+    /// Read the events of the terminal in the background until `stop` is set, so the picker can
+    /// wait for them and for redraws at the same time
+    fn read_input(&self, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+        let sender = self.wakeup_sender.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match event::poll(INPUT_STOP_INTERVAL) {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        if sender.send(Wakeup::Input(event::read())).is_err() {
+                            return;
+                        }
+                    }
+                    Err(error) => {
+                        let _ = sender.send(Wakeup::Input(Err(error)));
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    fn receive_items(&mut self) {
+        loop {
+            let Some(updates) = &self.item_updates else {
+                return;
+            };
+            match updates.try_recv() {
+                Ok(items) => {
+                    let listed: HashSet<&String> = self.items.iter().collect();
+                    let new_items: Vec<String> = items
+                        .into_iter()
+                        .filter(|item| !listed.contains(item) && !self.removed_items.contains(item))
+                        .collect();
+                    if !new_items.is_empty() {
+                        self.keep_selected = self.get_selected().cloned();
+                    }
+                    self.inject(&new_items);
+                    self.items.extend(new_items);
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.item_updates = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn receive_details(&mut self) {
+        let Some(updates) = &self.detail_updates else {
+            return;
+        };
+        loop {
+            match updates.try_recv() {
+                Ok((item, details)) => {
+                    self.item_details.insert(item, details);
+                }
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.detail_updates = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Ask whether to kill or delete the highlighted item, or do it right away when actions aren't
+    /// confirmed. Nothing happens when the picker doesn't allow the action
+    fn confirm_selected(&mut self, action: ConfirmAction) {
+        let allowed = match action {
+            ConfirmAction::Kill => self.kill_item.is_some(),
+            ConfirmAction::Delete => self.delete_item.is_some(),
+        };
+        let Some(selected) = self.get_selected().cloned().filter(|_| allowed) else {
+            return;
+        };
+        if self.confirm_actions {
+            self.confirm = Some(ConfirmPrompt {
+                item: selected,
+                action,
+            });
+        } else {
+            self.run_action(action, &selected);
+        }
+    }
+
+    fn run_action(&mut self, action: ConfirmAction, item: &str) {
+        match action {
+            ConfirmAction::Kill => self.kill_item(item),
+            ConfirmAction::Delete => self.delete_item(item),
+        }
+    }
+
+    fn kill_item(&mut self, item: &str) {
+        let Some(kill) = &self.kill_item else {
+            return;
+        };
+        if let Err(reason) = kill(item) {
+            self.set_status(tr_fmt("Could not kill {}: {}", &[&item, &reason]));
+            return;
+        }
+        self.remove_item(item);
+    }
+
+    fn delete_item(&mut self, item: &str) {
+        let Some(delete) = &self.delete_item else {
+            return;
+        };
+        if let Err(reason) = delete(item) {
+            self.set_status(tr_fmt("Could not delete {}: {}", &[&item, &reason]));
+            return;
+        }
+        self.remove_item(item);
+        self.set_info(tr_fmt("Moved {} to the trash", &[&item]));
+    }
+
+    fn remove_item(&mut self, removed: &str) {
+        self.items.retain(|item| item != removed);
+        self.selected_items.retain(|item| item != removed);
+        self.removed_items.insert(removed.to_owned());
+        // Nucleo can't remove single items, so the remaining ones are injected again
+        self.matcher.restart(true);
+        self.inject(&self.items.clone());
+    }
+
+    /// Add the items to the matcher, each matched by its name, the values of the matched columns and
+    /// its match text
+    fn inject(&mut self, items: &[String]) {
+        let matched: Vec<usize> = self
+            .column_options
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| column.matched)
+            .map(|(i, _)| i)
+            .collect();
+        let injector = self.matcher.injector();
+        for item in items {
+            let mut text = item.to_owned();
+            if let (Some(columns), false) = (&self.item_columns, matched.is_empty()) {
+                let values = self
+                    .column_values
+                    .entry(item.to_owned())
+                    .or_insert_with(|| columns(item));
+                for value in matched.iter().filter_map(|i| values.get(*i)) {
+                    text.push(' ');
+                    text.push_str(value);
+                }
+            }
+            if let Some(extra) = self
+                .match_text
+                .as_ref()
+                .and_then(|match_text| match_text(item))
+            {
+                text.push(' ');
+                text.push_str(&extra);
+            }
+            injector.push(item.to_owned(), |_, dst| dst[0] = text.into());
+        }
+    }
+
+    /// Inject all items again after what they're matched by changed
+    fn inject_again(&mut self) {
+        if self.match_text.is_some() || self.column_options.iter().any(|column| column.matched) {
+            self.matcher.restart(true);
+            self.inject(&self.items.clone());
+        }
+    }
+
+    fn mark_selected(&mut self) {
+        let Some(mark) = &self.mark_item else {
+            return;
+        };
+        let Some(selected) = self.get_selected().cloned() else {
+            return;
+        };
+        match mark(&selected) {
+            Some(index) => self.set_info(tr_fmt("Marked {} as {}", &[&selected, &index])),
+            None => self.set_status(tr_fmt("Could not mark {}", &[&selected])),
+        }
+    }
+
+    fn toggle_selection(&mut self) {
+        if !self.multi_select {
+            return;
+        }
+        let Some(selected) = self.get_selected().cloned() else {
+            return;
+        };
+
+        if let Some(index) = self
+            .selected_items
+            .iter()
+            .position(|item| *item == selected)
+        {
+            self.selected_items.remove(index);
+        } else {
+            self.selected_items.push(selected);
+        }
+    }
+
     fn move_up(&mut self) {
+        self.move_up_by(1);
+    }
+
+    fn move_down(&mut self) {
+        self.move_down_by(1);
+    }
+
+    /// Move the selection towards the worse matches, which are shown above the better ones
+    fn move_up_by(&mut self, lines: usize) {
         let item_count = self.matcher.snapshot().matched_item_count() as usize;
         if item_count == 0 {
             return;
@@ -322,20 +1587,30 @@ impl<'a> Picker<'a> {
         let max = item_count - 1;
 
         match self.selection.selected() {
-            Some(i) if i >= max => {}
-            Some(i) => self.selection.select(Some(i + 1)),
+            Some(i) => self
+                .selection
+                .select(Some(i.saturating_add(lines).min(max))),
             None => self.selection.select(Some(0)),
         }
     }
 
-    fn move_down(&mut self) {
+    fn move_down_by(&mut self, lines: usize) {
         match self.selection.selected() {
-            Some(0) => {}
-            Some(i) => self.selection.select(Some(i - 1)),
+            Some(i) => self.selection.select(Some(i.saturating_sub(lines))),
             None => self.selection.select(Some(0)),
         }
     }
 
+    /// The action bound to the key. Outside of search mode the ones editing the filter are left
+    /// out, like typed characters are
+    fn action_for(&self, key: KeyEvent) -> Option<PickerAction> {
+        self.keymap
+            .0
+            .get(&key.into())
+            .copied()
+            .filter(|action| self.searching || !action.edits_filter())
+    }
+
     fn move_cursor_left(&mut self) {
         if self.cursor_pos > 0 {
             self.cursor_pos -= 1;
@@ -450,7 +1725,103 @@ impl<'a> Picker<'a> {
     }
 }
 
-fn request_redraw() {}
+/// The actions on an item that are confirmed before they are done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfirmAction {
+    Kill,
+    Delete,
+}
+
+/// The question whether to kill or delete an item
+struct ConfirmPrompt {
+    item: String,
+    action: ConfirmAction,
+}
+
+impl ConfirmPrompt {
+    fn question(&self) -> String {
+        match self.action {
+            ConfirmAction::Kill => tr_fmt("Kill session {}?", &[&self.item]),
+            ConfirmAction::Delete => tr_fmt("Move {} to the trash?", &[&self.item]),
+        }
+    }
+}
+
+/// The prompt for the session name of an item whose own name is taken
+struct NamePrompt {
+    item: String,
+    input: String,
+    /// The position of the cursor in characters
+    cursor: usize,
+    /// Whether the item was confirmed with `confirm_background`
+    background: bool,
+    error: Option<String>,
+}
+
+impl NamePrompt {
+    fn new(item: &str, suggested: String, background: bool) -> Self {
+        NamePrompt {
+            item: item.to_owned(),
+            cursor: suggested.chars().count(),
+            input: suggested,
+            background,
+            error: None,
+        }
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.input.len(), |(index, _)| index)
+    }
+
+    fn insert(&mut self, c: char) {
+        let index = self.byte_index(self.cursor);
+        self.input.insert(index, c);
+        self.cursor += 1;
+        self.error = None;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete();
+        }
+    }
+
+    fn delete(&mut self) {
+        let index = self.byte_index(self.cursor);
+        if index < self.input.len() {
+            self.input.remove(index);
+            self.error = None;
+        }
+    }
+
+    fn delete_to_start(&mut self) {
+        let index = self.byte_index(self.cursor);
+        self.input.drain(..index);
+        self.cursor = 0;
+        self.error = None;
+    }
+}
+
+/// Whether the picker can be shown, which needs a terminal to read keys from and draw on, unlike
+/// when tms runs from a script or `run-shell`
+pub fn has_terminal() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// The item best matching `query` like the picker would rank it, without showing the picker
+pub fn best_match<'s>(list: &'s [String], query: &str) -> Option<&'s String> {
+    let mut matcher = Matcher::new(nucleo::Config::DEFAULT);
+    Pattern::parse(query, CaseMatching::Smart, Normalization::Smart)
+        .match_list(list, &mut matcher)
+        .into_iter()
+        // Ties go to the shorter item and then the earlier one, like in the picker
+        .min_by_key(|(item, score)| (Reverse(*score), item.chars().count()))
+        .map(|(item, _)| item)
+}
 
 fn str_to_text(s: &str, max: usize) -> Text {
     let mut text = Text::default();
@@ -563,3 +1934,367 @@ fn str_to_text(s: &str, max: usize) -> Text {
 
     text
 }
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    #[test]
+    fn best_match_prefers_the_highest_score() {
+        let list = ["dotfiles-old", "dotfiles", "website"].map(String::from);
+
+        assert_eq!(best_match(&list, "dotfiles").unwrap(), "dotfiles");
+        assert_eq!(best_match(&list, "web").unwrap(), "website");
+        assert!(best_match(&list, "xyz").is_none());
+    }
+
+    #[test]
+    fn best_match_understands_the_filter_syntax() {
+        let list = ["dotfiles", "dotfiles-old", "old-website", "web"].map(String::from);
+
+        assert_eq!(best_match(&list, "^old").unwrap(), "old-website");
+        assert_eq!(best_match(&list, "old$").unwrap(), "dotfiles-old");
+        assert_eq!(best_match(&list, "^web$").unwrap(), "web");
+        assert_eq!(best_match(&list, "'s-o").unwrap(), "dotfiles-old");
+        assert_eq!(best_match(&list, "dot !old").unwrap(), "dotfiles");
+        assert!(best_match(&list, "'dtf").is_none());
+    }
+
+    #[test]
+    fn renders_in_tiny_terminals() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(
+            &list,
+            Preview::Text(Box::new(|item| format!("preview of {item}"))),
+            None,
+            &tmux,
+        );
+
+        for (width, height) in [(0, 0), (1, 1), (9, 2), (10, 3), (20, 5), (29, 40), (80, 24)] {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| picker.render(f)).unwrap();
+        }
+
+        let mut terminal = Terminal::new(TestBackend::new(9, 2)).unwrap();
+        terminal.draw(|f| picker.render(f)).unwrap();
+        let content: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(content.starts_with("Terminal"));
+    }
+
+    #[test]
+    fn pages_through_long_lists() {
+        let list: Vec<String> = (0..1000).map(|i| format!("project-{i:04}")).collect();
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux);
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+        let mut terminal = Terminal::new(TestBackend::new(30, 12)).unwrap();
+        terminal.draw(|f| picker.render(f)).unwrap();
+
+        picker.move_up_by(picker.page_size);
+        picker.move_up_by(picker.page_size);
+        assert_eq!(picker.selection.selected(), Some(20));
+        picker.move_up_by(usize::MAX);
+        assert_eq!(picker.selection.selected(), Some(999));
+
+        terminal.draw(|f| picker.render(f)).unwrap();
+        let top_row: String = (0..30)
+            .map(|x| terminal.backend().buffer()[(x, 0)].symbol())
+            .collect();
+        assert!(top_row.starts_with("> "), "{top_row}");
+        picker.move_down_by(usize::MAX);
+        assert_eq!(picker.selection.selected(), Some(0));
+    }
+
+    #[test]
+    fn shows_a_status_when_killing_fails() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux)
+            .set_kill_session(|_| Err("it is protected".to_owned()));
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+
+        picker.confirm_selected(ConfirmAction::Kill);
+        picker.handle_confirm_key(KeyEvent::from(KeyCode::Char('y')));
+
+        let mut terminal = Terminal::new(TestBackend::new(50, 5)).unwrap();
+        terminal.draw(|f| picker.render(f)).unwrap();
+        let status_row: String = (0..50)
+            .map(|x| terminal.backend().buffer()[(x, 3)].symbol())
+            .collect();
+        assert_eq!(
+            status_row.trim_end(),
+            "Could not kill dotfiles: it is protected"
+        );
+        assert_eq!(picker.items.len(), 2);
+    }
+
+    #[test]
+    fn asks_before_deleting() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let deleted = std::cell::RefCell::new(Vec::new());
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_delete_item(|item| {
+            deleted.borrow_mut().push(item.to_owned());
+            Ok(())
+        });
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+        assert!(!picker
+            .keymap
+            .0
+            .values()
+            .any(|action| *action == PickerAction::DeleteProject));
+
+        picker.confirm_selected(ConfirmAction::Delete);
+        picker.handle_confirm_key(KeyEvent::from(KeyCode::Char('n')));
+        assert!(picker.confirm.is_none());
+        assert!(deleted.borrow().is_empty());
+
+        picker.confirm_selected(ConfirmAction::Delete);
+        picker.handle_confirm_key(KeyEvent::from(KeyCode::Enter));
+        assert!(picker.confirm.is_some());
+        picker.handle_confirm_key(KeyEvent::from(KeyCode::Char('y')));
+        assert_eq!(*deleted.borrow(), ["dotfiles"]);
+        assert_eq!(picker.items, ["website"]);
+    }
+
+    #[test]
+    fn asks_in_the_input_line_before_killing() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let killed = std::cell::RefCell::new(Vec::new());
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_kill_session(|item| {
+            killed.borrow_mut().push(item.to_owned());
+            Ok(())
+        });
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+
+        picker.confirm_selected(ConfirmAction::Kill);
+
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+        terminal.draw(|f| picker.render(f)).unwrap();
+        let input_row: String = (0..40)
+            .map(|x| terminal.backend().buffer()[(x, 4)].symbol())
+            .collect();
+        assert_eq!(input_row.trim_end(), "Kill session dotfiles? y/n");
+        assert!(killed.borrow().is_empty());
+
+        let mut picker = picker.set_confirm_actions(false);
+        picker.handle_confirm_key(KeyEvent::from(KeyCode::Esc));
+        picker.confirm_selected(ConfirmAction::Kill);
+        assert!(picker.confirm.is_none());
+        assert_eq!(*killed.borrow(), ["dotfiles"]);
+    }
+
+    #[test]
+    fn shows_the_index_of_a_new_mark() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_mark_item(|_| Some(2));
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+
+        picker.mark_selected();
+
+        let (message, error, _) = picker.status.as_ref().unwrap();
+        assert_eq!(message, "Marked dotfiles as 2");
+        assert!(!error);
+    }
+
+    #[test]
+    fn asks_for_another_name_when_it_is_taken() {
+        let list = ["api", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_name_prompt(
+            |item| (item == "api").then(|| "client/api".to_owned()),
+            |_, name| (name == "api").then(|| "api is used".to_owned()),
+        );
+        let key = KeyEvent::from;
+
+        assert!(picker.prompt_name("api", false));
+        for _ in 0.."api".len() {
+            picker.handle_prompt_key(key(KeyCode::Left));
+        }
+        for _ in 0.."client/".len() {
+            picker.handle_prompt_key(key(KeyCode::Backspace));
+        }
+        assert_eq!(picker.handle_prompt_key(key(KeyCode::Enter)), None);
+        assert_eq!(
+            picker.prompt.as_ref().unwrap().error.as_deref(),
+            Some("api is used")
+        );
+
+        picker.handle_prompt_key(key(KeyCode::End));
+        picker.handle_prompt_key(key(KeyCode::Char('2')));
+        assert_eq!(
+            picker.handle_prompt_key(key(KeyCode::Enter)),
+            Some("api".to_owned())
+        );
+        assert_eq!(picker.session_name(), Some("api2"));
+        assert!(!picker.prompt_name("website", false));
+    }
+
+    #[test]
+    fn adds_items_sent_while_running() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let (sender, updates) = std::sync::mpsc::channel();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_item_updates(updates);
+
+        sender
+            .send(["website", "notes"].map(String::from).to_vec())
+            .unwrap();
+        drop(sender);
+        picker.receive_items();
+        picker.receive_items();
+
+        assert_eq!(picker.items, ["dotfiles", "website", "notes"]);
+        assert!(picker.item_updates.is_none());
+        while picker.matcher.tick(10).running {}
+        assert_eq!(picker.matcher.snapshot().item_count(), 3);
+    }
+
+    #[test]
+    fn only_edits_the_filter_in_search_mode() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker =
+            Picker::new(&list, Preview::None, None, &tmux).set_explicit_search_mode(true);
+        let ctrl_w = KeyEvent::new(KeyCode::Char('w'), crossterm::event::KeyModifiers::CONTROL);
+
+        assert_eq!(picker.action_for(ctrl_w), None);
+        assert_eq!(
+            picker.action_for(KeyEvent::from(KeyCode::Down)),
+            Some(PickerAction::MoveDown)
+        );
+        picker.searching = true;
+        assert_eq!(picker.action_for(ctrl_w), Some(PickerAction::DeleteWord));
+    }
+
+    #[test]
+    fn wakes_up_once_for_redraws_requested_together() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let picker = Picker::new(&list, Preview::None, None, &tmux);
+        while picker.wakeups.try_recv().is_ok() {}
+        picker.redraw_requested.store(false, Ordering::Release);
+
+        let redraw = picker.request_redraw();
+        redraw();
+        redraw();
+
+        assert!(matches!(picker.wakeups.try_recv(), Ok(Wakeup::Redraw)));
+        assert!(picker.wakeups.try_recv().is_err());
+    }
+
+    #[test]
+    fn shows_details_sent_while_running() {
+        let list = ["dotfiles", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let (sender, updates) = std::sync::mpsc::channel();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux).set_detail_updates(updates);
+
+        sender.send(("website".into(), "main *".into())).unwrap();
+        picker.receive_details();
+        assert_eq!(picker.item_details["website"], "main *");
+        assert!(picker.detail_updates.is_some());
+
+        drop(sender);
+        picker.receive_details();
+        assert!(picker.detail_updates.is_none());
+    }
+
+    #[test]
+    fn keeps_the_selection_when_better_matches_are_added() {
+        let list = ["my-dotfiles-backup", "website"].map(String::from);
+        let tmux = Tmux::default();
+        let (sender, updates) = std::sync::mpsc::channel();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux)
+            .set_filter("dotfiles")
+            .set_item_updates(updates);
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+        assert_eq!(picker.get_selected().unwrap(), "my-dotfiles-backup");
+
+        sender.send(vec![String::from("dotfiles")]).unwrap();
+        drop(sender);
+        picker.receive_items();
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+        picker.restore_selection(false);
+
+        assert_eq!(picker.selection.selected(), Some(1));
+        assert_eq!(picker.get_selected().unwrap(), "my-dotfiles-backup");
+    }
+
+    #[test]
+    fn matches_the_matched_columns_too() {
+        let list = ["api:1", "api:2", "blog:1"].map(String::from);
+        let columns = HashMap::from([
+            ("api:1", ["nvim", "editor"]),
+            ("api:2", ["cargo", "tests"]),
+            ("blog:1", ["zsh", "nvim"]),
+        ]);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux)
+            .set_item_columns(move |item| columns[item].map(String::from).to_vec())
+            .set_column_options(vec![ItemColumn {
+                style: None,
+                matched: true,
+            }]);
+        let matched = |picker: &mut Picker, filter: &str| {
+            let previous = std::mem::replace(&mut picker.filter, filter.to_owned());
+            picker.update_matcher_pattern(&previous);
+            while picker.matcher.tick(10).running {}
+            let snapshot = picker.matcher.snapshot();
+            let mut items: Vec<String> = snapshot
+                .matched_items(..)
+                .map(|item| item.data.clone())
+                .collect();
+            items.sort();
+            items
+        };
+
+        assert_eq!(matched(&mut picker, "'nvim"), ["api:1"]);
+        assert_eq!(matched(&mut picker, "'cargo"), ["api:2"]);
+        assert!(matched(&mut picker, "'editor").is_empty());
+    }
+
+    #[test]
+    fn matches_the_match_text_without_showing_it() {
+        let list = ["api", "blog"].map(String::from);
+        let tmux = Tmux::default();
+        let mut picker = Picker::new(&list, Preview::None, None, &tmux)
+            .set_match_text(|item| (item == "api").then(|| "/home/me/work/api".to_owned()))
+            .set_filter("'work");
+        while picker.matcher.tick(10).running {}
+        picker.update_selection();
+
+        assert_eq!(picker.matcher.snapshot().matched_item_count(), 1);
+        assert_eq!(picker.get_selected().unwrap(), "api");
+
+        let mut terminal = Terminal::new(TestBackend::new(40, 5)).unwrap();
+        terminal.draw(|f| picker.render(f)).unwrap();
+        let shown: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(shown.contains("> api"), "{shown}");
+        assert!(!shown.contains("/home/me"), "{shown}");
+    }
+}
@@ -0,0 +1,361 @@
+//! What the picker shows next to the list for the highlighted item
+
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use git2::Repository;
+
+use crate::{columns::format_age, i18n::tr_fmt, state::now, tmux::Tmux};
+
+/// How much of a file is read to preview it
+const MAX_FILE_BYTES: u64 = 64 * 1024;
+/// How many lines of a file are previewed, more don't fit in the preview anyway
+const MAX_FILE_LINES: usize = 200;
+/// How many of the files modified last are listed
+const RECENT_FILES: usize = 30;
+/// How many entries of a project are looked at for the files modified last, so a huge one doesn't
+/// hold up the picker
+const MAX_RECENT_FILES_SCAN: usize = 20_000;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[90m";
+const BLUE: &str = "\x1b[34m";
+const CYAN: &str = "\x1b[36m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Builds the preview of the highlighted item. The text can be colored with ANSI escape codes, an
+/// error is a message to show in the picker's status line
+pub trait PreviewProvider {
+    fn preview(&self, item: &str, tmux: &Tmux) -> Result<String, String>;
+}
+
+impl<F: Fn(&str) -> String> PreviewProvider for F {
+    fn preview(&self, item: &str, _tmux: &Tmux) -> Result<String, String> {
+        Ok(self(item))
+    }
+}
+
+/// How much of a pane is captured for its preview
+#[derive(Debug, Clone, Copy)]
+pub struct PaneCapture {
+    /// The lines of scrollback above the visible part to include
+    pub scrollback: usize,
+    /// How many lines fit in the preview, the last ones are kept
+    pub max_lines: usize,
+}
+
+/// The visible part of the active pane of a session, and its scrollback
+pub struct SessionPanePreview(pub PaneCapture);
+
+impl PreviewProvider for SessionPanePreview {
+    fn preview(&self, item: &str, tmux: &Tmux) -> Result<String, String> {
+        Ok(pane_contents(item, self.0, tmux))
+    }
+}
+
+/// The visible part of the active pane of a window, listed as `target name`, and its scrollback
+pub struct WindowPanePreview(pub PaneCapture);
+
+impl PreviewProvider for WindowPanePreview {
+    fn preview(&self, item: &str, tmux: &Tmux) -> Result<String, String> {
+        let target = window_target(item).unwrap_or_default();
+        Ok(pane_contents(target, self.0, tmux))
+    }
+}
+
+/// The target of a window listed as `target name`, either its id like `@3` or `session:index`.
+/// Session names can have spaces but no `:`, which tmux replaces, so that target ends at the first
+/// space after the `:`
+pub fn window_target(item: &str) -> Option<&str> {
+    let start = if item.starts_with('@') {
+        0
+    } else {
+        item.find(':')?
+    };
+    let end = item[start..]
+        .find(' ')
+        .map_or(item.len(), |end| start + end);
+    Some(&item[..end])
+}
+
+fn pane_contents(target: &str, capture: PaneCapture, tmux: &Tmux) -> String {
+    let output = tmux.capture_pane(target, capture.scrollback);
+    if output.status.success() {
+        last_lines(&String::from_utf8_lossy(&output.stdout), capture.max_lines)
+    } else {
+        String::new()
+    }
+}
+
+/// The last lines of a captured pane that fit, leaving out the empty ones below the cursor so the
+/// latest output is shown
+fn last_lines(contents: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = contents.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(max_lines)..].join("\n")
+}
+
+/// The entries of a directory, followed by its README or else its first file, highlighted
+pub struct DirectoryPreview;
+
+impl PreviewProvider for DirectoryPreview {
+    fn preview(&self, item: &str, _tmux: &Tmux) -> Result<String, String> {
+        let path = shellexpand::tilde(item);
+        let path = Path::new(path.as_ref());
+        let (dirs, files) = list_directory(path)
+            .map_err(|error| tr_fmt("Could not preview {}: {}", &[&item, &error]))?;
+
+        let mut preview: Vec<String> = dirs
+            .iter()
+            .map(|dir| format!("{BLUE}{dir}/{RESET}"))
+            .chain(files.iter().cloned())
+            .collect();
+        let shown_file = files
+            .iter()
+            .find(|file| file.to_lowercase().starts_with("readme"))
+            .or(files.first())
+            .map(|file| path.join(file));
+        if let Some((file, contents)) = shown_file.and_then(|file| read_text(&file)) {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            preview.push(String::new());
+            preview.push(format!("{DIM}── {name} ──{RESET}"));
+            preview.push(highlight(&file, &contents));
+        }
+        Ok(preview.join("\n"))
+    }
+}
+
+/// The files of the project in the directory modified last, newest first with how long ago. In a
+/// repository the files git ignores are left out, elsewhere the hidden ones
+pub fn recent_files(path: &Path) -> String {
+    let repo = Repository::open(path).ok().filter(|repo| !repo.is_bare());
+    let is_ignored = |relative: &Path, name: &str| match &repo {
+        Some(repo) => name == ".git" || repo.is_path_ignored(relative).unwrap_or_default(),
+        None => name.starts_with('.'),
+    };
+
+    let mut files = Vec::new();
+    let mut to_read = vec![path.to_path_buf()];
+    let mut scanned = 0;
+    'scan: while let Some(dir) = to_read.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            scanned += 1;
+            if scanned > MAX_RECENT_FILES_SCAN {
+                break 'scan;
+            }
+            let entry_path = entry.path();
+            let relative = entry_path.strip_prefix(path).unwrap_or(&entry_path);
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if is_ignored(relative, &entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            if file_type.is_dir() {
+                to_read.push(entry_path);
+            } else if file_type.is_file() {
+                let modified = entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((modified, relative.to_path_buf()));
+            }
+        }
+    }
+
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    let now = now();
+    files
+        .iter()
+        .take(RECENT_FILES)
+        .map(|(modified, file)| {
+            let modified = modified
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            let age = format_age(now.saturating_sub(modified));
+            format!("{DIM}{age:>4}{RESET}  {}", file.display())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The names of the directories and of the files in the directory, sorted and without hidden ones
+fn list_directory(path: &Path) -> std::io::Result<(Vec<String>, Vec<String>)> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        if entry.path().is_dir() {
+            dirs.push(name);
+        } else {
+            files.push(name);
+        }
+    }
+    dirs.sort();
+    files.sort();
+    Ok((dirs, files))
+}
+
+/// The beginning of a file, unless it looks like a binary file
+fn read_text(path: &Path) -> Option<(PathBuf, String)> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(MAX_FILE_BYTES)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    if bytes.contains(&0) {
+        return None;
+    }
+    let contents = String::from_utf8_lossy(&bytes);
+    let contents = contents
+        .lines()
+        .take(MAX_FILE_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some((path.to_path_buf(), contents))
+}
+
+/// Color the contents of a file: the structure of Markdown, and the comments of other files
+fn highlight(path: &Path, contents: &str) -> String {
+    // Escape codes in the file itself would mess with the colors of the preview
+    let contents = contents.replace('\x1b', "");
+    let markdown = path
+        .extension()
+        .is_some_and(|extension| extension == "md" || extension == "markdown");
+    let mut in_code_block = false;
+
+    contents
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if markdown {
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                    in_code_block = !in_code_block;
+                    return format!("{DIM}{line}{RESET}");
+                }
+                if in_code_block {
+                    return format!("{YELLOW}{line}{RESET}");
+                }
+                if trimmed.starts_with('#') {
+                    return format!("{BOLD}{CYAN}{line}{RESET}");
+                }
+                if trimmed.starts_with('>') {
+                    return format!("{DIM}{line}{RESET}");
+                }
+                highlight_inline_code(line)
+            } else if ["#", "//", "--", ";"]
+                .iter()
+                .any(|comment| trimmed.starts_with(comment))
+            {
+                format!("{DIM}{line}{RESET}")
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Color the parts of a Markdown line between backticks. A backtick without a closing one is left
+/// as it is, along with the rest of the line
+fn highlight_inline_code(line: &str) -> String {
+    let parts: Vec<&str> = line.split('`').collect();
+    let mut highlighted = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i % 2 == 0 {
+            highlighted.push_str(part);
+        } else if i + 1 < parts.len() {
+            highlighted.push_str(&format!("{YELLOW}`{part}`{RESET}"));
+        } else {
+            highlighted.push('`');
+            highlighted.push_str(part);
+        }
+    }
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_last_lines_of_a_pane_that_fit() {
+        let pane = "$ make\nbuilding\nwarning\ndone\n$ \n\n\n";
+        assert_eq!(last_lines(pane, 3), "warning\ndone\n$");
+        assert_eq!(last_lines(pane, 100), "$ make\nbuilding\nwarning\ndone\n$");
+        assert_eq!(last_lines(pane, 0), "");
+    }
+
+    #[test]
+    fn finds_the_target_of_a_listed_window() {
+        assert_eq!(window_target("@3 vim"), Some("@3"));
+        assert_eq!(
+            window_target("my project:2 vim: notes"),
+            Some("my project:2")
+        );
+        assert_eq!(window_target("dev:1"), Some("dev:1"));
+        assert_eq!(window_target("no target"), None);
+    }
+
+    #[test]
+    fn highlights_markdown() {
+        let readme = "# tms\nRun `tms` to pick\n```sh\ncargo install\n```";
+
+        let highlighted = highlight(Path::new("README.md"), readme);
+        let lines: Vec<&str> = highlighted.lines().collect();
+        assert_eq!(lines[0], format!("{BOLD}{CYAN}# tms{RESET}"));
+        assert_eq!(lines[1], format!("Run {YELLOW}`tms`{RESET} to pick"));
+        assert_eq!(lines[3], format!("{YELLOW}cargo install{RESET}"));
+        assert_eq!(
+            highlight_inline_code("a `b` c ` d"),
+            format!("a {YELLOW}`b`{RESET} c ` d")
+        );
+        assert_eq!(
+            highlight(Path::new("Makefile"), "# build\nall:"),
+            format!("{DIM}# build{RESET}\nall:")
+        );
+    }
+
+    #[test]
+    fn lists_recent_files_without_ignored_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        Repository::init(dir.path()).unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        let hours_ago =
+            |hours: u64| SystemTime::now() - std::time::Duration::from_secs(hours * 60 * 60);
+        for (file, modified) in [
+            (".gitignore", hours_ago(48)),
+            ("src/main.rs", hours_ago(3)),
+            ("README.md", hours_ago(1)),
+            ("target/build.log", hours_ago(0)),
+        ] {
+            fs::write(dir.path().join(file), "target/\n").unwrap();
+            fs::File::options()
+                .write(true)
+                .open(dir.path().join(file))
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+        }
+
+        assert_eq!(
+            recent_files(dir.path()),
+            format!(
+                "{DIM}  1h{RESET}  README.md\n{DIM}  3h{RESET}  src/main.rs\n{DIM}  2d{RESET}  .gitignore"
+            )
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Projects on other machines, opened as local sessions that ssh into them
+
+use std::{collections::HashMap, path::PathBuf, process};
+
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configs::Config,
+    i18n::{tr, tr_fmt},
+    session::tmux_session_name,
+    tmux::Tmux,
+    Result,
+};
+
+/// How deep to look for repositories in the search directories of a remote by default
+const DEFAULT_DEPTH: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+pub struct RemoteConfig {
+    /// The host as given to ssh, which can be an alias from the ssh config
+    pub host: String,
+    /// Directories on the host to search for repositories, `~` is the remote home
+    pub search_dirs: Vec<String>,
+    pub depth: Option<usize>,
+    /// List the highlighted project's directory over ssh in the picker's preview
+    pub preview: Option<bool>,
+}
+
+/// A project on a remote host, listed in the picker as `host:path`. It isn't a [`Session`], which
+/// are the projects with a local directory and repository, as it only has a path on the host
+///
+/// [`Session`]: crate::session::Session
+#[derive(Debug, PartialEq, Eq)]
+pub struct RemoteProject<'a> {
+    pub remote: &'a RemoteConfig,
+    pub path: &'a str,
+}
+
+impl<'a> RemoteProject<'a> {
+    /// The remote project for a picker item, if it starts with one of the configured hosts
+    pub fn parse(item: &'a str, config: &'a Config) -> Option<Self> {
+        let (host, path) = item.split_once(':')?;
+        let remote = config
+            .remotes
+            .as_ref()?
+            .iter()
+            .find(|remote| remote.host == host)?;
+        Some(RemoteProject { remote, path })
+    }
+
+    /// The local session for the project, named after the directory and the host
+    pub fn session_name(&self) -> String {
+        let dir = self.path.trim_end_matches('/').rsplit('/').next();
+        tmux_session_name(&format!(
+            "{}@{}",
+            dir.unwrap_or(self.path),
+            self.remote.host
+        ))
+    }
+
+    /// Create a local session for the project whose first window connects to the host, unless
+    /// it is already running, returning the name of the session
+    pub fn create(&self, tmux: &Tmux) -> Result<String> {
+        let session_name = self.session_name();
+        if !tmux.session_exists(&session_name) {
+            tmux.new_session(Some(&session_name), None);
+            tmux.send_keys(&self.ssh_command(), Some(&session_name));
+        }
+        Ok(session_name)
+    }
+
+    pub fn switch_to(&self, tmux: &Tmux) -> Result<()> {
+        let session_name = self.create(tmux)?;
+        tmux.switch_to_session(&session_name)
+    }
+
+    /// What opening the project would do, with the files in it when `preview` is enabled
+    pub fn plan(&self, running: &HashMap<String, PathBuf>) -> String {
+        let session_name = self.session_name();
+        let mut plan = vec![
+            tr_fmt("Session: {}", &[&session_name]),
+            tr_fmt("Command: {}", &[&self.ssh_command()]),
+        ];
+        if running.contains_key(&session_name) {
+            plan.push(tr("Already running, it would be switched to").to_owned());
+        }
+        if self.remote.preview == Some(true) {
+            let listing = ssh(
+                &self.remote.host,
+                &format!("ls -A {}", remote_path(self.path)),
+            );
+            plan.push(String::new());
+            plan.push(listing.unwrap_or_else(|| tr("Could not list the directory").to_owned()));
+        }
+        plan.join("\n")
+    }
+
+    fn ssh_command(&self) -> String {
+        let remote_command = format!("cd {} && exec $SHELL -l", remote_path(self.path));
+        format!(
+            "ssh -t {} {}",
+            shell_words::quote(&self.remote.host),
+            shell_words::quote(&remote_command)
+        )
+    }
+}
+
+/// The repositories found on the configured remotes as `host:path`, leaving out the hosts that
+/// can't be reached without asking for a password
+pub fn remote_projects(config: &Config) -> Vec<String> {
+    let mut projects = Vec::new();
+    for remote in config.remotes.iter().flatten() {
+        let dirs: Vec<String> = remote
+            .search_dirs
+            .iter()
+            .map(|dir| remote_path(dir))
+            .collect();
+        let depth = remote.depth.unwrap_or(DEFAULT_DEPTH) + 1;
+        let command = format!(
+            "find {} -maxdepth {depth} -name .git -prune -print 2>/dev/null; true",
+            dirs.join(" ")
+        );
+        let Some(found) = ssh(&remote.host, &command) else {
+            continue;
+        };
+        projects.extend(found.lines().filter_map(|git_dir| {
+            let path = git_dir.strip_suffix("/.git")?;
+            Some(format!("{}:{path}", remote.host))
+        }));
+    }
+    projects
+}
+
+/// A path for the remote shell, quoted apart from a leading `~` so it is still expanded there
+fn remote_path(path: &str) -> String {
+    match path.strip_prefix('~') {
+        Some(rest) => format!("~{}", shell_words::quote(rest)),
+        None => shell_words::quote(path).into_owned(),
+    }
+}
+
+/// The output of a command run on the host, without prompting for anything. Built without
+/// external tools nothing is run and the host lists no projects
+fn ssh(host: &str, command: &str) -> Option<String> {
+    if cfg!(feature = "no-external-tools") {
+        return None;
+    }
+    let output = process::Command::new("ssh")
+        .args([
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=5",
+            host,
+            command,
+        ])
+        .stdin(process::Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_items_of_configured_hosts() {
+        let config = Config {
+            remotes: Some(vec![RemoteConfig {
+                host: "devbox".into(),
+                search_dirs: vec!["~/projects".into()],
+                depth: None,
+                preview: None,
+            }]),
+            ..Default::default()
+        };
+
+        let project = RemoteProject::parse("devbox:/home/me/projects/api.v2", &config).unwrap();
+        assert_eq!(project.path, "/home/me/projects/api.v2");
+        assert_eq!(project.session_name(), "api_v2@devbox");
+        assert_eq!(RemoteProject::parse("laptop:/home/me/notes", &config), None);
+        assert_eq!(RemoteProject::parse("my-repo", &config), None);
+    }
+
+    #[test]
+    fn keeps_the_remote_home_unquoted() {
+        assert_eq!(remote_path("~/my projects"), "~'/my projects'");
+        assert_eq!(remote_path("/srv/app"), "/srv/app");
+    }
+}
@@ -1,64 +1,132 @@
-use aho_corasick::{AhoCorasickBuilder, MatchKind};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use error_stack::ResultExt;
-use git2::Submodule;
+use git2::{Branch, BranchType, Repository, Submodule, Worktree, WorktreeAddOptions};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use std::{
     collections::{HashMap, VecDeque},
     fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Instant,
 };
 
 use crate::{
-    configs::{Config, SearchDirectory},
+    cache::{dir_mtime, ScanCache},
+    configs::{Config, SearchDirectory, VcsProvider},
     dirty_paths::DirtyUtf8Path,
-    session::{Session, SessionContainer, SessionType},
+    error::Suggestion,
+    glob::{glob_match, is_glob},
+    history::{self, Action, Entry},
+    i18n::tr_fmt,
+    interrupt,
+    session::{dir_name, Session, SessionContainer, SessionType},
     Result, TmsError,
 };
 
+/// A search directory along with what it excludes
+struct SearchRoot {
+    dir: SearchDirectory,
+    excluder: Excluder,
+}
+
+/// A directory to search, `depth` levels deep, in the search directory at index `root`
+struct ToSearch {
+    path: PathBuf,
+    /// The path without symlinks, the same directory reached through different symlinks has the
+    /// same one
+    real_path: PathBuf,
+    depth: usize,
+    root: usize,
+    /// The `.gitignore` files of the directories above it, from the search directory down
+    gitignores: Rc<Vec<Gitignore>>,
+}
+
 pub fn find_repos(config: &Config) -> Result<HashMap<String, Vec<Session>>> {
-    let directories = config.search_dirs().change_context(TmsError::ConfigError)?;
+    let roots = config
+        .search_dirs()
+        .change_context(TmsError::ConfigError)?
+        .into_iter()
+        .map(|dir| {
+            Ok(SearchRoot {
+                excluder: Excluder::for_search_dir(config, &dir)?,
+                dir,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
     let mut repos: HashMap<String, Vec<Session>> = HashMap::new();
-    let mut to_search: VecDeque<SearchDirectory> = directories.into();
-
-    let excluder = if let Some(excluded_dirs) = &config.excluded_dirs {
-        Some(
-            AhoCorasickBuilder::new()
-                .match_kind(MatchKind::LeftmostFirst)
-                .build(excluded_dirs)
-                .change_context(TmsError::IoError)?,
-        )
-    } else {
-        None
-    };
+    let mut to_search: VecDeque<ToSearch> = roots
+        .iter()
+        .enumerate()
+        .map(|(index, root)| ToSearch {
+            path: root.dir.path.clone(),
+            real_path: root.dir.path.clone(),
+            depth: root.dir.depth,
+            root: index,
+            gitignores: Rc::default(),
+        })
+        .collect();
+    let mut cache = ScanCache::load();
+    let excluder = Excluder::new(config)?;
+    let started = Instant::now();
+    // Symlinks can lead back to a directory that was searched already, which would list its
+    // projects twice or, for a link to one of its parents, search in circles until the depth runs
+    // out. The depth left when each directory was searched is kept, so one reached again with more
+    // depth left, like through a search directory inside another one, is searched deeper
+    let mut visited: HashMap<PathBuf, usize> = HashMap::new();
+    // Big trees take a while to scan, which ctrl-c stops without waiting for it
+    let _catch = interrupt::Catch::new();
 
     while let Some(file) = to_search.pop_front() {
-        if let Some(ref excluder) = excluder {
-            if excluder.is_match(&file.path.to_string()?) {
-                continue;
-            }
+        interrupt::check()?;
+        let root = &roots[file.root];
+        if root.excluder.is_excluded(&file.path) {
+            continue;
         }
+        let searched_depth = visited.get(&file.real_path).copied();
+        if searched_depth.is_some_and(|depth| depth >= file.depth) {
+            continue;
+        }
+        visited.insert(file.real_path.clone(), file.depth);
+        // Projects were listed the first time the directory was searched
+        let listed = searched_depth.is_some();
 
-        if let Ok(repo) = git2::Repository::open(&file.path) {
-            if repo.is_worktree() {
+        let mtime = dir_mtime(&file.path);
+        let known_non_repo = mtime.is_some_and(|mtime| cache.is_non_repo(&file.path, mtime));
+        let repo = if known_non_repo || !root.dir.has_provider(VcsProvider::Git) {
+            None
+        } else {
+            git2::Repository::open(&file.path).ok()
+        };
+
+        if let Some(repo) = repo {
+            if repo.is_worktree() || listed {
                 continue;
             }
-
-            let session_name = file
-                .path
-                .file_name()
-                .expect("The file name doesn't end in `..`")
-                .to_string()?;
-
-            let session = Session::new(session_name, SessionType::Git(repo));
-            if let Some(list) = repos.get_mut(&session.name) {
-                list.push(session);
-            } else {
-                repos.insert(session.name.clone(), vec![session]);
+            cache.insert_repo(&file.path);
+            add_repo(&mut repos, repo, &file.path, &root.excluder)?;
+        } else if root.dir.has_provider(VcsProvider::Jujutsu) && file.path.join(".jj").is_dir() {
+            if listed {
+                continue;
+            }
+            cache.insert_repo(&file.path);
+            let session = Session::bookmark(file.path)?;
+            repos.entry(session.name.clone()).or_default().push(session);
+        } else {
+            // Without git, the directory wasn't checked for a repository so nothing is known
+            if let Some(mtime) = mtime.filter(|_| root.dir.has_provider(VcsProvider::Git)) {
+                cache.insert_non_repo(&file.path, mtime);
+            }
+            if !file.path.is_dir() || file.depth == 0 {
+                continue;
             }
-        } else if file.path.is_dir() && file.depth > 0 {
             match fs::read_dir(&file.path) {
                 Err(ref e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
                     eprintln!(
-                        "Warning: insufficient permissions to read '{0}'. Skipping directory...",
-                        file.path.to_string()?
+                        "{}",
+                        tr_fmt(
+                            "Warning: insufficient permissions to read '{}'. Skipping directory...",
+                            &[&file.path.to_string()?],
+                        )
                     );
                 }
                 result => {
@@ -68,16 +136,489 @@ pub fn find_repos(config: &Config) -> Result<HashMap<String, Vec<Session>>> {
                             format!("Could not read directory {:?}", file.path)
                         })?
                         .map(|dir_entry| dir_entry.expect("Found non-valid utf8 path").path());
+                    let gitignores = if config.respect_gitignore == Some(true) {
+                        with_gitignore(&file.gitignores, &file.path)
+                    } else {
+                        Rc::clone(&file.gitignores)
+                    };
                     for dir in read_dir {
-                        to_search.push_back(SearchDirectory::new(dir, file.depth - 1))
+                        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+                        if config.skip_hidden_dirs == Some(true) && name.starts_with('.') {
+                            continue;
+                        }
+                        if is_gitignored(&gitignores, &dir) {
+                            continue;
+                        }
+                        let real_path = if dir.is_symlink() {
+                            if !root.dir.follows_symlinks() {
+                                continue;
+                            }
+                            // Broken links are skipped
+                            let Ok(real_path) = dir.canonicalize() else {
+                                continue;
+                            };
+                            real_path
+                        } else {
+                            file.real_path.join(dir.file_name().unwrap_or_default())
+                        };
+                        to_search.push_back(ToSearch {
+                            path: dir,
+                            real_path,
+                            depth: file.depth - 1,
+                            root: file.root,
+                            gitignores: Rc::clone(&gitignores),
+                        })
                     }
                 }
             }
         }
     }
+    for root in roots
+        .iter()
+        .filter(|root| root.dir.include_non_git_dirs == Some(true))
+    {
+        add_plain_dirs(&mut repos, root, config)?;
+    }
+    add_workspace_projects(&mut repos, &excluder, config)?;
+    // The cache is only an optimisation, failing to write it shouldn't prevent picking a session
+    let _ = cache.save();
+    let found = repos.values().map(Vec::len).sum::<usize>();
+    let details = format!(
+        "found {found} repositories in {}ms",
+        started.elapsed().as_millis()
+    );
+    // The log is opt-in bookkeeping, failing to write it shouldn't fail the scan
+    let _ = history::record(config, Entry::new(Action::Scan).details(details));
     Ok(repos)
 }
 
+/// The repositories found by the last scan that are still in the search paths, `None` when there
+/// was no scan yet. Nothing is scanned, so new repositories are missing until the next scan
+pub fn cached_repos(config: &Config) -> Result<Option<HashMap<String, Vec<Session>>>> {
+    let cache = ScanCache::load();
+    if cache.repos().is_empty() {
+        return Ok(None);
+    }
+    let directories = config.search_dirs().change_context(TmsError::ConfigError)?;
+    let excluder = Excluder::new(config)?;
+    let root_excluders = directories
+        .iter()
+        .map(|dir| Excluder::for_search_dir(config, dir))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut repos = HashMap::new();
+    for path in cache.repos() {
+        let Some((dir, root_excluder)) = directories
+            .iter()
+            .zip(&root_excluders)
+            .find(|(dir, _)| path.starts_with(&dir.path))
+        else {
+            continue;
+        };
+        if root_excluder.is_excluded(path) {
+            continue;
+        }
+        // Projects removed since the last scan are left out, and the kind of project is checked
+        // in the same order as the scan does
+        let repo = if dir.has_provider(VcsProvider::Git) {
+            Repository::open(path).ok()
+        } else {
+            None
+        };
+        if let Some(repo) = repo {
+            add_repo(&mut repos, repo, path, root_excluder)?;
+        } else if dir.has_provider(VcsProvider::Jujutsu) && path.join(".jj").is_dir() {
+            let session = Session::bookmark(path.clone())?;
+            repos.entry(session.name.clone()).or_default().push(session);
+        }
+    }
+    add_workspace_projects(&mut repos, &excluder, config)?;
+    Ok(Some(repos))
+}
+
+/// The `excluded_dirs`, which exclude every path containing them or, when they are glob patterns,
+/// the paths matching them. Patterns without a `/` are matched against the directory's name
+struct Excluder {
+    substrings: Option<AhoCorasick>,
+    globs: Vec<String>,
+}
+
+impl Excluder {
+    fn new(config: &Config) -> Result<Self> {
+        Self::from_patterns(config.excluded_dirs.iter().flatten())
+    }
+
+    /// The `excluded_dirs` along with the ones excluded in a single search directory
+    fn for_search_dir(config: &Config, dir: &SearchDirectory) -> Result<Self> {
+        Self::from_patterns(
+            config
+                .excluded_dirs
+                .iter()
+                .flatten()
+                .chain(dir.excluded.iter().flatten()),
+        )
+    }
+
+    fn from_patterns<'a>(patterns: impl Iterator<Item = &'a String>) -> Result<Self> {
+        let (globs, substrings): (Vec<&String>, Vec<&String>) =
+            patterns.partition(|pattern| is_glob(pattern));
+        let substrings = if substrings.is_empty() {
+            None
+        } else {
+            Some(
+                AhoCorasickBuilder::new()
+                    .match_kind(MatchKind::LeftmostFirst)
+                    .build(substrings)
+                    .change_context(TmsError::IoError)?,
+            )
+        };
+        Ok(Excluder {
+            substrings,
+            globs: globs.into_iter().cloned().collect(),
+        })
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let full_path = path.to_string_lossy();
+        if self
+            .substrings
+            .as_ref()
+            .is_some_and(|substrings| substrings.is_match(&*full_path))
+        {
+            return true;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.globs.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern, &full_path)
+            } else {
+                glob_match(pattern, &name)
+            }
+        })
+    }
+}
+
+/// The `.gitignore` files that apply to the entries of `dir`, the ones of the directories above it
+/// followed by its own when it has one
+fn with_gitignore(gitignores: &Rc<Vec<Gitignore>>, dir: &Path) -> Rc<Vec<Gitignore>> {
+    let path = dir.join(".gitignore");
+    if !path.is_file() {
+        return Rc::clone(gitignores);
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    // The valid patterns of a file with invalid ones still apply, like they do for git
+    let _ = builder.add(path);
+    let Ok(gitignore) = builder.build() else {
+        return Rc::clone(gitignores);
+    };
+    let mut gitignores = gitignores.to_vec();
+    gitignores.push(gitignore);
+    Rc::new(gitignores)
+}
+
+/// Whether the directory is ignored by the deepest `.gitignore` with a pattern for it, so that a
+/// `!` pattern further down brings back what one further up ignores, like in git
+fn is_gitignored(gitignores: &[Gitignore], dir: &Path) -> bool {
+    gitignores
+        .iter()
+        .rev()
+        .map(|gitignore| gitignore.matched(dir, true))
+        .find(|matched| !matched.is_none())
+        .is_some_and(|matched| matched.is_ignore())
+}
+
+/// Add the sessions for a repository found at `path` and its worktrees
+fn add_repo(
+    repos: &mut HashMap<String, Vec<Session>>,
+    repo: Repository,
+    path: &Path,
+    excluder: &Excluder,
+) -> Result<()> {
+    let session_name = dir_name(path)?;
+
+    // Worktrees are skipped when scanning and found through their main repository instead, as
+    // they often live next to it rather than inside a search path. Bare repositories open their
+    // worktrees as windows so they don't need sessions of their own
+    let mut sessions = if repo.is_bare() {
+        Vec::new()
+    } else {
+        linked_worktrees(&repo, &session_name)
+    };
+    sessions.retain(|session| !excluder.is_excluded(session.path()));
+    sessions.push(Session::new(session_name, SessionType::Git(repo)));
+
+    for session in sessions {
+        repos.entry(session.name.clone()).or_default().push(session);
+    }
+    Ok(())
+}
+
+/// Add the directories directly inside a search path that aren't repositories and don't contain any
+/// that were found, like bookmarks
+fn add_plain_dirs(
+    repos: &mut HashMap<String, Vec<Session>>,
+    root: &SearchRoot,
+    config: &Config,
+) -> Result<()> {
+    let Ok(entries) = fs::read_dir(&root.dir.path) else {
+        return Ok(());
+    };
+    let bookmarks = config.bookmark_paths();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if !path.is_dir()
+            || root.excluder.is_excluded(&path)
+            || (!root.dir.follows_symlinks() && path.is_symlink())
+            || (config.skip_hidden_dirs == Some(true) && name.starts_with('.'))
+            || bookmarks.contains(&path)
+        {
+            continue;
+        }
+        let has_repo = repos
+            .values()
+            .flatten()
+            .any(|session| session.path().starts_with(&path));
+        if !has_repo {
+            let session = Session::bookmark(path)?;
+            repos.entry(session.name.clone()).or_default().push(session);
+        }
+    }
+    Ok(())
+}
+
+/// Add the projects in the `workspace_dirs`, which are listed without searching them so they are
+/// never part of the scan cache
+fn add_workspace_projects(
+    repos: &mut HashMap<String, Vec<Session>>,
+    excluder: &Excluder,
+    config: &Config,
+) -> Result<()> {
+    for path in config.workspace_projects() {
+        let known = repos
+            .values()
+            .flatten()
+            .any(|session| session.path() == path);
+        if known || excluder.is_excluded(&path) {
+            continue;
+        }
+        let session = Session::from_path(&path)?;
+        repos.entry(session.name.clone()).or_default().push(session);
+    }
+    Ok(())
+}
+
+/// Sessions for the worktrees linked to a repository, labelled as `repo[worktree]`
+pub fn linked_worktrees(repo: &Repository, repo_name: &str) -> Vec<Session> {
+    let Ok(names) = repo.worktrees() else {
+        return Vec::new();
+    };
+
+    names
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let worktree = repo.find_worktree(name).ok()?;
+            // Worktrees whose directory was removed without pruning can't be opened
+            worktree.validate().ok()?;
+            let worktree_repo = Repository::open_from_worktree(&worktree).ok()?;
+            Some(Session::new(
+                format!("{repo_name}[{name}]"),
+                SessionType::Git(worktree_repo),
+            ))
+        })
+        .collect()
+}
+
+/// The repository a worktree belongs to, or the repository itself when it isn't a worktree
+pub fn main_repository(repo: Repository) -> Result<Repository> {
+    if !repo.is_worktree() {
+        return Ok(repo);
+    }
+    // The git directory of a worktree links back to the main one with a relative `commondir` path
+    let commondir = fs::read_to_string(repo.path().join("commondir"))
+        .change_context(TmsError::GitError)
+        .attach_printable("Could not find the repository of the worktree")?;
+    Repository::open(repo.path().join(commondir.trim())).change_context(TmsError::GitError)
+}
+
+/// Where worktrees of the project at `project_path` are added, the configured `worktrees_dir` or
+/// else a `project-worktrees` folder next to the project
+pub fn worktrees_dir(project_path: &Path, config: &Config) -> Result<PathBuf> {
+    let project_name = dir_name(project_path)?;
+    let dir = match &config.worktrees_dir {
+        Some(dir) => PathBuf::from(
+            shellexpand::full(dir)
+                .change_context(TmsError::IoError)?
+                .as_ref(),
+        )
+        .join(&project_name),
+        None => project_path.with_file_name(format!("{project_name}-worktrees")),
+    };
+    Ok(dir)
+}
+
+/// The working directory of a worktree with `reference` checked out, adding one in `dir` if there
+/// is none yet. Remote branches are checked out through a local branch tracking them and anything
+/// that isn't a branch, like a tag, with a detached HEAD
+pub fn worktree_for_ref(repo: &Repository, reference: &str, dir: &Path) -> Result<PathBuf> {
+    let name = reference.replace('/', "-");
+    let path = dir.join(&name);
+
+    let branch = local_branch(repo, reference)?;
+    if let Some(branch_name) = branch.as_ref().and_then(|branch| branch.get().name()) {
+        if let Some(path) = checked_out_at(repo, branch_name) {
+            return Ok(path);
+        }
+    }
+    // Another reference can lead to the same name, like `feature/x` and `feature-x`
+    if let Ok(worktree) = repo.find_worktree(&name) {
+        if worktree.validate().is_ok() {
+            if !has_checked_out(&worktree, repo, reference, branch.as_ref()) {
+                return Err(TmsError::GitError)
+                    .attach_printable(format!(
+                        "The worktree at {} has something other than {reference} checked out",
+                        worktree.path().display()
+                    ))
+                    .attach(Suggestion(
+                        "Remove that worktree with `git worktree remove` to add this one",
+                    ));
+            }
+            return Ok(worktree.path().to_path_buf());
+        }
+    }
+
+    fs::create_dir_all(dir)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not create {}", dir.display()))?;
+    let mut options = WorktreeAddOptions::new();
+
+    if let Some(branch) = branch {
+        options.reference(Some(branch.get()));
+        repo.worktree(&name, &path, Some(&options))
+            .change_context(TmsError::GitError)?;
+    } else {
+        let commit = repo
+            .revparse_single(reference)
+            .and_then(|object| object.peel_to_commit())
+            .change_context(TmsError::GitError)
+            .attach_printable_lazy(|| format!("{reference} is not a branch, tag or commit"))?;
+        // Worktrees can only be added for a branch, so one is created for the commit and deleted
+        // again once the worktree's HEAD is detached from it
+        let mut branch = repo
+            .branch(&name, &commit, false)
+            .change_context(TmsError::GitError)?;
+        options.reference(Some(branch.get()));
+        let worktree = repo
+            .worktree(&name, &path, Some(&options))
+            .change_context(TmsError::GitError)?;
+        Repository::open_from_worktree(&worktree)
+            .and_then(|worktree_repo| worktree_repo.set_head_detached(commit.id()))
+            .change_context(TmsError::GitError)?;
+        branch.delete().change_context(TmsError::GitError)?;
+    }
+
+    Ok(path)
+}
+
+/// Whether the worktree has the `branch` of `reference` checked out, or when it isn't a branch the
+/// commit it points to with a detached HEAD
+fn has_checked_out(
+    worktree: &Worktree,
+    repo: &Repository,
+    reference: &str,
+    branch: Option<&Branch>,
+) -> bool {
+    let Ok(worktree_repo) = Repository::open_from_worktree(worktree) else {
+        return false;
+    };
+    let Ok(head) = worktree_repo.head() else {
+        return false;
+    };
+    match branch {
+        Some(branch) => head.name() == branch.get().name(),
+        None => {
+            let commit = repo
+                .revparse_single(reference)
+                .and_then(|object| object.peel_to_commit());
+            worktree_repo.head_detached().unwrap_or(false)
+                && commit.is_ok_and(|commit| head.target() == Some(commit.id()))
+        }
+    }
+}
+
+/// The local branch for `reference`, creating one that tracks the remote branch of that name if
+/// there is no local one yet
+fn local_branch<'r>(repo: &'r Repository, reference: &str) -> Result<Option<Branch<'r>>> {
+    if let Ok(branch) = repo.find_branch(reference, BranchType::Local) {
+        return Ok(Some(branch));
+    }
+
+    let remote_branch = repo
+        .find_branch(reference, BranchType::Remote)
+        .ok()
+        .or_else(|| {
+            repo.branches(Some(BranchType::Remote))
+                .ok()?
+                .flatten()
+                .map(|(branch, _)| branch)
+                .find(|branch| {
+                    branch
+                        .name()
+                        .ok()
+                        .flatten()
+                        .and_then(|name| name.split_once('/'))
+                        .is_some_and(|(_, name)| name == reference)
+                })
+        });
+    let Some(remote_branch) = remote_branch else {
+        return Ok(None);
+    };
+
+    let remote_name = remote_branch
+        .name()
+        .change_context(TmsError::GitError)?
+        .ok_or(TmsError::NonUtf8Path)?
+        .to_owned();
+    let local_name = remote_name
+        .split_once('/')
+        .map_or(remote_name.as_str(), |(_, name)| name);
+    if let Ok(branch) = repo.find_branch(local_name, BranchType::Local) {
+        return Ok(Some(branch));
+    }
+
+    let commit = remote_branch
+        .get()
+        .peel_to_commit()
+        .change_context(TmsError::GitError)?;
+    let mut branch = repo
+        .branch(local_name, &commit, false)
+        .change_context(TmsError::GitError)?;
+    branch
+        .set_upstream(Some(&remote_name))
+        .change_context(TmsError::GitError)?;
+    Ok(Some(branch))
+}
+
+/// The working directory of the repository or one of its worktrees where `branch` is checked out
+fn checked_out_at(repo: &Repository, branch: &str) -> Option<PathBuf> {
+    let has_branch = |repo: &Repository| {
+        repo.head()
+            .is_ok_and(|head| head.is_branch() && head.name() == Some(branch))
+    };
+
+    if !repo.is_bare() && has_branch(repo) {
+        return repo.workdir().map(Path::to_path_buf);
+    }
+    repo.worktrees()
+        .ok()?
+        .iter()
+        .flatten()
+        .filter_map(|name| repo.find_worktree(name).ok())
+        .filter_map(|worktree| Repository::open_from_worktree(&worktree).ok())
+        .find(has_branch)
+        .and_then(|worktree_repo| worktree_repo.workdir().map(Path::to_path_buf))
+}
+
 pub fn find_submodules(
     submodules: Vec<Submodule>,
     parent_name: &String,
@@ -93,11 +634,7 @@ pub fn find_submodules(
             Some(path) => path,
             _ => continue,
         };
-        let submodule_file_name = path
-            .file_name()
-            .expect("The file name doesn't end in `..`")
-            .to_string()?;
-        let session_name = format!("{}>{}", parent_name, submodule_file_name);
+        let session_name = format!("{}>{}", parent_name, dir_name(path)?);
         let name = if let Some(true) = config.display_full_path {
             path.display().to_string()
         } else {
@@ -114,3 +651,137 @@ pub fn find_submodules(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo_with_commit(path: &Path) -> Repository {
+        let repo = Repository::init(path).unwrap();
+        let signature = git2::Signature::now("tms", "tms@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "init",
+            &repo.find_tree(tree_id).unwrap(),
+            &[],
+        )
+        .unwrap();
+        repo
+    }
+
+    #[test]
+    fn finds_worktrees_outside_of_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_commit(&dir.path().join("project"));
+        let worktree_path = dir.path().join("elsewhere/feature");
+        fs::create_dir(dir.path().join("elsewhere")).unwrap();
+        repo.worktree("feature", &worktree_path, None).unwrap();
+
+        let sessions = linked_worktrees(&repo, "project");
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "project[feature]");
+        assert_eq!(
+            sessions[0].path().canonicalize().unwrap(),
+            worktree_path.canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn finds_the_repository_of_a_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_commit(&dir.path().join("project"));
+        let worktree_path = dir.path().join("feature");
+        repo.worktree("feature", &worktree_path, None).unwrap();
+
+        let main = main_repository(Repository::open(&worktree_path).unwrap()).unwrap();
+
+        assert!(!main.is_worktree());
+        assert_eq!(
+            main.path().canonicalize().unwrap(),
+            repo.path().canonicalize().unwrap()
+        );
+    }
+
+    #[test]
+    fn lists_every_directory_of_a_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        repo_with_commit(&dir.path().join("compilers"));
+        fs::create_dir_all(dir.path().join("databases/week1")).unwrap();
+        fs::create_dir(dir.path().join(".trash")).unwrap();
+        fs::write(dir.path().join("notes.md"), "").unwrap();
+        let config = Config {
+            workspace_dirs: Some(vec![dir.path().to_string_lossy().into_owned()]),
+            ..Default::default()
+        };
+
+        let mut repos = HashMap::new();
+        add_workspace_projects(&mut repos, &Excluder::new(&config).unwrap(), &config).unwrap();
+
+        let mut names: Vec<&String> = repos.keys().collect();
+        names.sort();
+        assert_eq!(names, ["compilers", "databases"]);
+        assert!(matches!(
+            repos["compilers"][0].session_type,
+            SessionType::Git(_)
+        ));
+    }
+
+    #[test]
+    fn adds_worktrees_for_branches_and_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_commit(&dir.path().join("project"));
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("release/1.0", &head, false).unwrap();
+        repo.tag_lightweight("v1.0", head.as_object(), false)
+            .unwrap();
+        let worktrees = dir.path().join("worktrees");
+
+        let release = worktree_for_ref(&repo, "release/1.0", &worktrees).unwrap();
+        assert_eq!(release, worktrees.join("release-1.0"));
+        let release_repo = Repository::open(&release).unwrap();
+        assert_eq!(
+            release_repo.head().unwrap().name(),
+            Some("refs/heads/release/1.0")
+        );
+        assert_eq!(
+            worktree_for_ref(&repo, "release/1.0", &worktrees).unwrap(),
+            release
+        );
+
+        let tag = worktree_for_ref(&repo, "v1.0", &worktrees).unwrap();
+        let tag_repo = Repository::open(&tag).unwrap();
+        assert!(tag_repo.head_detached().unwrap());
+        assert_eq!(tag_repo.head().unwrap().target(), Some(head.id()));
+        assert!(repo.find_branch("v1.0", BranchType::Local).is_err());
+        assert_eq!(worktree_for_ref(&repo, "v1.0", &worktrees).unwrap(), tag);
+
+        // Named like the worktree of `release/1.0`, which has another branch checked out
+        repo.branch("release-1.0", &head, false).unwrap();
+        assert!(worktree_for_ref(&repo, "release-1.0", &worktrees).is_err());
+    }
+
+    #[test]
+    fn skips_directories_like_git_ignores_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        fs::write(path.join(".gitignore"), "vendor\n/build\n*-cache\n").unwrap();
+        fs::create_dir_all(path.join("app/src")).unwrap();
+        fs::write(path.join("app/.gitignore"), "!vendor\nsrc/generated\n").unwrap();
+
+        let top = with_gitignore(&Rc::default(), path);
+        assert!(is_gitignored(&top, &path.join("vendor")));
+        assert!(is_gitignored(&top, &path.join("build")));
+        assert!(!is_gitignored(&top, &path.join("app")));
+
+        let app = with_gitignore(&top, &path.join("app"));
+        assert!(!is_gitignored(&app, &path.join("app/vendor")));
+        assert!(!is_gitignored(&app, &path.join("app/build")));
+        assert!(is_gitignored(&app, &path.join("app/tmp-cache")));
+        let src = with_gitignore(&app, &path.join("app/src"));
+        assert!(is_gitignored(&src, &path.join("app/src/generated")));
+    }
+}
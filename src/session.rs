@@ -1,20 +1,32 @@
 use std::{
     collections::HashMap,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use error_stack::ResultExt;
 use git2::Repository;
+use serde_derive::Serialize;
 
 use crate::{
-    configs::Config,
+    columns::PickerColumn,
+    configs::{Config, SessionEvent, SessionSortOrderConfig},
     dirty_paths::DirtyUtf8Path,
     error::TmsError,
-    repos::{find_repos, find_submodules},
+    frecency::sort_by_frecency,
+    fs_ops::move_to_trash,
+    i18n::{tr, tr_fmt},
+    repos::{cached_repos, find_repos, find_submodules, main_repository},
+    sort::sort_by_keys,
+    sparse,
+    state::{now, State},
     tmux::Tmux,
     Result,
 };
 
+/// A project that can be opened as a tmux session, whether it was found in the search paths,
+/// bookmarked or marked. It's the one shape projects have: the scan, bookmarks, marks and the
+/// picker's items all go through it, and its VCS details come from the repository of its
+/// [`SessionType`], see [`Session::kind`]
 pub struct Session {
     pub name: String,
     pub session_type: SessionType,
@@ -25,32 +37,128 @@ pub enum SessionType {
     Bookmark(PathBuf),
 }
 
+/// What kind of directory a project is, as listed by `--output json`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectKind {
+    Repository,
+    BareRepository,
+    Worktree,
+    Directory,
+}
+
 impl Session {
     pub fn new(name: String, session_type: SessionType) -> Self {
         Session { name, session_type }
     }
 
+    /// A bookmarked directory, named after the directory
+    pub fn bookmark(path: PathBuf) -> Result<Self> {
+        Ok(Session::new(dir_name(&path)?, SessionType::Bookmark(path)))
+    }
+
+    /// The project in the directory at `path` without searching for it, a repository when it is one
+    /// and otherwise a plain directory like a bookmark
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let path = path
+            .canonicalize()
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("{} does not exist", path.display()))?;
+        if !path.is_dir() {
+            return Err(TmsError::IoError)
+                .attach_printable(format!("{} is not a directory", path.display()));
+        }
+
+        match Repository::open(&path) {
+            Ok(repo) => Ok(Session::new(dir_name(&path)?, SessionType::Git(repo))),
+            Err(_) => Session::bookmark(path),
+        }
+    }
+
     pub fn path(&self) -> &Path {
         match &self.session_type {
             SessionType::Git(repo) if repo.is_bare() => repo.path(),
+            SessionType::Git(repo) if repo.is_worktree() => repo
+                .workdir()
+                .expect("worktrees should always have a working directory"),
             SessionType::Git(repo) => repo.path().parent().unwrap(),
             SessionType::Bookmark(path) => path,
         }
     }
 
+    pub fn kind(&self) -> ProjectKind {
+        match &self.session_type {
+            SessionType::Git(repo) if repo.is_bare() => ProjectKind::BareRepository,
+            SessionType::Git(repo) if repo.is_worktree() => ProjectKind::Worktree,
+            SessionType::Git(_) => ProjectKind::Repository,
+            SessionType::Bookmark(_) => ProjectKind::Directory,
+        }
+    }
+
+    /// The running tmux session open in the project's directory, preferring the one named after
+    /// the project when there are several. `running` is what [`running_sessions`] returns
+    pub fn running_session(&self, running: &HashMap<String, PathBuf>) -> Option<String> {
+        let own_name = tmux_session_name(&self.name);
+        if running
+            .get(&own_name)
+            .is_some_and(|path| same_directory(path, self.path()))
+        {
+            return Some(own_name);
+        }
+        running
+            .iter()
+            .filter(|(_, path)| same_directory(path, self.path()))
+            .map(|(name, _)| name)
+            .min()
+            .cloned()
+    }
+
     pub fn switch_to(&self, tmux: &Tmux, config: &Config) -> Result<()> {
+        self.switch_to_as(None, tmux, config)
+    }
+
+    /// Like [`Session::switch_to`], with the session named `session_name` instead of after the
+    /// project when it is given
+    pub fn switch_to_as(
+        &self,
+        session_name: Option<&str>,
+        tmux: &Tmux,
+        config: &Config,
+    ) -> Result<()> {
+        let session_name = self.create_as(session_name, tmux, config)?;
+        tmux.run_session_hooks(SessionEvent::Attach, self.path(), &session_name, config)?;
+        tmux.switch_to_session(&session_name)
+    }
+
+    /// Create the tmux session for this project unless it is already running, returning the name
+    /// of the tmux session
+    pub fn create(&self, tmux: &Tmux, config: &Config) -> Result<String> {
+        self.create_as(None, tmux, config)
+    }
+
+    /// Like [`Session::create`], with the session named `session_name` instead of after the project
+    /// when it is given
+    pub fn create_as(
+        &self,
+        session_name: Option<&str>,
+        tmux: &Tmux,
+        config: &Config,
+    ) -> Result<String> {
         match &self.session_type {
-            SessionType::Git(repo) => self.switch_to_repo_session(repo, tmux, config),
-            SessionType::Bookmark(path) => self.switch_to_bookmark_session(tmux, path, config),
+            SessionType::Git(repo) => self.create_repo_session(repo, session_name, tmux, config),
+            SessionType::Bookmark(path) => {
+                self.create_bookmark_session(tmux, path, session_name, config)
+            }
         }
     }
 
-    fn switch_to_repo_session(
+    fn create_repo_session(
         &self,
         repo: &Repository,
+        session_name: Option<&str>,
         tmux: &Tmux,
         config: &Config,
-    ) -> Result<()> {
+    ) -> Result<String> {
         let path = if repo.is_bare() {
             repo.path().to_path_buf().to_string()?
         } else {
@@ -60,33 +168,338 @@ impl Session {
                 .change_context(TmsError::IoError)?
                 .to_string()?
         };
-        let session_name = self.name.replace('.', "_");
+        let session_name = match session_name {
+            Some(name) => tmux_session_name(name),
+            None => self.unique_session_name(Path::new(&path), tmux),
+        };
 
         if !tmux.session_exists(&session_name) {
             tmux.new_session(Some(&session_name), Some(&path));
-            tmux.set_up_tmux_env(repo, &session_name)?;
-            tmux.run_session_create_script(self.path(), &session_name, config)?;
+            if let Some(color) = config.project_color(&session_name) {
+                tmux.set_status_color(&session_name, color);
+            }
+            if let Some(windows) = config.session_windows(&session_name) {
+                tmux.create_layout(&session_name, self.path(), windows)?;
+            } else {
+                tmux.set_up_tmux_env(repo, &session_name, config)?;
+            }
+            tmux.run_session_hooks(SessionEvent::Create, self.path(), &session_name, config)?;
         }
 
-        tmux.switch_to_session(&session_name);
-
-        Ok(())
+        Ok(session_name)
     }
 
-    fn switch_to_bookmark_session(&self, tmux: &Tmux, path: &Path, config: &Config) -> Result<()> {
-        let session_name = self.name.replace('.', "_");
+    fn create_bookmark_session(
+        &self,
+        tmux: &Tmux,
+        path: &Path,
+        session_name: Option<&str>,
+        config: &Config,
+    ) -> Result<String> {
+        let session_name = match session_name {
+            Some(name) => tmux_session_name(name),
+            None => self.unique_session_name(path, tmux),
+        };
 
         if !tmux.session_exists(&session_name) {
             tmux.new_session(Some(&session_name), path.to_str());
-            tmux.run_session_create_script(path, &session_name, config)?;
+            if let Some(color) = config.project_color(&session_name) {
+                tmux.set_status_color(&session_name, color);
+            }
+            if let Some(windows) = config.session_windows(&session_name) {
+                tmux.create_layout(&session_name, path, windows)?;
+            }
+            tmux.run_session_hooks(SessionEvent::Create, path, &session_name, config)?;
+        }
+
+        Ok(session_name)
+    }
+
+    /// What creating the session would do, shown before confirming it in the picker. `running` are
+    /// the running sessions, see [`running_sessions`], listed once by the caller as the plan is made
+    /// again for every frame of the preview
+    pub fn plan(&self, running: &HashMap<String, PathBuf>, config: &Config) -> String {
+        let path = self.path();
+        let session_name = unique_session_name(&self.name, path, running);
+        let mut plan = vec![
+            tr_fmt("Session: {}", &[&session_name]),
+            tr_fmt("Directory: {}", &[&path.display()]),
+        ];
+        if let SessionType::Git(repo) = &self.session_type {
+            if let Some(sparse) = sparse::describe(repo) {
+                plan.push(tr_fmt("Sparse checkout: {}", &[&sparse]));
+            }
+        }
+
+        if running.contains_key(&session_name) {
+            plan.push(tr("Already running, it would be switched to").to_owned());
+            return plan.join("\n");
+        }
+
+        plan.push(tr_fmt(
+            "Windows: {}",
+            &[&self.planned_windows(&session_name, config)],
+        ));
+        let script = config.create_script(&session_name, path);
+        if script.exists() {
+            plan.push(tr_fmt("Create script: {}", &[&script.display()]));
+        }
+        if let Some(color) = config.project_color(&session_name) {
+            plan.push(tr_fmt("Status line color: {}", &[&color]));
+        }
+        plan.join("\n")
+    }
+
+    fn planned_windows(&self, session_name: &str, config: &Config) -> String {
+        if let Some(windows) = config.session_windows(session_name) {
+            let repo = match &self.session_type {
+                SessionType::Git(repo) => Some(repo),
+                SessionType::Bookmark(_) => None,
+            };
+            return windows
+                .iter()
+                .map(|window| {
+                    let name = window.name.as_deref().unwrap_or(tr("unnamed"));
+                    let path = window
+                        .path
+                        .as_deref()
+                        .map_or_else(|| self.path().to_path_buf(), |path| self.path().join(path));
+                    if repo.is_some_and(|repo| sparse::is_excluded(repo, &path)) {
+                        tr_fmt("{} (not checked out)", &[&name])
+                    } else {
+                        name.to_owned()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+        }
+
+        let SessionType::Git(repo) = &self.session_type else {
+            return tr("a single window").to_owned();
+        };
+        if !repo.is_bare() || !config.worktree_windows(session_name) {
+            return tr("a single window").to_owned();
         }
+        let Ok(head) = repo.head() else {
+            return tr("a single window").to_owned();
+        };
+
+        let trees = repo
+            .worktrees()
+            .map(|trees| {
+                trees
+                    .iter()
+                    .flatten()
+                    .filter(|tree| {
+                        repo.find_worktree(tree)
+                            .is_ok_and(|worktree| worktree.validate().is_ok())
+                    })
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .unwrap_or_else(|_| Vec::new());
+        match trees.as_slice() {
+            [] => tr_fmt(
+                "{}, adding a worktree for it",
+                &[&head.shorthand().unwrap_or_default()],
+            ),
+            [_, _, ..] if config.pick_worktrees(session_name) => {
+                tr_fmt("picked from {}", &[&trees.join(", ")])
+            }
+            _ => trees.join(", "),
+        }
+    }
+
+    /// The values of the picker columns for the project, which has none when it isn't a repository
+    pub fn column_values(&self, columns: &[PickerColumn]) -> Vec<String> {
+        match &self.session_type {
+            SessionType::Git(repo) => columns.iter().map(|column| column.value(repo)).collect(),
+            SessionType::Bookmark(_) => Vec::new(),
+        }
+    }
 
-        tmux.switch_to_session(&session_name);
+    /// The name of the tmux session for this project, see [`unique_session_name`]
+    fn unique_session_name(&self, path: &Path, tmux: &Tmux) -> String {
+        unique_session_name(&self.name, path, &running_sessions(tmux))
+    }
+
+    /// The name to suggest for the project's session when its own name is used by a session in
+    /// another directory, so a different one can be asked for, see [`name_conflict`]
+    pub fn name_conflict(&self, tmux: &Tmux) -> Option<String> {
+        name_conflict(&self.name, self.path(), &running_sessions(tmux))
+    }
+
+    /// Why the project's session can't be named `session_name`, when a session of that name is
+    /// running in another directory
+    pub fn check_session_name(&self, session_name: &str, tmux: &Tmux) -> Option<String> {
+        let running = running_sessions(tmux);
+        let running_path = running.get(&tmux_session_name(session_name))?;
+        (!same_directory(running_path, self.path())).then(|| {
+            tr_fmt(
+                "{} is used by a session in {}",
+                &[&session_name, &running_path.display()],
+            )
+        })
+    }
 
+    /// Move the project's directory to the trash, killing its session when it's running. Sessions
+    /// listed in `protected_sessions` are not killed, so their project is kept as well. A worktree is
+    /// pruned from its repository afterwards
+    pub fn delete(&self, tmux: &Tmux, config: &Config) -> Result<()> {
+        let session = self.running_session(&running_sessions(tmux));
+        if let Some(session) = session.as_deref().filter(|name| config.is_protected(name)) {
+            return Err(TmsError::KillRefused(session.to_owned()))
+                .attach_printable(format!("{} is protected", self.path().display()));
+        }
+        if let Some(session) = &session {
+            tmux.run_session_hooks(SessionEvent::Kill, self.path(), session, config)?;
+        }
+        // The repository of a worktree is found while the worktree is still there to lead to it
+        let worktree = match &self.session_type {
+            SessionType::Git(repo) if repo.is_worktree() => {
+                let worktree_repo =
+                    Repository::open(repo.path()).change_context(TmsError::GitError)?;
+                Some((main_repository(worktree_repo)?, dir_name(repo.path())?))
+            }
+            _ => None,
+        };
+        // The directory is moved first, killing the session can end the picker when it runs in it
+        move_to_trash(self.path())?;
+        if let Some(session) = &session {
+            tmux.kill_session(session);
+        }
+        if let Some((repo, name)) = worktree {
+            prune_worktree(&repo, &name)?;
+        }
         Ok(())
     }
 }
 
+/// Remove what the repository knows about its worktree `name` once its directory is gone, like
+/// `git worktree prune` does
+fn prune_worktree(repo: &Repository, name: &str) -> Result<()> {
+    repo.find_worktree(name)
+        .and_then(|worktree| worktree.prune(None))
+        .change_context(TmsError::GitError)
+        .attach_printable_lazy(|| format!("Could not prune the worktree {name}"))
+}
+
+/// The running tmux sessions and their directories, see [`Tmux::running_sessions`]
+pub fn running_sessions(tmux: &Tmux) -> HashMap<String, PathBuf> {
+    tmux.running_sessions()
+}
+
+/// The tmux session name for the project `name` at `path`. A session already running in the
+/// directory is used whatever its name, so the project gets the same session from the picker, marks
+/// and `open-session`. Otherwise, while a session of that name is running for a different
+/// directory, the project's parent directories are prepended to the name, the same way projects
+/// sharing a name are told apart in the picker
+fn unique_session_name(name: &str, path: &Path, running: &HashMap<String, PathBuf>) -> String {
+    let mut session_name = tmux_session_name(name);
+    let running_here = |session_name: &String| {
+        running
+            .get(session_name)
+            .is_some_and(|running_path| same_directory(running_path, path))
+    };
+    if running_here(&session_name) {
+        return session_name;
+    }
+    // The project's session may have been named by another entry point, or renamed
+    if let Some(existing) = running
+        .iter()
+        .filter(|(_, running_path)| same_directory(running_path, path))
+        .map(|(name, _)| name)
+        .min()
+    {
+        return existing.clone();
+    }
+
+    let is_other_project = |session_name: &String| {
+        running
+            .get(session_name)
+            .is_some_and(|running_path| !same_directory(running_path, path))
+    };
+
+    // Names that were already deduplicated end in the directories they were made of
+    let name_len = Path::new(name).components().count();
+    let parents = if path.ends_with(name) {
+        path.ancestors().nth(name_len)
+    } else {
+        path.parent()
+    };
+    let mut parents = parents
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| match component {
+            Component::Normal(dir) => Some(dir.to_string_lossy()),
+            _ => None,
+        })
+        .rev();
+
+    let mut prefix = Vec::new();
+    while is_other_project(&session_name) {
+        let Some(parent) = parents.next() else {
+            break;
+        };
+        prefix.insert(0, parent);
+        session_name = tmux_session_name(&format!("{}/{name}", prefix.join("/")));
+    }
+    session_name
+}
+
+/// The name [`unique_session_name`] picks for the project `name` at `path`, when a session of the
+/// project's own name runs in another directory and none runs in the project's. The picked name can
+/// still be taken when the project's parent directories run out
+fn name_conflict(name: &str, path: &Path, running: &HashMap<String, PathBuf>) -> Option<String> {
+    let taken = running
+        .get(&tmux_session_name(name))
+        .is_some_and(|running_path| !same_directory(running_path, path));
+    let running_here = running
+        .values()
+        .any(|running_path| same_directory(running_path, path));
+    (taken && !running_here).then(|| unique_session_name(name, path, running))
+}
+
+fn same_directory(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// The name the scan gives the project at `path`, worked out from the path without searching. A
+/// worktree is named after its repository and itself, like `api[feature]`, and anything else after
+/// its directory
+pub fn project_name(path: &Path) -> Result<String> {
+    let name = dir_name(path)?;
+    let Some(repo) = Repository::open(path).ok().filter(Repository::is_worktree) else {
+        return Ok(name);
+    };
+    // The git directory of a worktree is `.git/worktrees/<name>` in its repository
+    let worktree = dir_name(repo.path())?;
+    let main = main_repository(repo)?;
+    // Bare repositories open their worktrees as windows, their worktrees aren't projects of their
+    // own
+    match main.workdir() {
+        Some(workdir) if !main.is_bare() => Ok(format!("{}[{worktree}]", dir_name(workdir)?)),
+        _ => Ok(name),
+    }
+}
+
+/// The name of the directory at `path`, which is the default name of the project in it
+pub fn dir_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .ok_or(TmsError::IoError)
+        .attach_printable_lazy(|| format!("{} has no directory name", path.display()))?
+        .to_string()
+}
+
+/// The name tmux gives a session created with `name`, as tmux replaces the characters it uses to
+/// separate session, window and pane targets
+pub fn tmux_session_name(name: &str) -> String {
+    name.replace(['.', ':'], "_")
+}
+
 pub trait SessionContainer {
     fn find_session(&self, name: &str) -> Option<&Session>;
     fn insert_session(&mut self, name: String, repo: Session);
@@ -110,6 +523,63 @@ impl SessionContainer for HashMap<String, Session> {
     }
 }
 
+/// Order the listed sessions so the ones recently closed through tms come first, returning how
+/// many of them are at the front of the list. `running` are the running sessions from
+/// [`running_sessions`]. The others are sorted by the `picker_sort` keys, or by
+/// `Frecency` when that is the configured order
+pub fn recently_closed_first(
+    sessions: &impl SessionContainer,
+    running: &HashMap<String, PathBuf>,
+    config: &Config,
+) -> Result<(Vec<String>, usize)> {
+    let mut list = sessions.list();
+    let state = State::load()?;
+    let session_name = |name: &str| match sessions.find_session(name) {
+        Some(session) => tmux_session_name(&session.name),
+        None => name.to_owned(),
+    };
+    let weights = config.session_sort_weights.clone().unwrap_or_default();
+    let sort_keys = config
+        .picker_sort_keys()
+        .change_context(TmsError::ConfigError)?;
+    if let Some(keys) = sort_keys {
+        let path = |name: &str| {
+            sessions
+                .find_session(name)
+                .map(|session| session.path().to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        sort_by_keys(
+            &mut list,
+            &keys,
+            path,
+            session_name,
+            &state,
+            &weights,
+            now(),
+        );
+    } else if let Some(SessionSortOrderConfig::Frecency) = config.session_sort_order {
+        sort_by_frecency(&mut list, session_name, &state, &weights, now());
+    }
+    let mut recent = Vec::new();
+    for closed in &state.recently_closed {
+        if running.contains_key(&closed.name) {
+            continue;
+        }
+        if let Some(index) = list.iter().position(|name| {
+            sessions
+                .find_session(name)
+                .is_some_and(|session| session.path() == closed.path)
+        }) {
+            recent.push(list.remove(index));
+        }
+    }
+
+    let len = recent.len();
+    recent.extend(list);
+    Ok((recent, len))
+}
+
 pub fn create_sessions(config: &Config) -> Result<impl SessionContainer> {
     let mut sessions = find_repos(config)?;
     sessions = append_bookmarks(config, sessions)?;
@@ -119,6 +589,21 @@ pub fn create_sessions(config: &Config) -> Result<impl SessionContainer> {
     Ok(sessions)
 }
 
+/// The sessions for the repositories found by the last scan, which is much faster than scanning
+/// on big trees. Falls back to scanning when there was no scan yet, returning whether the sessions
+/// came from the cache
+pub fn cached_sessions(config: &Config) -> Result<(impl SessionContainer, bool)> {
+    let (mut sessions, cached) = match cached_repos(config)? {
+        Some(sessions) => (sessions, true),
+        None => (find_repos(config)?, false),
+    };
+    sessions = append_bookmarks(config, sessions)?;
+
+    let sessions = generate_session_container(sessions, config)?;
+
+    Ok((sessions, cached))
+}
+
 fn generate_session_container(
     mut sessions: HashMap<String, Vec<Session>>,
     config: &Config,
@@ -222,11 +707,7 @@ fn append_bookmarks(
     let bookmarks = config.bookmark_paths();
 
     for path in bookmarks {
-        let session_name = path
-            .file_name()
-            .expect("The file name doesn't end in `..`")
-            .to_string()?;
-        let session = Session::new(session_name, SessionType::Bookmark(path));
+        let session = Session::bookmark(path)?;
         if let Some(list) = sessions.get_mut(&session.name) {
             list.push(session);
         } else {
@@ -239,8 +720,56 @@ fn append_bookmarks(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{BTreeSet, HashSet};
+
+    use proptest::prelude::*;
+
     use super::*;
 
+    #[test]
+    fn names_a_worktree_after_its_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("api")).unwrap();
+        let signature = git2::Signature::now("tms", "tms@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.worktree("feature", &dir.path().join("api-feature"), None)
+            .unwrap();
+
+        assert_eq!(project_name(&dir.path().join("api")).unwrap(), "api");
+        assert_eq!(
+            project_name(&dir.path().join("api-feature")).unwrap(),
+            "api[feature]"
+        );
+        assert_eq!(
+            project_name(dir.path()).unwrap(),
+            dir_name(dir.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn prunes_a_worktree_whose_directory_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("api")).unwrap();
+        let signature = git2::Signature::now("tms", "tms@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.worktree("feature", &dir.path().join("api-feature"), None)
+            .unwrap();
+
+        std::fs::remove_dir_all(dir.path().join("api-feature")).unwrap();
+        prune_worktree(&repo, "feature").unwrap();
+
+        assert!(repo.find_worktree("feature").is_err());
+        assert!(!repo.path().join("worktrees/feature").exists());
+    }
+
     #[test]
     fn verify_session_name_deduplication() {
         let mut test_sessions = vec![
@@ -264,4 +793,133 @@ mod tests {
         assert_eq!(deduplicated[1].name, "to/proj2/test");
         assert_eq!(deduplicated[2].name, "to/proj1/test");
     }
+
+    #[test]
+    fn prepends_parents_when_the_name_is_taken() {
+        let running = HashMap::from([
+            ("api".to_owned(), PathBuf::from("/work/client/api")),
+            ("client/api".to_owned(), PathBuf::from("/old/client/api")),
+            ("proj2/test".to_owned(), PathBuf::from("/other/proj2/test")),
+        ]);
+
+        let name = |name, path| unique_session_name(name, Path::new(path), &running);
+        assert_eq!(name("api", "/work/client/api"), "api");
+        assert_eq!(name("web", "/work/client/web"), "web");
+        assert_eq!(name("api", "/work/server/api"), "server/api");
+        assert_eq!(name("api", "/home/client/api"), "home/client/api");
+        assert_eq!(
+            name("proj2/test", "/search/proj2/test"),
+            "search/proj2/test"
+        );
+        assert_eq!(name("api[main]", "/work/api.main"), "api[main]");
+        assert_eq!(name("api", "/api"), "api");
+    }
+
+    #[test]
+    fn uses_the_session_running_in_the_directory_whatever_its_name() {
+        let running =
+            HashMap::from([("to/proj1/test".to_owned(), PathBuf::from("/to/proj1/test"))]);
+        assert_eq!(
+            unique_session_name("test", Path::new("/to/proj1/test"), &running),
+            "to/proj1/test"
+        );
+
+        let running = HashMap::from([("test".to_owned(), PathBuf::from("/to/proj1/test"))]);
+        assert_eq!(
+            unique_session_name("to/proj1/test", Path::new("/to/proj1/test"), &running),
+            "test"
+        );
+    }
+
+    #[test]
+    fn suggests_a_name_only_when_the_project_has_no_session() {
+        let running = HashMap::from([
+            ("api".to_owned(), PathBuf::from("/work/client/api")),
+            ("old-api".to_owned(), PathBuf::from("/old/client/api")),
+        ]);
+
+        let conflict = |name, path| name_conflict(name, Path::new(path), &running);
+        assert_eq!(conflict("api", "/work/client/api"), None);
+        assert_eq!(conflict("web", "/work/client/web"), None);
+        assert_eq!(
+            conflict("api", "/work/server/api"),
+            Some("server/api".into())
+        );
+        assert_eq!(conflict("api", "/api"), Some("api".into()));
+        assert_eq!(conflict("api", "/old/client/api"), None);
+        assert_eq!(
+            unique_session_name("api", Path::new("/old/client/api"), &running),
+            "old-api"
+        );
+    }
+
+    /// Distinct absolute paths that all end in the same project directory, built from a small set
+    /// of directory names so they often share long suffixes
+    fn duplicate_paths() -> impl Strategy<Value = BTreeSet<PathBuf>> {
+        let parents = prop::collection::vec(prop::sample::select(vec!["a", "b", "c"]), 0..5);
+        prop::collection::btree_set(parents, 2..8).prop_map(|sets| {
+            sets.into_iter()
+                .map(|parents| {
+                    let mut path = PathBuf::from("/");
+                    path.extend(parents);
+                    path.join("project")
+                })
+                .collect()
+        })
+    }
+
+    fn bookmarks(paths: &BTreeSet<PathBuf>) -> Vec<Session> {
+        paths
+            .iter()
+            .map(|path| Session::new("project".into(), SessionType::Bookmark(path.clone())))
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn deduplicated_names_are_unique(paths in duplicate_paths()) {
+            let deduplicated = deduplicate_sessions(&mut bookmarks(&paths));
+
+            prop_assert_eq!(deduplicated.len(), paths.len());
+            let names = deduplicated
+                .iter()
+                .map(|session| session.name.as_str())
+                .collect::<HashSet<_>>();
+            prop_assert_eq!(names.len(), paths.len());
+        }
+
+        #[test]
+        fn deduplicated_names_are_suffixes_of_their_path(paths in duplicate_paths()) {
+            for session in deduplicate_sessions(&mut bookmarks(&paths)) {
+                prop_assert!(session.name.ends_with("project"));
+                prop_assert!(
+                    session.path().ends_with(&session.name),
+                    "{} is not a suffix of {}",
+                    session.name,
+                    session.path().display()
+                );
+            }
+        }
+
+        #[test]
+        fn deduplication_handles_any_path(
+            paths in prop::collection::vec(any::<PathBuf>(), 0..6)
+        ) {
+            let mut sessions = paths
+                .into_iter()
+                .map(|path| Session::new("project".into(), SessionType::Bookmark(path)))
+                .collect();
+
+            deduplicate_sessions(&mut sessions);
+        }
+
+        #[test]
+        fn tmux_session_names_have_no_target_separators(name in any::<String>()) {
+            let sanitized = tmux_session_name(&name);
+
+            prop_assert!(!sanitized.contains(['.', ':']));
+            prop_assert_eq!(sanitized.chars().count(), name.chars().count());
+            prop_assert_eq!(tmux_session_name(&sanitized), sanitized);
+        }
+    }
 }
@@ -0,0 +1,24 @@
+# tms shell integration for bash, loaded with: eval "$(tms shell-init bash)"
+
+# Pick a project and cd into its directory, without tmux
+tms_cd() {
+    local cwd_file dir
+    cwd_file="$(mktemp)" || return
+    __TMS__ --cwd-file "$cwd_file" "$@"
+    dir="$(cat -- "$cwd_file")"
+    rm -f -- "$cwd_file"
+    if [ -n "$dir" ]; then
+        cd -- "$dir" || return
+    fi
+}
+
+# Ctrl-F opens the picker, through a function since the command can't be quoted in the binding
+_tms_widget() {
+    __TMS__
+}
+if [[ $- == *i* ]]; then
+    bind -x '"\C-f": _tms_widget'
+fi
+
+# Completion of the subcommands and their arguments
+source <(COMPLETE=bash __TMS__)
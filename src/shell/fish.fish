@@ -0,0 +1,21 @@
+# tms shell integration for fish, loaded with: tms shell-init fish | source
+
+function tms_cd --description 'Pick a project and cd into its directory, without tmux'
+    set -l cwd_file (mktemp); or return
+    __TMS__ --cwd-file $cwd_file $argv
+    set -l dir (cat $cwd_file)
+    rm -f $cwd_file
+    if test -n "$dir"
+        cd $dir
+    end
+end
+
+# Ctrl-F opens the picker, through a function since the command can't be quoted in the binding
+function _tms_widget
+    __TMS__
+    commandline -f repaint
+end
+bind \cf _tms_widget
+
+# Completion of the subcommands and their arguments
+COMPLETE=fish __TMS__ | source
@@ -0,0 +1,46 @@
+//! The scripts that integrate tms with the shell, printed by `tms shell-init`
+
+use clap::ValueEnum;
+
+/// Stands for the command that runs tms in the templates
+const COMMAND: &str = "__TMS__";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    fn template(self) -> &'static str {
+        match self {
+            Shell::Bash => include_str!("bash.sh"),
+            Shell::Zsh => include_str!("zsh.zsh"),
+            Shell::Fish => include_str!("fish.fish"),
+        }
+    }
+}
+
+/// The script that binds Ctrl-F to the picker, defines `tms_cd` and sets up the completions, with
+/// tms run as `command`
+pub fn init_script(shell: Shell, command: &str) -> String {
+    shell
+        .template()
+        .replace(COMMAND, &shell_words::quote(command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binds_a_function_instead_of_the_quoted_command() {
+        let script = init_script(Shell::Bash, "my tms");
+        assert!(script.contains("bind -x '\"\\C-f\": _tms_widget'"));
+        assert!(script.contains("_tms_widget() {\n    'my tms'\n}"));
+
+        let script = init_script(Shell::Fish, "my tms");
+        assert!(script.contains("bind \\cf _tms_widget\n"));
+    }
+}
@@ -0,0 +1,227 @@
+//! The order the picker lists the projects in before anything is typed, set with a `picker_sort`
+//! expression like `recency desc, name asc`
+
+use std::{cmp::Ordering, fmt::Display};
+
+use crate::{configs::SortWeights, frecency, state::State};
+
+/// What the projects can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    /// The name shown in the picker
+    Name,
+    /// The project's directory
+    Path,
+    /// When the project's session was last opened or switched to with tms
+    Recency,
+    /// How often the project's session was opened or switched to with tms
+    Frequency,
+    /// Recency and frequency together, weighed with `session_sort_weights`
+    Frecency,
+}
+
+impl SortField {
+    const ALL: [(&'static str, Self); 5] = [
+        ("name", Self::Name),
+        ("path", Self::Path),
+        ("recency", Self::Recency),
+        ("frequency", Self::Frequency),
+        ("frecency", Self::Frecency),
+    ];
+
+    /// Names and paths are sorted from a to z, the others with the most used first
+    fn descending_by_default(self) -> bool {
+        !matches!(self, Self::Name | Self::Path)
+    }
+}
+
+impl Display for SortField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = Self::ALL
+            .iter()
+            .find(|(_, field)| field == self)
+            .expect("Every field has a name");
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+/// Parse keys separated by commas, each a field optionally followed by `asc` or `desc`. Later keys
+/// only order the projects the earlier ones consider equal
+pub fn parse_sort_keys(expression: &str) -> std::result::Result<Vec<SortKey>, String> {
+    expression
+        .split(',')
+        .map(|key| {
+            let mut words = key.split_whitespace();
+            let field = match words.next() {
+                Some(name) => SortField::ALL
+                    .iter()
+                    .find(|(field_name, _)| field_name.eq_ignore_ascii_case(name))
+                    .map(|(_, field)| *field)
+                    .ok_or_else(|| format!("unknown sort key `{name}`"))?,
+                None => return Err("expected a sort key between the commas".to_owned()),
+            };
+            let descending = match words.next() {
+                None => field.descending_by_default(),
+                Some(order) if order.eq_ignore_ascii_case("asc") => false,
+                Some(order) if order.eq_ignore_ascii_case("desc") => true,
+                Some(order) => {
+                    return Err(format!(
+                        "expected `asc` or `desc` after `{field}` but got `{order}`"
+                    ))
+                }
+            };
+            if let Some(extra) = words.next() {
+                return Err(format!(
+                    "unexpected `{extra}` after `{key}`",
+                    key = key.trim()
+                ));
+            }
+            Ok(SortKey { field, descending })
+        })
+        .collect()
+}
+
+/// A project's values for each of the sort fields
+struct Row {
+    item: String,
+    path: String,
+    last_used: u64,
+    uses: u64,
+    frecency: f64,
+}
+
+/// Sort the items by the keys, where `path` is the directory of an item's project and
+/// `session_name` the name of its session, which its uses are recorded by. Items the keys consider
+/// equal keep their order
+pub fn sort_by_keys(
+    items: &mut [String],
+    keys: &[SortKey],
+    path: impl Fn(&str) -> String,
+    session_name: impl Fn(&str) -> String,
+    state: &State,
+    weights: &SortWeights,
+    now: u64,
+) {
+    let names: Vec<String> = items.iter().map(|item| session_name(item)).collect();
+    let frecency = frecency::scores(&names, &state.last_used, &state.use_counts, weights, now);
+    let mut rows: Vec<Row> = items
+        .iter()
+        .zip(names)
+        .zip(frecency)
+        .map(|((item, name), frecency)| Row {
+            path: path(item),
+            last_used: state.last_used.get(&name).copied().unwrap_or_default(),
+            uses: state.use_counts.get(&name).copied().unwrap_or_default(),
+            item: item.clone(),
+            frecency,
+        })
+        .collect();
+
+    rows.sort_by(|a, b| {
+        keys.iter().fold(Ordering::Equal, |ordering, key| {
+            ordering.then_with(|| compare(a, b, *key))
+        })
+    });
+    for (item, row) in items.iter_mut().zip(rows) {
+        *item = row.item;
+    }
+}
+
+fn compare(a: &Row, b: &Row, key: SortKey) -> Ordering {
+    let ordering = match key.field {
+        SortField::Name => a.item.cmp(&b.item),
+        SortField::Path => a.path.cmp(&b.path),
+        SortField::Recency => a.last_used.cmp(&b.last_used),
+        SortField::Frequency => a.uses.cmp(&b.uses),
+        SortField::Frecency => a
+            .frecency
+            .partial_cmp(&b.frecency)
+            .unwrap_or(Ordering::Equal),
+    };
+    if key.descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn parses_keys_with_their_default_order() {
+        assert_eq!(
+            parse_sort_keys("recency desc, name asc"),
+            Ok(vec![
+                SortKey {
+                    field: SortField::Recency,
+                    descending: true,
+                },
+                SortKey {
+                    field: SortField::Name,
+                    descending: false,
+                },
+            ])
+        );
+        assert_eq!(
+            parse_sort_keys("Frequency,path desc"),
+            Ok(vec![
+                SortKey {
+                    field: SortField::Frequency,
+                    descending: true,
+                },
+                SortKey {
+                    field: SortField::Path,
+                    descending: true,
+                },
+            ])
+        );
+        assert!(parse_sort_keys("size desc").is_err());
+        assert!(parse_sort_keys("name upwards").is_err());
+        assert!(parse_sort_keys("name asc desc").is_err());
+        assert!(parse_sort_keys("name,,path").is_err());
+    }
+
+    #[test]
+    fn sorts_by_each_key_in_turn() {
+        let state = State {
+            last_used: BTreeMap::from([
+                ("api".into(), 20),
+                ("blog".into(), 20),
+                ("zsh".into(), 30),
+            ]),
+            use_counts: BTreeMap::from([("api".into(), 1), ("blog".into(), 5), ("zsh".into(), 2)]),
+            ..Default::default()
+        };
+        let sorted = |expression: &str| {
+            let mut items = vec!["api".into(), "blog".into(), "notes".into(), "zsh".into()];
+            sort_by_keys(
+                &mut items,
+                &parse_sort_keys(expression).unwrap(),
+                |item| format!("/code/{}", item.chars().rev().collect::<String>()),
+                str::to_owned,
+                &state,
+                &SortWeights::default(),
+                30,
+            );
+            items
+        };
+
+        assert_eq!(sorted("recency"), ["zsh", "api", "blog", "notes"]);
+        assert_eq!(
+            sorted("recency, frequency"),
+            ["zsh", "blog", "api", "notes"]
+        );
+        assert_eq!(sorted("name desc"), ["zsh", "notes", "blog", "api"]);
+        assert_eq!(sorted("path"), ["blog", "zsh", "api", "notes"]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Sparse checkouts, which leave parts of a repository out of its working tree, so windows can't be
+//! opened in them
+
+use std::{fs, path::Path};
+
+use git2::{IndexEntryExtendedFlag, Repository};
+
+/// Whether only part of the repository is checked out
+pub fn is_sparse(repo: &Repository) -> bool {
+    !repo.is_bare()
+        && repo
+            .config()
+            .and_then(|config| config.get_bool("core.sparseCheckout"))
+            .unwrap_or_default()
+}
+
+/// Whether the directory belongs to the repository but is left out of its sparse checkout. Paths
+/// outside of the repository and ones that don't exist in it at all aren't excluded
+pub fn is_excluded(repo: &Repository, path: &Path) -> bool {
+    if path.exists() || !is_sparse(repo) {
+        return false;
+    }
+    let Some(relative) = repo
+        .workdir()
+        .and_then(|workdir| path.strip_prefix(workdir).ok())
+        .and_then(Path::to_str)
+    else {
+        return false;
+    };
+    let Ok(index) = repo.index() else {
+        return false;
+    };
+
+    let prefix = format!("{}/", relative.trim_end_matches('/'));
+    index.iter().any(|entry| {
+        entry.path.starts_with(prefix.as_bytes())
+            && entry.flags_extended & IndexEntryExtendedFlag::SKIP_WORKTREE.bits() != 0
+    })
+}
+
+/// What a sparse checkout includes, for the preview: the directories of a cone mode checkout, or
+/// else its patterns
+pub fn describe(repo: &Repository) -> Option<String> {
+    if !is_sparse(repo) {
+        return None;
+    }
+    // Linked worktrees have their own in their git directory
+    let contents = fs::read_to_string(repo.path().join("info").join("sparse-checkout")).ok()?;
+    let patterns: Vec<&str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let cone = repo
+        .config()
+        .and_then(|config| config.get_bool("core.sparseCheckoutCone"))
+        .unwrap_or_default();
+    if cone {
+        Some(cone_dirs(&patterns).join(", "))
+    } else {
+        Some(patterns.join(" "))
+    }
+}
+
+/// The directories a cone mode checkout includes. Cone mode lists the parents of each directory as
+/// well, only the deepest ones are kept
+fn cone_dirs<'a>(patterns: &[&'a str]) -> Vec<&'a str> {
+    let dirs: Vec<&str> = patterns
+        .iter()
+        .filter(|pattern| !pattern.starts_with('!') && **pattern != "/*")
+        .map(|pattern| pattern.trim_matches('/'))
+        .collect();
+    let deepest: Vec<&str> = dirs
+        .iter()
+        .filter(|dir| {
+            !dirs
+                .iter()
+                .any(|other| other.starts_with(&format!("{dir}/")))
+        })
+        .copied()
+        .collect();
+    if deepest.is_empty() {
+        // Only the files at the top are checked out
+        vec!["/"]
+    } else {
+        deepest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_the_deepest_directories_of_a_cone() {
+        let patterns = ["/*", "!/*/", "/apps/", "!/apps/*/", "/apps/web/", "/docs/"];
+        assert_eq!(cone_dirs(&patterns), vec!["apps/web", "docs"]);
+        assert_eq!(cone_dirs(&["/*", "!/*/"]), vec!["/"]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::ResultExt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    error::Suggestion,
+    files::{write_atomic, FileLock},
+    Result, TmsError,
+};
+
+const RECENTLY_CLOSED_LIMIT: usize = 10;
+const RECENT_SESSIONS_LIMIT: usize = 10;
+/// Uses of a session closer together than this are counted once, so switching with tms and the
+/// hook reporting that switch aren't two uses
+const USE_INTERVAL: u64 = 60;
+
+/// Data tms keeps track of between invocations which isn't user configuration
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct State {
+    #[serde(default)]
+    pub recently_closed: Vec<ClosedSession>,
+    /// When each session was last created or switched to, in seconds since the unix epoch
+    #[serde(default)]
+    pub last_used: BTreeMap<String, u64>,
+    /// How many times each session was used
+    #[serde(default)]
+    pub use_counts: BTreeMap<String, u64>,
+    /// The worktrees picked to be opened as windows, by the path of their repository
+    #[serde(default)]
+    pub worktree_selections: BTreeMap<String, Vec<String>>,
+    /// When sessions were last checked for being stale, in seconds since the unix epoch
+    #[serde(default)]
+    pub last_stale_check: u64,
+    /// The sessions each client switched to, most recent first, by the client's tty or the tmux
+    /// socket when switching from outside of tmux
+    #[serde(default)]
+    pub recent_sessions: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClosedSession {
+    pub name: String,
+    pub path: PathBuf,
+    /// Seconds since the unix epoch
+    pub closed_at: u64,
+}
+
+impl State {
+    pub fn load() -> Result<Self> {
+        let path = state_file()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not read state file {}", path.display()))?;
+        toml::from_str(&contents)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not parse state file {}", path.display()))
+    }
+
+    /// Load the state, change it and save it again, while other tms invocations wait to change it
+    /// so none of the changes are lost
+    pub fn update<T>(change: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        let path = state_file()?;
+        let _lock = FileLock::acquire(&path)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not lock state file {}", path.display()))?;
+        let mut state = Self::load()?;
+        let changed = change(&mut state);
+        state.save()?;
+        Ok(changed)
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = state_file()?;
+        let contents = toml::to_string_pretty(self).change_context(TmsError::IoError)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .change_context(TmsError::IoError)
+                .attach_printable("Unable to create tms state folder")?;
+        }
+        write_atomic(&path, contents)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not write state file {}", path.display()))
+    }
+
+    pub fn add_recently_closed(&mut self, name: String, path: PathBuf) {
+        self.recently_closed.retain(|session| session.path != path);
+        self.recently_closed.insert(
+            0,
+            ClosedSession {
+                name,
+                path,
+                closed_at: now(),
+            },
+        );
+        self.recently_closed.truncate(RECENTLY_CLOSED_LIMIT);
+    }
+
+    /// Record that the session was used now, counting it as another use only when it wasn't used
+    /// in the last [`USE_INTERVAL`] seconds, so switching back and forth doesn't inflate the count
+    pub fn mark_used(&mut self, name: &str) {
+        let now = now();
+        let last_used = self.last_used.insert(name.to_owned(), now);
+        if last_used.is_none_or(|last_used| now.saturating_sub(last_used) >= USE_INTERVAL) {
+            *self.use_counts.entry(name.to_owned()).or_default() += 1;
+        }
+    }
+
+    /// Move the session switched to, and the one left for it, to the front of the client's recent
+    /// sessions
+    pub fn record_switch(&mut self, client: &str, from: Option<&str>, to: &str) {
+        let recent = self.recent_sessions.entry(client.to_owned()).or_default();
+        for name in from.into_iter().chain([to]) {
+            recent.retain(|session| session != name);
+            recent.insert(0, name.to_owned());
+        }
+        recent.truncate(RECENT_SESSIONS_LIMIT);
+    }
+}
+
+/// Add a line about a session killed for being stale to the log next to the state file
+pub fn log_stale_session(name: &str, path: &Path, unused_for: Duration) -> Result<()> {
+    let path_to_log = data_file("stale_sessions.log")?;
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path_to_log)
+        .change_context(TmsError::IoError)
+        .attach_printable_lazy(|| format!("Could not open {}", path_to_log.display()))?;
+    let hours = unused_for.as_secs() / (60 * 60);
+    let unused_for = if hours < 48 {
+        format!("{hours} hours")
+    } else {
+        format!("{} days", hours / 24)
+    };
+    writeln!(
+        log,
+        "{} killed {name} ({}), unused for {unused_for}",
+        now(),
+        path.display(),
+    )
+    .change_context(TmsError::IoError)
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// A file that tms keeps next to the state file
+pub(crate) fn data_file(name: &str) -> Result<PathBuf> {
+    Ok(state_file()?.with_file_name(name))
+}
+
+fn state_file() -> Result<PathBuf> {
+    if let Ok(path) = env::var("TMS_STATE_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+    dirs::data_local_dir()
+        .map(|path| path.join("tms/state.toml"))
+        .ok_or(TmsError::IoError)
+        .attach_printable("Could not find a valid location for the state file")
+        .attach(Suggestion(
+            "Try specifying a state file with the TMS_STATE_FILE environment variable.",
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_most_recent_sessions_first() {
+        let mut state = State::default();
+        state.record_switch("default:/dev/pts/1", Some("notes"), "api");
+        state.record_switch("default:/dev/pts/1", Some("api"), "notes");
+        state.record_switch("default:/dev/pts/2", None, "music");
+
+        assert_eq!(
+            state.recent_sessions["default:/dev/pts/1"],
+            ["notes", "api"]
+        );
+        assert_eq!(state.recent_sessions["default:/dev/pts/2"], ["music"]);
+    }
+}
@@ -1,17 +1,53 @@
-use std::{env, os::unix::process::CommandExt, path::Path, process};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
 
 use error_stack::ResultExt;
 use git2::Repository;
+use ratatui::style::Color;
 
 use crate::{
-    configs::Config,
+    configs::{Config, Pane, SessionEvent, Window},
     dirty_paths::DirtyUtf8Path,
-    error::{Result, TmsError},
+    error::{Result, Suggestion, TmsError},
+    history::{self, Entry},
+    picker::{has_terminal, Picker, Preview},
+    sparse::is_excluded,
+    state::State,
 };
 
+/// How long the listed sessions are used for before tmux is asked again, so flows that check the
+/// sessions several times only list them once while still seeing sessions made outside of tms
+const SESSIONS_CACHE_TIME: Duration = Duration::from_secs(1);
+
+/// The tmux commands that create, remove or rename sessions or change their directories, which
+/// make the listed sessions out of date
+const SESSION_CHANGING_COMMANDS: [&str; 5] = [
+    "new-session",
+    "kill-session",
+    "rename-session",
+    "kill-server",
+    "attach-session",
+];
+
+/// The running sessions and their directories, with when they were listed
+type ListedSessions = (Instant, HashMap<String, PathBuf>);
+
 #[derive(Clone)]
 pub struct Tmux {
     socket_name: String,
+    /// Shared by the clones of this, so changing the sessions through one clears it for all
+    sessions: Arc<Mutex<Option<ListedSessions>>>,
+    /// Whether tms runs inside of tmux, which doesn't change while it runs, so the server is only
+    /// asked once
+    in_session: Arc<OnceLock<bool>>,
 }
 
 impl Default for Tmux {
@@ -20,28 +56,61 @@ impl Default for Tmux {
             .ok()
             .unwrap_or(String::from("default"));
 
-        Self { socket_name }
+        Self {
+            socket_name,
+            sessions: Arc::default(),
+            in_session: Arc::default(),
+        }
     }
 }
 
 impl Tmux {
+    pub fn socket_name(&self) -> &str {
+        &self.socket_name
+    }
+
     // Private utility functions
 
     fn execute_tmux_command(&self, args: &[&str]) -> process::Output {
-        process::Command::new("tmux")
-            .args(["-L", &self.socket_name])
-            .args(args)
-            .stdin(process::Stdio::inherit())
+        if args
+            .first()
+            .is_some_and(|command| SESSION_CHANGING_COMMANDS.contains(command))
+        {
+            self.forget_sessions();
+        }
+        self.tmux_command(args)
             .output()
             .unwrap_or_else(|_| panic!("Failed to execute the tmux command `{args:?}`"))
     }
 
+    #[cfg(unix)]
     fn replace_with_tmux_command(&self, args: &[&str]) -> std::io::Error {
-        process::Command::new("tmux")
+        self.tmux_command(args).exec()
+    }
+
+    /// Without `exec` tmux runs until it exits, and then tms exits the way it did
+    #[cfg(not(unix))]
+    fn replace_with_tmux_command(&self, args: &[&str]) -> std::io::Error {
+        match self.tmux_command(args).status() {
+            Ok(status) => process::exit(status.code().unwrap_or(1)),
+            Err(error) => error,
+        }
+    }
+
+    fn tmux_command(&self, args: &[&str]) -> process::Command {
+        let mut command = tmux_program();
+        command
             .args(["-L", &self.socket_name])
-            .args(args)
-            .stdin(process::Stdio::inherit())
-            .exec()
+            .args(args.iter().enumerate().map(|(i, arg)| {
+                // The directories of sessions and windows follow `-c`
+                if i > 0 && args[i - 1] == "-c" {
+                    tmux_path(arg)
+                } else {
+                    (*arg).to_owned()
+                }
+            }))
+            .stdin(process::Stdio::inherit());
+        command
     }
 
     fn stdout_to_string(output: process::Output) -> String {
@@ -95,7 +164,49 @@ impl Tmux {
         self.execute_tmux_command(&["rename-session", session_name])
     }
 
-    pub fn attach_session(&self, session_name: Option<&str>, path: Option<&str>) -> std::io::Error {
+    /// Change the directory new windows of the session start in, returning whether it changed.
+    ///
+    /// `attach-session -c` is the only command that changes it. It's run without a terminal to
+    /// attach, and without `TMUX` as inside of tmux it would refuse to nest the session before doing
+    /// anything. It then fails, but tmux sets the path before it finds out there is no terminal.
+    /// Should a version of tmux check for the terminal first, the path stays as it was, which is
+    /// why it's read back afterwards
+    pub fn set_session_path(&self, session: &str, path: &str) -> bool {
+        let _ = self
+            .tmux_command(&["attach-session", "-t", session, "-c", path])
+            .env_remove("TMUX")
+            .stdin(process::Stdio::null())
+            .output();
+        self.session_path(session) == path
+    }
+
+    /// The directory new windows of the session start in
+    pub fn session_path(&self, session: &str) -> String {
+        let output =
+            self.execute_tmux_command(&["display-message", "-p", "-t", session, "#{session_path}"]);
+        Tmux::stdout_to_string(output).trim().to_owned()
+    }
+
+    /// Attach to the session in place of tms, or inside of tmux switch the client to it, as
+    /// attaching there would nest the session. Without a session to attach to, the most recent one
+    /// is used outside of tmux and nothing changes inside of it. Only returns an error when tmux
+    /// couldn't be started
+    pub fn attach_session(
+        &self,
+        session_name: Option<&str>,
+        path: Option<&str>,
+    ) -> std::io::Result<()> {
+        if self.in_session() {
+            let Some(name) = session_name else {
+                return Ok(());
+            };
+            // A client of another server, like with `TMS_TMUX_SOCKET`, can't be switched and
+            // attaches instead
+            let switched = self.execute_tmux_command(&["switch-client", "-t", name]);
+            if switched.status.success() {
+                return Ok(());
+            }
+        }
         let mut args = vec!["attach-session"];
 
         if let Some(name) = session_name {
@@ -106,52 +217,151 @@ impl Tmux {
             args.extend(["-c", path]);
         }
 
-        self.replace_with_tmux_command(&args)
+        Err(self.replace_with_tmux_command(&args))
     }
 
-    pub fn switch_to_session(&self, repo_short_name: &str) {
-        if !is_in_tmux_session() {
-            self.attach_session(Some(repo_short_name), None);
-        } else {
-            let result = self.switch_client(repo_short_name);
-            if !result.status.success() {
-                self.attach_session(Some(repo_short_name), None);
+    /// Switch the client to the session, or attach to it in place of tms when there is no client
+    /// of this server to switch, like when run outside of tmux
+    pub fn switch_to_session(&self, repo_short_name: &str) -> Result<()> {
+        // The recent sessions are bookkeeping, failing to update them shouldn't stop the switch
+        let _ = self.record_switch(repo_short_name);
+        self.attach_session(Some(repo_short_name), None)
+            .change_context(TmsError::IoError)
+            .attach_printable_lazy(|| format!("Could not attach to session {repo_short_name}"))
+    }
+
+    /// Whether tms runs inside of tmux, known from `$TMUX` or, when that was cleared, by asking
+    /// the server whether one of its panes is the terminal tms runs in
+    pub fn in_session(&self) -> bool {
+        *self.in_session.get_or_init(|| {
+            if env::var_os("TMUX").is_some_and(|tmux| !tmux.is_empty()) {
+                return true;
             }
+            let Some(terminal) = terminal_name() else {
+                return false;
+            };
+            let output = self.execute_tmux_command(&["list-panes", "-a", "-F", "#{pane_tty}"]);
+            Tmux::stdout_to_string(output)
+                .lines()
+                .any(|pane_tty| pane_tty == terminal)
+        })
+    }
+
+    /// Remember the switch in the recent sessions of the client, for `tms last`, and as a use of the
+    /// session for sorting by `Frecency`
+    fn record_switch(&self, session_name: &str) -> Result<()> {
+        let current = self
+            .in_session()
+            .then(|| self.display_message("#{session_name}"));
+        let current = current
+            .as_deref()
+            .map(str::trim)
+            .filter(|current| !current.is_empty());
+        let client = self.client_key();
+        State::update(|state| {
+            state.record_switch(&client, current, session_name);
+            state.mark_used(session_name);
+        })
+    }
+
+    /// What the recent sessions of the current client are kept by: its tty inside of tmux, or only
+    /// the socket when there is no client yet
+    pub fn client_key(&self) -> String {
+        if !self.in_session() {
+            return self.socket_name.clone();
         }
+        self.client_key_of(self.display_message("#{client_tty}").trim())
+    }
+
+    pub fn client_key_of(&self, client_tty: &str) -> String {
+        format!("{}:{client_tty}", self.socket_name)
+    }
+
+    /// Whether the tmux server is running, it isn't until the first session is created
+    pub fn server_running(&self) -> bool {
+        self.execute_tmux_command(&["list-sessions"])
+            .status
+            .success()
+    }
+
+    /// Fail when there is no tmux server with sessions to work with
+    pub fn require_server(&self) -> Result<()> {
+        if self.server_running() {
+            return Ok(());
+        }
+        Err(TmsError::NoServer).attach(Suggestion(
+            "Run `tms` to pick a project, which starts tmux with a session for it",
+        ))
     }
 
     pub fn session_exists(&self, repo_short_name: &str) -> bool {
-        // Get the tmux sessions
-        let sessions = self.list_sessions("'#S'");
+        self.running_sessions().contains_key(repo_short_name)
+    }
 
-        // If the session already exists switch to it, else create the new session and then switch
-        sessions.lines().any(|line| {
-            let mut cleaned_line = line.to_owned();
-            // tmux will return the output with extra ' and \n characters
-            cleaned_line.retain(|char| char != '\'' && char != '\n');
+    /// The running sessions and their directories. They are listed once for a short while, until
+    /// tms changes the sessions itself
+    pub fn running_sessions(&self) -> HashMap<String, PathBuf> {
+        let mut cached = self
+            .sessions
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        if let Some((_, sessions)) = cached
+            .as_ref()
+            .filter(|(listed, _)| listed.elapsed() < SESSIONS_CACHE_TIME)
+        {
+            return sessions.clone();
+        }
+        let sessions: HashMap<String, PathBuf> = self
+            .list_sessions("#{session_name}\t#{session_path}")
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(name, path)| (name.to_owned(), PathBuf::from(path)))
+            .collect();
+        *cached = Some((Instant::now(), sessions.clone()));
+        sessions
+    }
 
-            cleaned_line == repo_short_name
-        })
+    /// List the sessions again the next time they are needed
+    fn forget_sessions(&self) {
+        *self
+            .sessions
+            .lock()
+            .unwrap_or_else(|error| error.into_inner()) = None;
     }
 
-    pub fn run_session_create_script(
+    /// Run the hooks configured for `event`, after sending the create script to a created session
+    pub fn run_session_hooks(
         &self,
+        event: SessionEvent,
         path: &Path,
         session_name: &str,
         config: &Config,
     ) -> Result<()> {
-        let command_path = match &config.session_configs {
-            Some(sessions) => match sessions.get(session_name) {
-                Some(session) => match &session.create_script {
-                    Some(create_script) => create_script.to_owned(),
-                    None => path.join(".tms-create"),
-                },
-                None => path.join(".tms-create"),
-            },
-            None => path.join(".tms-create"),
-        };
+        if event == SessionEvent::Create {
+            let command_path = config.create_script(session_name, path);
+            self.run_session_script(&command_path, session_name)?;
+        }
+
+        // The log is opt-in bookkeeping, failing to write it shouldn't stop the session's hooks
+        let _ = history::record(config, Entry::new(event.into()).session(session_name, path));
+
+        for command in config.hooks(event, session_name) {
+            // A failing hook is the user's to fix and shouldn't stop tms from doing what was asked
+            let mut hook = hook_command(command)?;
+            if path.is_dir() {
+                hook.current_dir(path);
+            }
+            hook.env("TMS_SESSION_NAME", session_name)
+                .env("TMS_SESSION_PATH", path)
+                .stdin(process::Stdio::null())
+                .status()
+                .change_context(TmsError::IoError)
+                .attach_printable_lazy(|| format!("Could not run the hook `{command}`"))?;
+            // Hooks can change the sessions through tmux themselves
+            self.forget_sessions();
+        }
 
-        self.run_session_script(&command_path, session_name)
+        Ok(())
     }
 
     fn run_session_script(&self, command_path: &Path, session_name: &str) -> Result<()> {
@@ -190,6 +400,11 @@ impl Tmux {
         self.execute_tmux_command(&args)
     }
 
+    /// Run a command in a new window of the current session without switching to it
+    pub fn new_background_window(&self, name: &str, command: &str) -> process::Output {
+        self.execute_tmux_command(&["new-window", "-d", "-n", name, command])
+    }
+
     pub fn kill_window(&self, window: &str) -> process::Output {
         self.execute_tmux_command(&["kill-window", "-t", window])
     }
@@ -205,6 +420,16 @@ impl Tmux {
         Tmux::stdout_to_string(output)
     }
 
+    pub fn list_all_windows(&self, format: &str) -> String {
+        let output = self.execute_tmux_command(&["list-windows", "-a", "-F", format]);
+        Tmux::stdout_to_string(output)
+    }
+
+    pub fn list_panes(&self, format: &str, session: &str) -> String {
+        let output = self.execute_tmux_command(&["list-panes", "-s", "-F", format, "-t", session]);
+        Tmux::stdout_to_string(output)
+    }
+
     pub fn select_window(&self, window: &str) -> process::Output {
         self.execute_tmux_command(&["select-window", "-t", window])
     }
@@ -232,21 +457,201 @@ impl Tmux {
         }
     }
 
+    /// Switch a given client, by its tty, to the session
+    pub fn switch_client_of(&self, client: &str, session_name: &str) -> process::Output {
+        self.execute_tmux_command(&["switch-client", "-c", client, "-t", session_name])
+    }
+
+    pub fn list_clients(&self, format: &str) -> String {
+        let output = self.execute_tmux_command(&["list-clients", "-F", format]);
+        Tmux::stdout_to_string(output)
+    }
+
+    /// Detach every client attached to a session
+    pub fn detach_clients(&self, session_name: &str) -> process::Output {
+        self.execute_tmux_command(&["detach-client", "-s", session_name])
+    }
+
+    /// The version of the tmux installed, `None` when tmux can't be run
+    pub fn version(&self) -> Option<String> {
+        let output = tmux_program().arg("-V").output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| Tmux::stdout_to_string(output).trim().to_owned())
+    }
+
     pub fn display_message(&self, format: &str) -> String {
         let output = self.execute_tmux_command(&["display-message", "-p", format]);
         Tmux::stdout_to_string(output)
     }
 
+    pub fn set_global_option(&self, option: &str, value: &str) -> process::Output {
+        self.execute_tmux_command(&["set-option", "-g", option, value])
+    }
+
+    /// Color the status line of a session
+    pub fn set_status_color(&self, session: &str, color: Color) -> process::Output {
+        let style = format!("bg={}", tmux_color(color));
+        self.execute_tmux_command(&["set-option", "-t", session, "status-style", &style])
+    }
+
+    pub fn set_hook(&self, hook: &str, command: &str) -> process::Output {
+        self.execute_tmux_command(&["set-hook", "-g", hook, command])
+    }
+
+    pub fn unset_hook(&self, hook: &str) -> process::Output {
+        self.execute_tmux_command(&["set-hook", "-gu", hook])
+    }
+
     pub fn refresh_client(&self) -> process::Output {
         self.execute_tmux_command(&["refresh-client", "-S"])
     }
 
-    pub fn capture_pane(&self, target_pane: &str) -> process::Output {
-        self.execute_tmux_command(&["capture-pane", "-ep", "-t", target_pane])
+    /// The visible part of the pane, and as many lines of its scrollback above it
+    pub fn capture_pane(&self, target_pane: &str, scrollback: usize) -> process::Output {
+        let start = format!("-{scrollback}");
+        self.execute_tmux_command(&["capture-pane", "-ep", "-S", &start, "-t", target_pane])
     }
 
-    pub fn set_up_tmux_env(&self, repo: &Repository, repo_name: &str) -> Result<()> {
-        if repo.is_bare() && repo.head().is_ok() {
+    /// Build the windows of a layout template in a newly created session, in place of the window it
+    /// was created with. Relative paths are relative to the project's directory
+    pub fn create_layout(
+        &self,
+        session_name: &str,
+        project_path: &Path,
+        windows: &[Window],
+    ) -> Result<()> {
+        let default_window = self.list_windows("#{window_id}", Some(session_name));
+        let mut first_window = None;
+        let repo = Repository::open(project_path).ok();
+
+        for window in windows {
+            if let Some(window_id) =
+                self.create_layout_window(session_name, project_path, repo.as_ref(), window)?
+            {
+                first_window.get_or_insert(window_id);
+            }
+        }
+
+        if let Some(first_window) = first_window {
+            for window in default_window.lines() {
+                self.kill_window(window);
+            }
+            self.select_window(&first_window);
+        }
+        Ok(())
+    }
+
+    /// Add the named windows of a layout template that are missing from a running session, like
+    /// ones that were closed since it was created. Returns how many were added
+    pub fn restore_layout(
+        &self,
+        session_name: &str,
+        project_path: &Path,
+        windows: &[Window],
+    ) -> Result<usize> {
+        let existing = self.list_windows("#{window_name}", Some(session_name));
+        let existing: Vec<&str> = existing.lines().collect();
+        let repo = Repository::open(project_path).ok();
+        let mut added = 0;
+        for window in windows {
+            // Unnamed windows can't be told apart from ones the user opened
+            let Some(name) = &window.name else {
+                continue;
+            };
+            if !existing.contains(&name.as_str())
+                && self
+                    .create_layout_window(session_name, project_path, repo.as_ref(), window)?
+                    .is_some()
+            {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Create a window of a layout template with its panes in the background, returning its id.
+    /// Windows in a directory left out of the project's sparse checkout aren't created, and panes
+    /// in one open in the window's directory instead
+    fn create_layout_window(
+        &self,
+        session_name: &str,
+        project_path: &Path,
+        repo: Option<&Repository>,
+        window: &Window,
+    ) -> Result<Option<String>> {
+        let left_out = |path: &str| repo.is_some_and(|repo| is_excluded(repo, Path::new(path)));
+        let panes = window.panes.as_deref().unwrap_or_default();
+        let window_path = layout_path(window.path.as_deref(), project_path)?;
+        if left_out(&window_path) {
+            return Ok(None);
+        }
+        let pane_path = |pane: &Pane| -> Result<String> {
+            let path = match &pane.path {
+                Some(_) => layout_path(pane.path.as_deref(), project_path)?,
+                None => window_path.clone(),
+            };
+            Ok(if left_out(&path) {
+                window_path.clone()
+            } else {
+                path
+            })
+        };
+        let first_pane_path = match panes.first() {
+            Some(pane) => pane_path(pane)?,
+            None => window_path.clone(),
+        };
+
+        let target = format!("{session_name}:");
+        let mut args = vec!["new-window", "-d", "-P", "-F", "#{window_id} #{pane_id}"];
+        args.extend(["-t", &target, "-c", &first_pane_path]);
+        if let Some(name) = &window.name {
+            args.extend(["-n", name]);
+        }
+        let output = Tmux::stdout_to_string(self.execute_tmux_command(&args));
+        let Some((window_id, first_pane)) = output.trim().split_once(' ') else {
+            return Err(TmsError::IoError)
+                .attach_printable(format!("Could not create a window in {session_name}"));
+        };
+
+        let mut pane_ids = vec![first_pane.to_owned()];
+        for pane in panes.iter().skip(1) {
+            let pane_path = pane_path(pane)?;
+            let output = self.execute_tmux_command(&[
+                "split-window",
+                "-P",
+                "-F",
+                "#{pane_id}",
+                "-t",
+                first_pane,
+                "-c",
+                &pane_path,
+            ]);
+            pane_ids.push(Tmux::stdout_to_string(output).trim().to_owned());
+        }
+        if let Some(layout) = &window.layout {
+            self.execute_tmux_command(&["select-layout", "-t", window_id, layout]);
+        }
+
+        if let Some(command) = &window.command {
+            self.send_keys(command, Some(first_pane));
+        }
+        for (pane, pane_id) in panes.iter().zip(&pane_ids) {
+            if let Some(command) = &pane.command {
+                self.send_keys(command, Some(pane_id));
+            }
+        }
+        Ok(Some(window_id.to_owned()))
+    }
+
+    pub fn set_up_tmux_env(
+        &self,
+        repo: &Repository,
+        repo_name: &str,
+        config: &Config,
+    ) -> Result<()> {
+        if repo.is_bare() && repo.head().is_ok() && config.worktree_windows(repo_name) {
             if repo
                 .worktrees()
                 .change_context(TmsError::GitError)?
@@ -266,26 +671,221 @@ impl Tmux {
                 )
                 .change_context(TmsError::GitError)?;
             }
-            for tree in repo.worktrees().change_context(TmsError::GitError)?.iter() {
-                let tree = tree.ok_or(TmsError::NonUtf8Path).attach_printable(format!(
-                    "The path to the found sub-tree {tree:?} has a non-utf8 path",
-                ))?;
-                let window_name = tree.to_string();
+            let mut trees = repo
+                .worktrees()
+                .change_context(TmsError::GitError)?
+                .iter()
+                .map(|tree| {
+                    tree.map(str::to_owned)
+                        .ok_or(TmsError::NonUtf8Path)
+                        .attach_printable(format!(
+                            "The path to the found sub-tree {tree:?} has a non-utf8 path",
+                        ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Worktrees whose directory is gone have nothing to open a window in
+            trees.retain(|tree| {
+                repo.find_worktree(tree)
+                    .is_ok_and(|worktree| worktree.validate().is_ok())
+            });
+            if trees.len() > 1 && config.pick_worktrees(repo_name) {
+                trees = self.pick_worktrees(repo, trees, config)?;
+            }
+            for tree in &trees {
                 let path_to_tree = repo
                     .find_worktree(tree)
                     .change_context(TmsError::GitError)?
                     .path()
                     .to_string()?;
 
-                self.new_window(Some(&window_name), Some(&path_to_tree), Some(repo_name));
+                self.new_window(Some(tree), Some(&path_to_tree), Some(repo_name));
+            }
+            // Kill that first extra window, unless it is the only one left
+            if !trees.is_empty() {
+                self.kill_window(&format!("{repo_name}:^"));
             }
-            // Kill that first extra window
-            self.kill_window(&format!("{repo_name}:^"));
         }
         Ok(())
     }
+
+    /// Let the user choose which of the worktrees to open, starting with the previous choice
+    fn pick_worktrees(
+        &self,
+        repo: &Repository,
+        trees: Vec<String>,
+        config: &Config,
+    ) -> Result<Vec<String>> {
+        let key = repo.path().to_string()?;
+        let previous = State::load()?
+            .worktree_selections
+            .get(&key)
+            .map(|selection| {
+                selection
+                    .iter()
+                    .filter(|tree| trees.contains(tree))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        // Without a terminal, like in `run-shell`, the previous choice is kept or else every
+        // worktree is opened
+        if !has_terminal() {
+            return Ok(if previous.is_empty() { trees } else { previous });
+        }
+        let Some(picked) = Picker::new(&trees, Preview::None, config.shortcuts.as_ref(), self)
+            .set_colors(config.picker_colors.as_ref())
+            .set_color_mode(config.color)
+            .set_explicit_search_mode(config.explicit_search_mode == Some(true))
+            .set_multi_select(&previous)
+            .run_multi()?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let picked = trees
+            .into_iter()
+            .filter(|tree| picked.contains(tree))
+            .collect::<Vec<_>>();
+        State::update(|state| state.worktree_selections.insert(key, picked.clone()))?;
+
+        Ok(picked)
+    }
+}
+
+/// The process running a hook, through `sh` so the hook can use everything the shell offers
+#[cfg(not(feature = "no-external-tools"))]
+pub(crate) fn hook_command(command: &str) -> Result<process::Command> {
+    let mut hook = process::Command::new("sh");
+    hook.args(["-c", command]);
+    Ok(hook)
+}
+
+/// The process running a hook, without a shell the hook is only split into a program and its
+/// arguments
+#[cfg(feature = "no-external-tools")]
+pub(crate) fn hook_command(command: &str) -> Result<process::Command> {
+    let words = shell_words::split(command)
+        .change_context(TmsError::ConfigError)
+        .attach_printable_lazy(|| format!("Could not parse the hook `{command}`"))?;
+    let (program, args) = words
+        .split_first()
+        .ok_or(TmsError::ConfigError)
+        .attach_printable("A hook is empty")?;
+    let mut hook = process::Command::new(program);
+    hook.args(args);
+    Ok(hook)
+}
+
+/// The tmux program, which on Windows runs in WSL and is started through `wsl`
+fn tmux_program() -> process::Command {
+    if cfg!(windows) {
+        let mut command = process::Command::new("wsl");
+        command.arg("tmux");
+        command
+    } else {
+        process::Command::new("tmux")
+    }
 }
 
-fn is_in_tmux_session() -> bool {
-    std::env::var("TERM_PROGRAM").is_ok_and(|program| program == "tmux")
+/// The path as tmux knows it, which on Windows is the one in WSL
+fn tmux_path(path: &str) -> String {
+    if cfg!(windows) {
+        if let Some(path) = wsl_path(path) {
+            return path;
+        }
+    }
+    path.to_owned()
+}
+
+/// The path WSL knows a Windows path by, none when it isn't one. Drives are mounted under `/mnt`,
+/// and `\\wsl$\<distro>` or `\\wsl.localhost\<distro>` is the root of WSL's own file system
+fn wsl_path(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    if let (Some(drive), Some(':'), Some('\\' | '/')) = (chars.next(), chars.next(), chars.next()) {
+        if drive.is_ascii_alphabetic() {
+            let rest = chars.as_str().replace('\\', "/");
+            return Some(format!("/mnt/{}/{rest}", drive.to_ascii_lowercase()));
+        }
+    }
+    let rest = path
+        .strip_prefix(r"\\wsl$\")
+        .or_else(|| path.strip_prefix(r"\\wsl.localhost\"))?;
+    let (_distro, rest) = rest.split_once('\\').unwrap_or((rest, ""));
+    Some(format!("/{}", rest.replace('\\', "/")))
+}
+
+/// The terminal device tms reads its input from, none when it isn't run in a terminal
+#[cfg(unix)]
+fn terminal_name() -> Option<String> {
+    let mut name = [0 as libc::c_char; 256];
+    // `ttyname_r` writes a nul terminated name into the buffer, or fails without touching it
+    let failed = unsafe { libc::ttyname_r(libc::STDIN_FILENO, name.as_mut_ptr(), name.len()) };
+    if failed != 0 {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+    name.to_str().ok().map(str::to_owned)
+}
+
+/// Without ttys, a terminal can't be told apart from the panes of tmux in WSL
+#[cfg(not(unix))]
+fn terminal_name() -> Option<String> {
+    None
+}
+
+/// The absolute path for a path in a layout template, expanding `~` and variables and resolving it
+/// relative to the project's directory
+fn layout_path(path: Option<&str>, project_path: &Path) -> Result<String> {
+    let Some(path) = path else {
+        return project_path.to_string();
+    };
+    let expanded = shellexpand::full(path).change_context(TmsError::IoError)?;
+    project_path.join(expanded.as_ref()).to_string()
+}
+
+/// The name tmux uses for a color
+fn tmux_color(color: Color) -> String {
+    match color {
+        Color::Reset => "default".to_owned(),
+        Color::Black => "black".to_owned(),
+        Color::Red => "red".to_owned(),
+        Color::Green => "green".to_owned(),
+        Color::Yellow => "yellow".to_owned(),
+        Color::Blue => "blue".to_owned(),
+        Color::Magenta => "magenta".to_owned(),
+        Color::Cyan => "cyan".to_owned(),
+        Color::Gray => "white".to_owned(),
+        Color::DarkGray => "brightblack".to_owned(),
+        Color::LightRed => "brightred".to_owned(),
+        Color::LightGreen => "brightgreen".to_owned(),
+        Color::LightYellow => "brightyellow".to_owned(),
+        Color::LightBlue => "brightblue".to_owned(),
+        Color::LightMagenta => "brightmagenta".to_owned(),
+        Color::LightCyan => "brightcyan".to_owned(),
+        Color::White => "brightwhite".to_owned(),
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::Indexed(index) => format!("colour{index}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_windows_paths_to_wsl_ones() {
+        assert_eq!(
+            wsl_path(r"C:\Users\me\code").as_deref(),
+            Some("/mnt/c/Users/me/code")
+        );
+        assert_eq!(wsl_path("d:/notes").as_deref(), Some("/mnt/d/notes"));
+        assert_eq!(
+            wsl_path(r"\\wsl$\Ubuntu\home\me").as_deref(),
+            Some("/home/me")
+        );
+        assert_eq!(wsl_path(r"\\wsl.localhost\Ubuntu").as_deref(), Some("/"));
+        assert_eq!(wsl_path("/home/me"), None);
+        assert_eq!(wsl_path("api:1"), None);
+    }
 }
@@ -3,8 +3,12 @@ use pretty_assertions::assert_eq;
 use ratatui::style::Color;
 use std::{fs, str::FromStr};
 use tempfile::tempdir;
-use tms::configs::{
-    CloneRepoSwitchConfig, Config, PickerColorConfig, SearchDirectory, SessionSortOrderConfig,
+use tms::{
+    columns::PickerColumn,
+    configs::{
+        CloneRepoSwitchConfig, Config, PickerColorConfig, SearchDirectory, SessionSortOrderConfig,
+    },
+    forge::{CloneProtocol, Forge},
 };
 
 #[test]
@@ -40,6 +44,7 @@ fn tms_config() -> anyhow::Result<()> {
     let picker_border_color = Color::from_str("#cccccc")?;
     let picker_info_color = Color::from_str("green")?;
     let picker_prompt_color = Color::from_str("#eeeeee")?;
+    let picker_error_color = Color::from_str("red")?;
 
     let expected_config = Config {
         default_session: Some(default_session.clone()),
@@ -48,6 +53,7 @@ fn tms_config() -> anyhow::Result<()> {
         recursive_submodules: Some(false),
         switch_filter_unknown: Some(false),
         session_sort_order: Some(SessionSortOrderConfig::Alphabetical),
+        session_sort_weights: None,
         excluded_dirs: Some(vec![excluded_dir.clone()]),
         search_paths: None,
         search_dirs: Some(vec![SearchDirectory::new(
@@ -61,12 +67,43 @@ fn tms_config() -> anyhow::Result<()> {
             border_color: Some(picker_border_color),
             info_color: Some(picker_info_color),
             prompt_color: Some(picker_prompt_color),
+            error_color: Some(picker_error_color),
         }),
         shortcuts: None,
         bookmarks: None,
         session_configs: None,
         marks: None,
         clone_repo_switch: Some(CloneRepoSwitchConfig::Always),
+        color: None,
+        explicit_search_mode: Some(true),
+        worktree_windows: Some(false),
+        pick_worktrees: Some(true),
+        project_colors: Some(true),
+        session_aliases: None,
+        worktrees_dir: Some("/tmp/worktrees".into()),
+        team_config: Some("https://example.com/tms.toml".into()),
+        skip_hidden_dirs: Some(true),
+        respect_gitignore: Some(false),
+        hooks: None,
+        auto_kill_stale: Some("30d".into()),
+        protected_sessions: Some(vec!["notes".into(), "music".into()]),
+        event_log: Some(true),
+        remotes: None,
+        clone_protocol: Some(CloneProtocol::Ssh),
+        forges: Some(vec![Forge::Github, Forge::Gitlab]),
+        workspace_dirs: Some(vec!["~/courses".into()]),
+        picker_columns: Some(vec![PickerColumn::Branch, PickerColumn::AheadBehind]),
+        init_repo_branch: Some("main".into()),
+        picker_sort: Some("recency desc, name".into()),
+        profile: None,
+        profiles: None,
+        preview_scrollback: Some(200),
+        confirm_destructive_actions: Some(false),
+        include: None,
+        templates: None,
+        show_git_status: Some(true),
+        merge_search_dirs: Some(true),
+        match_paths: Some(true),
     };
 
     let mut tms = Command::cargo_bin("tms")?;
@@ -102,8 +139,57 @@ fn tms_config() -> anyhow::Result<()> {
             &picker_info_color.to_string(),
             "--picker-prompt-color",
             &picker_prompt_color.to_string(),
+            "--picker-error-color",
+            &picker_error_color.to_string(),
             "--clone-repo-switch",
             "Always",
+            "--explicit-search-mode",
+            "true",
+            "--worktree-windows",
+            "false",
+            "--pick-worktrees",
+            "true",
+            "--project-colors",
+            "true",
+            "--worktrees-dir",
+            "/tmp/worktrees",
+            "--team-config",
+            "https://example.com/tms.toml",
+            "--skip-hidden-dirs",
+            "true",
+            "--respect-gitignore",
+            "false",
+            "--auto-kill-stale",
+            "30d",
+            "--protected-sessions",
+            "notes",
+            "music",
+            "--event-log",
+            "true",
+            "--clone-protocol",
+            "ssh",
+            "--forges",
+            "github",
+            "gitlab",
+            "--workspace-dirs",
+            "~/courses",
+            "--picker-columns",
+            "branch",
+            "ahead_behind",
+            "--init-repo-branch",
+            "main",
+            "--picker-sort",
+            "recency desc, name",
+            "--preview-scrollback",
+            "200",
+            "--confirm-destructive-actions",
+            "false",
+            "--show-git-status",
+            "true",
+            "--merge-search-dirs",
+            "true",
+            "--match-paths",
+            "true",
         ]);
 
     tms.assert().success().code(0);
@@ -117,3 +203,417 @@ fn tms_config() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn tms_config_schema() -> anyhow::Result<()> {
+    let mut tms = Command::cargo_bin("tms")?;
+    let output = tms.args(["config", "schema"]).output()?;
+    assert!(output.status.success());
+
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let properties = &schema["properties"];
+    assert_eq!(properties["search_dirs"]["type"], "array");
+    assert_eq!(
+        properties["clone_protocol"]["$ref"],
+        "#/definitions/CloneProtocol"
+    );
+    assert_eq!(schema["definitions"]["PickerAction"]["enum"][1], "cancel");
+
+    Ok(())
+}
+
+#[test]
+fn tms_cd_gets_the_directory_from_the_cwd_file() -> anyhow::Result<()> {
+    let mut tms = Command::cargo_bin("tms")?;
+    let output = tms.args(["shell-init", "bash"]).output()?;
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout)?;
+    assert!(script.contains("tms_cd() {"));
+    assert!(script.contains("tms --cwd-file \"$cwd_file\" \"$@\""));
+
+    let directory = tempdir()?;
+    let project = directory.path().join("project");
+    fs::create_dir(&project)?;
+    let cwd_file = directory.path().join("cwd");
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", directory.path().join("config.toml"))
+        .arg(&project)
+        .arg("--cwd-file")
+        .arg(&cwd_file);
+
+    tms.assert().success();
+    assert_eq!(
+        fs::read_to_string(&cwd_file)?,
+        project.to_string_lossy().into_owned()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tms_list_projects() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let projects = directory.path().join("projects");
+    for project in ["beta", "alpha"] {
+        git2::Repository::init(projects.join(project))?;
+    }
+    let projects = projects.to_string_lossy().into_owned();
+    fs::write(
+        &config_file_path,
+        format!("[[search_dirs]]\npath = \"{projects}\"\ndepth = 1\n"),
+    )?;
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .env("XDG_CACHE_HOME", directory.path().join("cache"))
+        .arg("list-projects");
+
+    tms.assert().success().stdout("alpha\nbeta\n");
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .env("XDG_CACHE_HOME", directory.path().join("cache"))
+        .args(["list-projects", "--with-path"]);
+
+    tms.assert()
+        .success()
+        .stdout(format!("alpha\t{projects}/alpha\nbeta\t{projects}/beta\n"));
+
+    let mut tms = Command::cargo_bin("tms")?;
+    let output = tms
+        .env("TMS_CONFIG_FILE", &config_file_path)
+        .env("XDG_CACHE_HOME", directory.path().join("cache"))
+        .env("TMS_TMUX_SOCKET", "tms-test-no-server")
+        .args(["--output", "json", "list-projects"])
+        .output()?;
+    assert!(output.status.success());
+    let listed: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        listed,
+        serde_json::json!([
+            {
+                "name": "alpha",
+                "path": format!("{projects}/alpha"),
+                "type": "repository",
+                "running_session": null,
+            },
+            {
+                "name": "beta",
+                "path": format!("{projects}/beta"),
+                "type": "repository",
+                "running_session": null,
+            },
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tms_set_goes_before_or_after_the_subcommand() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let projects = directory.path().join("projects");
+    git2::Repository::init(projects.join("alpha"))?;
+    let projects = projects.to_string_lossy().into_owned();
+    let search_dirs = format!("search_dirs=[{{ path = \"{projects}\", depth = 1 }}]");
+
+    for args in [
+        ["--set", &search_dirs, "list-projects"],
+        ["list-projects", "--set", &search_dirs],
+    ] {
+        let mut tms = Command::cargo_bin("tms")?;
+        tms.env("TMS_CONFIG_FILE", &config_file_path)
+            .env("XDG_CACHE_HOME", directory.path().join("cache"))
+            .args(args);
+
+        tms.assert().success().stdout("alpha\n");
+    }
+
+    // `switch` is taken as the subcommand, not as the path of a project
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .env("TMS_TMUX_SOCKET", "tms-test-no-server")
+        .args(["--set", &search_dirs, "--color", "never", "switch"]);
+
+    tms.assert()
+        .failure()
+        .stderr(predicates::str::contains("No tmux server is running"));
+
+    Ok(())
+}
+
+#[test]
+fn tms_list_projects_searches_nested_search_dirs_to_their_own_depth() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let outer = directory.path().join("a");
+    git2::Repository::init(outer.join("b/c/repo"))?;
+    let outer = outer.to_string_lossy().into_owned();
+    fs::write(
+        &config_file_path,
+        format!(
+            "[[search_dirs]]\npath = \"{outer}\"\ndepth = 5\n\n[[search_dirs]]\npath = \"{outer}/b\"\ndepth = 1\n"
+        ),
+    )?;
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .env("XDG_CACHE_HOME", directory.path().join("cache"))
+        .arg("list-projects");
+
+    // The overlap is pointed out on stderr, leaving the list alone
+    tms.assert()
+        .success()
+        .stdout("repo\n")
+        .stderr(predicates::str::contains(format!(
+            "Warning: the search directory {outer}/b is inside {outer}"
+        )));
+
+    Ok(())
+}
+
+#[test]
+fn tms_profile_restricts_the_projects() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let projects = directory.path().join("projects");
+    git2::Repository::init(projects.join("dotfiles"))?;
+    for bookmark in ["client", "notes"] {
+        fs::create_dir(directory.path().join(bookmark))?;
+    }
+    fs::write(
+        &config_file_path,
+        format!(
+            "[[search_dirs]]\npath = \"{}\"\ndepth = 1\n",
+            projects.to_string_lossy()
+        ),
+    )?;
+    let tms = || -> anyhow::Result<Command> {
+        let mut tms = Command::cargo_bin("tms")?;
+        tms.env("TMS_CONFIG_FILE", &config_file_path)
+            .env("XDG_CACHE_HOME", directory.path().join("cache"))
+            .env_remove("TMS_PROFILE");
+        Ok(tms)
+    };
+
+    tms()?
+        .args(["bookmark", "--group", "work"])
+        .arg(directory.path().join("client"))
+        .assert()
+        .success();
+    tms()?
+        .arg("bookmark")
+        .arg(directory.path().join("notes"))
+        .assert()
+        .success();
+
+    tms()?
+        .arg("list-projects")
+        .assert()
+        .success()
+        .stdout("client\ndotfiles\nnotes\n");
+    tms()?
+        .args(["list-projects", "--profile", "work"])
+        .assert()
+        .success()
+        .stdout("client\ndotfiles\n");
+    tms()?
+        .env("TMS_PROFILE", "work")
+        .arg("list-projects")
+        .assert()
+        .success()
+        .stdout("client\ndotfiles\n");
+    tms()?
+        .args(["list-projects", "--profile", "home"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("There is no profile named home"));
+
+    Ok(())
+}
+
+#[test]
+fn tms_layers_included_and_profile_files() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let projects = directory.path().join("projects");
+    git2::Repository::init(projects.join("dotfiles"))?;
+    for bookmark in ["shared", "mine", "client", "extra"] {
+        fs::create_dir(directory.path().join(bookmark))?;
+    }
+    let path = |name: &str| directory.path().join(name).to_string_lossy().into_owned();
+    fs::write(
+        directory.path().join("base.toml"),
+        format!(
+            "bookmarks = [\"{}\"]\n[[search_dirs]]\npath = \"{}\"\ndepth = 1\n",
+            path("shared"),
+            path("projects")
+        ),
+    )?;
+    fs::write(
+        &config_file_path,
+        format!(
+            "include = [\"base.toml\"]\nbookmarks = [\"{}\"]\n",
+            path("mine")
+        ),
+    )?;
+    fs::write(
+        directory.path().join("work.toml"),
+        format!("bookmarks = [\"{}\"]\n", path("client")),
+    )?;
+    let tms = || -> anyhow::Result<Command> {
+        let mut tms = Command::cargo_bin("tms")?;
+        tms.env("TMS_CONFIG_FILE", &config_file_path)
+            .env("XDG_CACHE_HOME", directory.path().join("cache"))
+            .env_remove("TMS_PROFILE");
+        Ok(tms)
+    };
+
+    tms()?
+        .arg("list-projects")
+        .assert()
+        .success()
+        .stdout("dotfiles\nmine\n");
+    tms()?
+        .env("TMS_PROFILE", "work")
+        .arg("list-projects")
+        .assert()
+        .success()
+        .stdout("client\ndotfiles\n");
+    tms()?
+        .env("TMS_PROFILE", "home")
+        .arg("list-projects")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("There is no profile named home"));
+
+    tms()?
+        .env("TMS_PROFILE", "work")
+        .args(["bookmark", &path("extra")])
+        .assert()
+        .success();
+    let stored: Config = toml::from_str(&fs::read_to_string(&config_file_path)?)?;
+    assert_eq!(stored.include, Some(vec!["base.toml".into()]));
+    assert_eq!(stored.bookmarks, Some(vec![path("mine"), path("extra")]));
+    assert_eq!(stored.search_dirs, None);
+
+    Ok(())
+}
+
+#[test]
+fn tms_export_and_import_move_the_paths() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let path = |name: &str| directory.path().join(name).to_string_lossy().into_owned();
+    let exported_config = directory.path().join("exported.toml");
+    fs::write(
+        &exported_config,
+        format!(
+            "bookmarks = [\"{}\"]\n[marks]\n0 = \"{}\"\n",
+            path("old-home/notes"),
+            path("disk/code/api")
+        ),
+    )?;
+    // The active profile's overrides aren't part of the exported config file
+    fs::write(
+        directory.path().join("work.toml"),
+        "bookmarks = [\"/work/notes\"]\n",
+    )?;
+    let export_file = directory.path().join("setup.toml");
+    Command::cargo_bin("tms")?
+        .env("TMS_CONFIG_FILE", &exported_config)
+        .env("HOME", path("old-home"))
+        .env("TMS_PROFILE", "work")
+        .arg("export")
+        .arg(&export_file)
+        .assert()
+        .success();
+
+    let config_file_path = directory.path().join("config.toml");
+    fs::write(&config_file_path, "bookmarks = [\"~/old\"]\n")?;
+    Command::cargo_bin("tms")?
+        .env("TMS_CONFIG_FILE", &config_file_path)
+        .env("HOME", path("new-home"))
+        .env_remove("TMS_PROFILE")
+        .arg("import")
+        .arg(&export_file)
+        .args(["--map", &format!("{}={}", path("disk"), path("mnt"))])
+        .assert()
+        .success();
+
+    let imported: Config = toml::from_str(&fs::read_to_string(&config_file_path)?)?;
+    assert_eq!(imported.bookmarks, Some(vec![path("new-home/notes")]));
+    assert_eq!(
+        imported.marks.unwrap_or_default().get("0"),
+        Some(&path("mnt/code/api"))
+    );
+    let backup = fs::read_to_string(directory.path().join("config.toml.bak"))?;
+    assert_eq!(backup, "bookmarks = [\"~/old\"]\n");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn tms_list_projects_follows_symlinks_once() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let projects = directory.path().join("projects");
+    git2::Repository::init(projects.join("src/example.com/app"))?;
+    git2::Repository::init(directory.path().join("elsewhere/tool"))?;
+    std::os::unix::fs::symlink(directory.path().join("elsewhere"), projects.join("linked"))?;
+    std::os::unix::fs::symlink(&projects, projects.join("src/loop"))?;
+    std::os::unix::fs::symlink(projects.join("src"), projects.join("src-again"))?;
+    let projects = projects.to_string_lossy().into_owned();
+    fs::write(
+        &config_file_path,
+        format!("[[search_dirs]]\npath = \"{projects}\"\ndepth = 10\n"),
+    )?;
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .env("XDG_CACHE_HOME", directory.path().join("cache"))
+        .arg("list-projects");
+
+    tms.assert().success().stdout("app\ntool\n");
+
+    Ok(())
+}
+
+#[test]
+fn tms_bookmark_from_stdin() -> anyhow::Result<()> {
+    let directory = tempdir()?;
+    let config_file_path = directory.path().join("config.toml");
+    let project = directory.path().join("project");
+    fs::create_dir(&project)?;
+    let project = project.to_string_lossy().into_owned();
+    let list = format!("# projects\n{project}\n{project}/\n\n{project}/missing\n");
+
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.env("TMS_CONFIG_FILE", &config_file_path)
+        .args(["bookmark", "--from-file", "-"])
+        .write_stdin(list);
+
+    tms.assert()
+        .success()
+        .stdout(predicates::str::contains("Added 1 bookmarks, skipped 2"));
+
+    let config: Config = toml::from_str(&fs::read_to_string(&config_file_path)?)?;
+    assert_eq!(config.bookmarks, Some(vec![project]));
+
+    Ok(())
+}
+
+#[test]
+fn tms_sessions_rejects_a_zero_interval() -> anyhow::Result<()> {
+    let mut tms = Command::cargo_bin("tms")?;
+    tms.args(["sessions", "--watch", "--interval", "0"]);
+
+    tms.assert()
+        .failure()
+        .stderr(predicates::str::contains("0 is not in 1.."));
+
+    Ok(())
+}